@@ -1,4 +1,4 @@
-use openai_api_rs::request::chat_completion::{ChatMessage, ChatRole};
+use openai_api_rs::chat;
 use openai_api_rs::request::{ChatCompletionRequest, EditRequest, Request};
 use openai_api_rs::{client::Client, request::TextCompletionRequest};
 
@@ -20,8 +20,8 @@ async fn main() {
         .unwrap();
 
     // Init the completion request for this model and configure it
-    let completion_request = TextCompletionRequest::init(&text_davinci_model)
-        .with_prompt(vec!["This is a test".to_string()]);
+    let completion_request =
+        TextCompletionRequest::init(&text_davinci_model).with_prompt("This is a test".into());
 
     // Request the completion
     let completion = completion_request.execute().await.unwrap();
@@ -32,8 +32,8 @@ async fn main() {
     let gpt35_turbo_model = client.retrieve_model_info("gpt-3.5-turbo").await.unwrap();
 
     // Init the completion request for this model and configure it
-    let completion_request = TextCompletionRequest::init(&gpt35_turbo_model)
-        .with_prompt(vec!["This is a test".to_string()]);
+    let completion_request =
+        TextCompletionRequest::init(&gpt35_turbo_model).with_prompt("This is a test".into());
 
     // Request the completion, expecting an error since this model is not supposed to be compatible
     // with completions
@@ -43,13 +43,8 @@ async fn main() {
     };
 
     // Init the chat completion request for this model and configure it
-    let chat_completion_request = ChatCompletionRequest::init(
-        &gpt35_turbo_model,
-        vec![ChatMessage {
-            role: ChatRole::User,
-            content: "Hello, how are you?".to_string(),
-        }],
-    );
+    let chat_completion_request =
+        ChatCompletionRequest::init(&gpt35_turbo_model, chat![user: "Hello, how are you?"]);
 
     // Request the chat completion
     let response = chat_completion_request.execute().await.unwrap();
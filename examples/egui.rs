@@ -77,7 +77,7 @@ impl<'client> eframe::App for App<'client> {
 
                 if ui.button("Create a completion ->").clicked() {
                     let request = TextCompletionRequest::init(&self.text_davinci_model)
-                        .with_prompt(vec![self.text.clone()]);
+                        .with_prompt(self.text.clone().into());
                     let completion = request
                         .execute_blocking()
                         .expect("Failed to create completion");
@@ -6,13 +6,20 @@ use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::{format_ident, ToTokens};
 use syn::{
     parse::Parser, parse_quote, punctuated::Punctuated, Data, DataStruct, DeriveInput, Expr,
-    ExprLit, Field, Lit, LitStr, Meta, MetaList, Token,
+    ExprLit, Field, Lit, LitStr, Meta, MetaList, Path, Token,
 };
 
+mod tool;
+
 struct SubstructData {
     doc: TokenStream2,
     url: LitStr,
-    compatible_models: Vec<LitStr>,
+    compatible_models: Vec<Expr>,
+    beta: Option<LitStr>,
+    validate: Option<Path>,
+    transform_json: Option<Path>,
+    moderation_input: Option<Path>,
+    output_tokens: Option<Path>,
 }
 
 impl Default for SubstructData {
@@ -21,6 +28,11 @@ impl Default for SubstructData {
             doc: TokenStream2::new(),
             url: LitStr::new("", Span::call_site()),
             compatible_models: Vec::new(),
+            beta: None,
+            validate: None,
+            transform_json: None,
+            moderation_input: None,
+            output_tokens: None,
         }
     }
 }
@@ -30,14 +42,40 @@ pub fn rq(attr: TokenStream, input: TokenStream) -> TokenStream {
     rq_impl(attr, input).unwrap()
 }
 
+#[proc_macro_derive(OpenAiTool, attributes(tool))]
+pub fn openai_tool(input: TokenStream) -> TokenStream {
+    tool::openai_tool_impl(input).unwrap()
+}
+
 fn rq_impl(attr: TokenStream, input: TokenStream) -> miette::Result<TokenStream> {
     let DeriveInput { data: Data::Struct(DataStruct {fields, ..}), .. } = syn::parse(input).into_diagnostic()? else {
         panic!("Expected a struct");
     };
 
     let parser = Punctuated::<MetaList, Token![,]>::parse_separated_nonempty;
-    let substructs_names_docs = parser.parse(attr).into_diagnostic()?;
-    let substructs_names_docs = substructs_names_docs
+    let (crate_path_metas, substructs_metas): (Vec<_>, Vec<_>) = parser
+        .parse(attr)
+        .into_diagnostic()?
+        .into_iter()
+        .partition(|meta| meta.path.is_ident("crate_path"));
+
+    assert!(
+        crate_path_metas.len() <= 1,
+        "Expected at most one crate_path(...) tag"
+    );
+
+    let crate_path: Path = crate_path_metas
+        .first()
+        .map(|meta| {
+            meta.parse_args::<LitStr>()
+                .expect("Couldn't parse the crate_path")
+                .parse()
+                .expect("Expected crate_path to be a valid path")
+        })
+        .unwrap_or_else(|| parse_quote!(crate));
+    let crate_path_str = crate_path.to_token_stream().to_string().replace(' ', "");
+
+    let substructs_names_docs = substructs_metas
         .iter()
         .map(|meta| {
             let name = meta.path.get_ident().expect("Expected an identifier");
@@ -54,12 +92,33 @@ fn rq_impl(attr: TokenStream, input: TokenStream) -> miette::Result<TokenStream>
                     data.url = tag.parse_args::<LitStr>().expect("Couldn't parse the url");
                 } else if tag.path.is_ident("compatible_models") {
                     let models = tag
-                        .parse_args_with(Punctuated::<LitStr, Token![,]>::parse_terminated)
+                        .parse_args_with(Punctuated::<Expr, Token![,]>::parse_terminated)
                         .expect("Couldn't parse the compatible_models");
 
                     data.compatible_models = models.into_iter().collect();
+                } else if tag.path.is_ident("beta") {
+                    data.beta = Some(tag.parse_args::<LitStr>().expect("Couldn't parse the beta"));
+                } else if tag.path.is_ident("validate") {
+                    data.validate = Some(tag.parse_args::<Path>().expect("Couldn't parse the validate"));
+                } else if tag.path.is_ident("transform_json") {
+                    data.transform_json = Some(
+                        tag.parse_args::<Path>()
+                            .expect("Couldn't parse the transform_json"),
+                    );
+                } else if tag.path.is_ident("moderation_input") {
+                    data.moderation_input = Some(
+                        tag.parse_args::<Path>()
+                            .expect("Couldn't parse the moderation_input"),
+                    );
+                } else if tag.path.is_ident("output_tokens") {
+                    data.output_tokens = Some(
+                        tag.parse_args::<Path>()
+                            .expect("Couldn't parse the output_tokens"),
+                    );
                 } else {
-                    panic!("Expected on of these tags: ['doc', 'url', 'compatible_models']");
+                    panic!(
+                        "Expected on of these tags: ['doc', 'url', 'compatible_models', 'beta', 'validate', 'transform_json', 'moderation_input', 'output_tokens']"
+                    );
                 }
 
                 data
@@ -152,11 +211,11 @@ fn rq_impl(attr: TokenStream, input: TokenStream) -> miette::Result<TokenStream>
                 {
                     substructs_names_docs
                         .iter()
-                        .for_each(|(&ref substruct_name, _)| {
+                        .for_each(|(substruct_name, _)| {
                             let field = field.clone();
 
                             substructs_fields
-                                .entry(substruct_name.clone())
+                                .entry((*substruct_name).clone())
                                 .or_insert(Vec::new())
                                 .push((field, all_req));
                         });
@@ -178,10 +237,10 @@ fn rq_impl(attr: TokenStream, input: TokenStream) -> miette::Result<TokenStream>
                 substructs_fields
             });
 
-    let substructs = substructs_names_docs.iter().map(|(substruct_name, SubstructData { doc, url, compatible_models })| {
+    let substructs = substructs_names_docs.iter().map(|(substruct_name, SubstructData { doc, url, compatible_models, beta, validate, transform_json, moderation_input, output_tokens })| {
         let actual_substruct_name = format_ident!("{substruct_name}Request");
 
-        let fields = substructs_fields.get(&substruct_name).expect("Couldn't find the substruct fields");
+        let fields = substructs_fields.get(substruct_name).expect("Couldn't find the substruct fields");
 
         let fields_tokens = fields.iter().map(|(f, _)| quote::quote!(#f));
 
@@ -203,19 +262,59 @@ fn rq_impl(attr: TokenStream, input: TokenStream) -> miette::Result<TokenStream>
             let ty = &f.ty;
 
             quote::quote!(#ident: #ty)
-        });
+        }).collect::<Vec<_>>();
         let init_default_vals = non_required_fields.iter().map(|f| {
             let name = f.ident.as_ref().expect("Expected a named field");
 
             quote::quote! { #name: Default::default() }
-        });
-
+        }).collect::<Vec<_>>();
+
+        let init_doc = format!(
+            "Creates a new request for the given `model`{}. Every optional field is left unset, so \
+            the API falls back to its documented default.",
+            if required_fields_names.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    " with the required {} set",
+                    required_fields_names
+                        .iter()
+                        .map(|name| format!("`{name}`"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }
+        );
+        let for_model_doc = format!(
+            "Same as [`Self::init`], but takes a model id instead of a model retrieved from the API, \
+            saving the extra request. See [`{crate_path_str}::client::Client::model_from_id`]."
+        );
         let init_func = quote::quote! {
-            pub fn init(model: &'model Model<'client>, #(#init_func_args),*) -> Self {
+            #[doc = #init_doc]
+            pub fn init(model: &'model #crate_path::model::Model<'client>, #(#init_func_args),*) -> Self {
+                Self {
+                    model: std::borrow::Cow::Borrowed(model)
+                    #(,#required_fields_names)*
+                    #(,#init_default_vals)*
+                    ,extra: Default::default()
+                    ,usage_tag: None
+                    ,timeout: None
+                }
+            }
+
+            #[doc = #for_model_doc]
+            pub fn for_model(
+                client: &'client #crate_path::client::Client,
+                model_id: impl Into<#crate_path::model::ModelId>,
+                #(#init_func_args),*
+            ) -> Self {
                 Self {
-                    model
+                    model: std::borrow::Cow::Owned(client.model_from_id(model_id))
                     #(,#required_fields_names)*
                     #(,#init_default_vals)*
+                    ,extra: Default::default()
+                    ,usage_tag: None
+                    ,timeout: None
                 }
             }
         };
@@ -227,8 +326,10 @@ fn rq_impl(attr: TokenStream, input: TokenStream) -> miette::Result<TokenStream>
             let ident = f.ident.as_ref().expect("Expected a named field");
             let fn_name = format_ident!("with_{}", ident);
             let ty = &f.ty;
+            let field_docs = f.attrs.iter().filter(|attr| attr.path().is_ident("doc"));
 
             quote::quote! {
+                #(#field_docs)*
                 pub fn #fn_name(mut self, #ident: #ty) -> Self {
                     self.#ident = Some(#ident);
 
@@ -240,7 +341,7 @@ fn rq_impl(attr: TokenStream, input: TokenStream) -> miette::Result<TokenStream>
         let to_json_req_fields = required_fields.iter().fold(quote::quote! {
             res.insert(
                 "model".to_string(),
-                serde_json::value::to_value(self.model.id().clone())?,
+                #crate_path::__private::serde_json::value::to_value(self.model.id())?,
             );
         }, |res_tokens, f| {
             let ident = f.ident.as_ref().expect("Expected a named field");
@@ -251,7 +352,7 @@ fn rq_impl(attr: TokenStream, input: TokenStream) -> miette::Result<TokenStream>
 
                 res.insert(
                     #ident_lit_str.to_string(),
-                    serde_json::value::to_value(self.#ident.clone())?,
+                    #crate_path::__private::serde_json::value::to_value(&self.#ident)?,
                 );
             }
         });
@@ -260,66 +361,363 @@ fn rq_impl(attr: TokenStream, input: TokenStream) -> miette::Result<TokenStream>
             let ident_lit_str = LitStr::new(&ident.to_string(), Span::call_site());
 
             quote::quote! {
-                if let Some(#ident) = self.#ident.clone() {
+                if let Some(#ident) = &self.#ident {
                     res.insert(
                         #ident_lit_str.to_string(),
-                        serde_json::value::to_value(#ident)?,
+                        #crate_path::__private::serde_json::value::to_value(#ident)?,
                     );
                 }
             }
         });
-        let to_json = quote::quote! {
-            fn to_json(&self) -> serde_json::Result<serde_json::Value> {
-                let mut res = serde_json::Map::<String, serde_json::Value>::new();
+        let transform_json = transform_json
+            .as_ref()
+            .map(|transform_json| quote::quote!(#transform_json(self, &mut res);));
+        let fields_from_json = required_fields.iter().chain(&non_required_fields).map(|f| {
+            let ident = f.ident.as_ref().expect("Expected a named field");
+            let ident_lit_str = LitStr::new(&ident.to_string(), Span::call_site());
+
+            quote::quote! {
+                if let Some(value) = fields.remove(#ident_lit_str) {
+                    self.#ident = #crate_path::__private::serde_json::from_value(value)?;
+                }
+            }
+        });
+        let fields_json = quote::quote! {
+            /// The typed fields of the request body as JSON, `model` included, without the extra
+            /// fields nor the adaptations to the model made when sending it.
+            pub fn fields_to_json(&self) -> #crate_path::__private::serde_json::Result<#crate_path::__private::serde_json::Map<String, #crate_path::__private::serde_json::Value>> {
+                let mut res = #crate_path::__private::serde_json::Map::<String, #crate_path::__private::serde_json::Value>::new();
 
                 #to_json_req_fields
 
                 #(#to_json_non_req_fields)*
 
-                Ok(serde_json::Value::Object(res))
+                Ok(res)
+            }
+
+            /// Sets the typed fields from their JSON, as returned by [`Self::fields_to_json`]. The
+            /// other keys are added as extra fields (see [`Self::with_extra`]), and `model` is
+            /// ignored, the request keeping its own.
+            pub fn with_fields_json(
+                mut self,
+                mut fields: #crate_path::__private::serde_json::Map<String, #crate_path::__private::serde_json::Value>,
+            ) -> #crate_path::__private::serde_json::Result<Self> {
+                fields.remove("model");
+
+                #(#fields_from_json)*
+
+                self.extra.extend(fields);
+
+                Ok(self)
             }
         };
+        let to_json = quote::quote! {
+            fn to_json(&self) -> #crate_path::__private::serde_json::Result<#crate_path::__private::serde_json::Value> {
+                let mut res = self.fields_to_json()?;
+
+                #transform_json
+
+                res.extend(self.extra.clone());
+
+                Ok(#crate_path::__private::serde_json::Value::Object(res))
+            }
+        };
+
+        // Without a transformation, the fields are written straight to the serializer instead of
+        // going through a `serde_json::Value`
+        let serialize_body = match transform_json {
+            Some(_) => quote::quote! {
+                use #crate_path::__private::serde::ser::Error;
+                use #crate_path::request::Request;
+
+                self.to_json()
+                    .map_err(S::Error::custom)?
+                    .serialize(serializer)
+            },
+            None => {
+                let serialize_req_fields = required_fields.iter().map(|f| {
+                    let ident = f.ident.as_ref().expect("Expected a named field");
+                    let ident_lit_str = LitStr::new(&ident.to_string(), Span::call_site());
+
+                    quote::quote! {
+                        if !self.extra.contains_key(#ident_lit_str) {
+                            map.serialize_entry(#ident_lit_str, &self.#ident)?;
+                        }
+                    }
+                });
+                let serialize_non_req_fields = non_required_fields.iter().map(|f| {
+                    let ident = f.ident.as_ref().expect("Expected a named field");
+                    let ident_lit_str = LitStr::new(&ident.to_string(), Span::call_site());
+
+                    quote::quote! {
+                        if let Some(#ident) = &self.#ident {
+                            if !self.extra.contains_key(#ident_lit_str) {
+                                map.serialize_entry(#ident_lit_str, #ident)?;
+                            }
+                        }
+                    }
+                });
+
+                quote::quote! {
+                    use #crate_path::__private::serde::ser::SerializeMap;
+
+                    let mut map = serializer.serialize_map(None)?;
+
+                    if !self.extra.contains_key("model") {
+                        map.serialize_entry("model", self.model.id())?;
+                    }
+
+                    #(#serialize_req_fields)*
+
+                    #(#serialize_non_req_fields)*
+
+                    for (key, value) in &self.extra {
+                        map.serialize_entry(key, value)?;
+                    }
+
+                    map.end()
+                }
+            }
+        };
+        let to_body = transform_json.is_none().then(|| {
+            quote::quote! {
+                fn to_body(&self) -> #crate_path::__private::serde_json::Result<Vec<u8>> {
+                    #crate_path::__private::serde_json::to_vec(self)
+                }
+            }
+        });
+
+        let url_doc = format!("Endpoint: `POST {}`", url.value());
+        let compatible_models_doc = format!(
+            "Compatible models: {}",
+            compatible_models
+                .iter()
+                .map(|model| match model {
+                    Expr::Path(path) => {
+                        let variant = path.to_token_stream().to_string();
+
+                        format!("[`ModelId::{variant}`]({crate_path_str}::model::ModelId::{variant})")
+                    }
+                    Expr::Lit(ExprLit { lit: Lit::Str(id), .. }) => format!("`{}`", id.value()),
+                    _ => panic!("Expected a ModelId variant or a string literal"),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        // Known models are given as `ModelId` variants, anything else as a string literal
+        let compatible_models = compatible_models.iter().map(|model| match model {
+            Expr::Path(path) => quote::quote!(#crate_path::model::ModelId::#path),
+            Expr::Lit(ExprLit { lit: Lit::Str(id), .. }) => {
+                quote::quote!(#crate_path::model::ModelId::Other(std::borrow::Cow::Borrowed(#id)))
+            }
+            _ => panic!("Expected a ModelId variant or a string literal"),
+        }).collect::<Vec<_>>();
+
+        let beta_doc = beta.iter().map(|beta| {
+            let beta_doc = format!("Beta: sent with the `OpenAI-Beta: {}` header", beta.value());
+
+            quote::quote! {
+                #[doc = ""]
+                #[doc = #beta_doc]
+            }
+        });
+        let beta_const = beta.as_ref().map(|beta| {
+            quote::quote! {
+                const BETA: Option<&'static str> = Some(#beta);
+            }
+        });
 
-        let model_error = format_ident!("NotCompatibleWith{}", substruct_name);
+        let validate_fn = validate.as_ref().map(|validate| {
+            quote::quote! {
+                fn validate(&self) -> Result<(), Vec<#crate_path::error::ValidationIssue>> {
+                    #validate(self)
+                }
+            }
+        });
+
+        let moderation_input_fn = moderation_input.as_ref().map(|moderation_input| {
+            quote::quote! {
+                fn moderation_input(&self) -> Option<#crate_path::request::moderation::ModerationInput> {
+                    #moderation_input(self)
+                }
+            }
+        });
+
+        let output_tokens_fn = output_tokens.as_ref().map(|output_tokens| {
+            quote::quote! {
+                fn output_tokens(&self) -> Option<u64> {
+                    #output_tokens(self)
+                }
+            }
+        });
+
+        // Downstream crates can't add variants to `ModelError`, so they get the generic one
+        let model_error = if crate_path.is_ident("crate") {
+            let variant = format_ident!("NotCompatibleWith{}", substruct_name);
+
+            quote::quote!(#crate_path::error::ModelError::#variant)
+        } else {
+            quote::quote! {
+                #crate_path::error::ModelError::NotCompatible {
+                    url: #url,
+                    compatible_models: &[#(#compatible_models),*],
+                }
+            }
+        };
+        let model_field_doc = format!(
+            "ID of the model to use. You can use the [`{crate_path_str}::client::Client::list_models`] or \
+            [`{crate_path_str}::client::Client::list_models_blocking`] to see all of your available models, \
+            or see the [Model overview](https://platform.openai.com/docs/models/overview) for \
+            descriptions of them."
+        );
         let response = format_ident!("{}Response", substruct_name);
+        let usage_tag_doc = format!(
+            "Accounts the usage of the response under `usage_tag` (a user, a session, a feature, \
+            ...) on top of the totals, see [`{crate_path_str}::client::Client::usage`]."
+        );
+        let timeout_doc = format!(
+            "Sets the timeout of this request, instead of the one derived from its `max_tokens` by \
+            [`{crate_path_str}::client::Client::timeout_policy`]."
+        );
+        let canonical_json_doc = format!(
+            "[`Request::to_canonical_json`]({crate_path_str}::request::Request::to_canonical_json). \
+            Requests for different clients with the same body are equal."
+        );
 
         quote::quote! {
             #doc
-            #[derive(Debug, getset::Getters)]
+            #[doc = ""]
+            #[doc = #url_doc]
+            #[doc = ""]
+            #[doc = #compatible_models_doc]
+            #(#beta_doc)*
+            #[derive(Debug, Clone, #crate_path::__private::getset::Getters)]
             pub struct #actual_substruct_name<'model, 'client> {
-                /// Required.
-                ///
-                /// ID of the model to use. You can use the [`crate::client::Client::list_models`] or
-                /// [`crate::client::Client::list_models_blocking`] to see all of your available models,
-                /// or see the [Model overview](https://platform.openai.com/docs/models/overview) for
-                /// descriptions of them.
-                model: &'model Model<'client>,
-
-                #(#fields_tokens),*
+                #[doc = "Required."]
+                #[doc = ""]
+                #[doc = #model_field_doc]
+                model: std::borrow::Cow<'model, #crate_path::model::Model<'client>>,
+
+                #(#fields_tokens,)*
+
+                /// Additional fields merged into the request body, overriding the typed ones.
+                #[get = "pub"]
+                extra: #crate_path::__private::serde_json::Map<String, #crate_path::__private::serde_json::Value>,
+
+                /// Tag the usage of the response is accounted under, not sent to the API. Read
+                /// with `Request::usage_tag`.
+                usage_tag: Option<String>,
+
+                /// Timeout of this request, overriding the one of the client. Read with
+                /// `Request::timeout`.
+                timeout: Option<std::time::Duration>,
             }
 
             impl<'model, 'client> #actual_substruct_name<'model, 'client> {
                 #init_func
 
                 #(#with_functions)*
+
+                #fields_json
+
+                /// Adds an arbitrary field to the request body, for provider specific or brand-new
+                /// parameters this crate doesn't model yet.
+                pub fn with_extra(
+                    mut self,
+                    key: impl AsRef<str>,
+                    value: impl Into<#crate_path::__private::serde_json::Value>,
+                ) -> Self {
+                    self.extra.insert(key.as_ref().to_string(), value.into());
+
+                    self
+                }
+
+                #[doc = #usage_tag_doc]
+                pub fn with_usage_tag(mut self, usage_tag: impl AsRef<str>) -> Self {
+                    self.usage_tag = Some(usage_tag.as_ref().to_string());
+
+                    self
+                }
+
+                #[doc = #timeout_doc]
+                pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+                    self.timeout = Some(timeout);
+
+                    self
+                }
             }
 
-            impl<'model, 'client> crate::request::Request<'model, 'client, #response> for #actual_substruct_name<'model, 'client> {
+            impl<'model, 'client> #crate_path::__private::serde::Serialize for #actual_substruct_name<'model, 'client> {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: #crate_path::__private::serde::Serializer,
+                {
+                    #serialize_body
+                }
+            }
+
+            /// Compares the canonical JSON bodies, see
+            #[doc = #canonical_json_doc]
+            impl<'model, 'client> PartialEq for #actual_substruct_name<'model, 'client> {
+                fn eq(&self, other: &Self) -> bool {
+                    use #crate_path::request::Request;
+
+                    let canonical = |request: &Self| {
+                        request.to_canonical_json().ok().map(|json| json.to_string())
+                    };
+
+                    canonical(self) == canonical(other)
+                }
+            }
+
+            impl<'model, 'client> Eq for #actual_substruct_name<'model, 'client> {}
+
+            /// Hashes the canonical JSON body, consistently with [`PartialEq`].
+            impl<'model, 'client> std::hash::Hash for #actual_substruct_name<'model, 'client> {
+                fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                    use #crate_path::request::Request;
+
+                    self.to_canonical_json()
+                        .ok()
+                        .map(|json| json.to_string())
+                        .hash(state);
+                }
+            }
+
+            impl<'model, 'client> #crate_path::request::Request<'model, 'client, #response> for #actual_substruct_name<'model, 'client> {
                 const URL: &'static str = #url;
 
-                const COMPATIBLE_MODELS: &'static [&'static str] = &[
+                const COMPATIBLE_MODELS: &'static [#crate_path::model::ModelId] = &[
                     #(#compatible_models),*
                 ];
 
-                fn model(&self) -> &'model Model<'client> {
+                #beta_const
+
+                #validate_fn
+
+                #moderation_input_fn
+
+                #output_tokens_fn
+
+                fn model(&self) -> &#crate_path::model::Model<'client> {
                     &self.model
                 }
 
-                fn model_error() -> crate::error::ModelError {
-                    crate::error::ModelError::#model_error
+                fn model_error() -> #crate_path::error::ModelError {
+                    #model_error
                 }
 
                 #to_json
+
+                #to_body
+
+                fn usage_tag(&self) -> Option<&str> {
+                    self.usage_tag.as_deref()
+                }
+
+                fn timeout(&self) -> Option<std::time::Duration> {
+                    self.timeout
+                }
             }
         }
     });
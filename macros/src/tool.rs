@@ -0,0 +1,364 @@
+use convert_case::{Case, Casing};
+use miette::IntoDiagnostic;
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::ToTokens;
+use syn::{
+    ext::IdentExt, parse_quote, punctuated::Punctuated, Attribute, Data, DataEnum, DataStruct,
+    DeriveInput, Expr, ExprLit, Fields, Lit, LitStr, Meta, Path, Token, Type,
+};
+
+pub(crate) fn openai_tool_impl(input: TokenStream) -> miette::Result<TokenStream> {
+    let DeriveInput {
+        attrs,
+        ident,
+        generics,
+        data,
+        ..
+    } = syn::parse(input).into_diagnostic()?;
+
+    assert!(
+        generics.params.is_empty(),
+        "OpenAiTool can't be derived for generic types"
+    );
+
+    let ToolAttrs {
+        name,
+        strict,
+        crate_path,
+    } = parse_tool_attrs(&attrs);
+    let container_serde = SerdeAttrs::parse(&attrs);
+    let name = name.unwrap_or_else(|| {
+        LitStr::new(
+            &ident.unraw().to_string().to_case(Case::Snake),
+            ident.span(),
+        )
+    });
+    let description = doc_string(&attrs)
+        .map(|doc| quote::quote!(Some(#doc)))
+        .unwrap_or_else(|| quote::quote!(None));
+
+    let schema = match data {
+        Data::Struct(DataStruct {
+            fields: Fields::Named(fields),
+            ..
+        }) => {
+            // Named as serde deserializes them, the skipped ones aren't parameters
+            let fields = fields
+                .named
+                .iter()
+                .filter_map(|field| {
+                    let serde = SerdeAttrs::parse(&field.attrs);
+                    let ident = field.ident.as_ref().expect("Expected a named field");
+                    let name = match (serde.rename, &container_serde.rename_all) {
+                        (Some(rename), _) => rename,
+                        (None, Some(rule)) => rule.apply_to_field(&ident.unraw().to_string()),
+                        (None, None) => ident.unraw().to_string(),
+                    };
+
+                    (!serde.skip).then(|| (field, LitStr::new(&name, ident.span())))
+                })
+                .collect::<Vec<_>>();
+
+            let properties = fields.iter().map(|(field, field_name)| {
+                let ty = &field.ty;
+
+                let description = doc_string(&field.attrs).map(|doc| {
+                    quote::quote! {
+                        if let Some(schema) = schema.as_object_mut() {
+                            schema.insert("description".to_string(), #doc.into());
+                        }
+                    }
+                });
+                // Strict mode requires every field, the optional ones accept null instead
+                let schema = match strict && is_option(ty) {
+                    true => quote::quote! {
+                        #crate_path::tool::nullable(<#ty as #crate_path::tool::ToolParameter>::schema())
+                    },
+                    false => quote::quote!(<#ty as #crate_path::tool::ToolParameter>::schema()),
+                };
+
+                quote::quote! {
+                    properties.insert(#field_name.to_string(), {
+                        let mut schema = #schema;
+                        #description
+                        schema
+                    });
+                }
+            });
+            let required = fields
+                .iter()
+                .filter(|(field, _)| strict || !is_option(&field.ty))
+                .map(|(_, field_name)| field_name);
+
+            quote::quote! {
+                let mut properties = #crate_path::__private::serde_json::Map::new();
+                #(#properties)*
+
+                #crate_path::__private::serde_json::json!({
+                    "type": "object",
+                    "properties": properties,
+                    "required": [#(#required),*],
+                    "additionalProperties": false,
+                })
+            }
+        }
+        Data::Enum(DataEnum { variants, .. }) => {
+            let variants = variants.iter().filter_map(|variant| {
+                assert!(
+                    matches!(variant.fields, Fields::Unit),
+                    "OpenAiTool can only be derived for enums with unit variants"
+                );
+
+                let serde = SerdeAttrs::parse(&variant.attrs);
+                let name = match (serde.rename, &container_serde.rename_all) {
+                    (Some(rename), _) => rename,
+                    (None, Some(rule)) => rule.apply_to_variant(&variant.ident.unraw().to_string()),
+                    (None, None) => variant.ident.unraw().to_string(),
+                };
+
+                (!serde.skip).then(|| LitStr::new(&name, variant.ident.span()))
+            });
+
+            quote::quote! {
+                #crate_path::__private::serde_json::json!({
+                    "type": "string",
+                    "enum": [#(#variants),*],
+                })
+            }
+        }
+        _ => panic!("OpenAiTool can only be derived for structs with named fields or unit enums"),
+    };
+
+    Ok((quote::quote! {
+        impl #crate_path::tool::ToolParameter for #ident {
+            fn schema() -> #crate_path::__private::serde_json::Value {
+                #schema
+            }
+        }
+
+        impl #crate_path::tool::OpenAiTool for #ident {
+            const NAME: &'static str = #name;
+            const DESCRIPTION: Option<&'static str> = #description;
+            const STRICT: bool = #strict;
+        }
+    })
+    .into())
+}
+
+struct ToolAttrs {
+    name: Option<LitStr>,
+    strict: bool,
+    crate_path: TokenStream2,
+}
+
+/// Parses `#[tool(name("..."), strict, crate_path("..."))]`
+fn parse_tool_attrs(attrs: &[Attribute]) -> ToolAttrs {
+    let mut name = None;
+    let mut strict = false;
+    let mut crate_path: Path = parse_quote!(::openai_api_rs);
+
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("tool"))
+        .for_each(|attr| {
+            let tags = attr
+                .parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+                .expect("Couldn't parse the #[tool(...)] attribute");
+
+            tags.iter().for_each(|tag| match tag {
+                Meta::Path(path) if path.is_ident("strict") => strict = true,
+                Meta::List(tag) if tag.path.is_ident("name") => {
+                    name = Some(tag.parse_args::<LitStr>().expect("Couldn't parse the name"));
+                }
+                Meta::List(tag) if tag.path.is_ident("crate_path") => {
+                    crate_path = tag
+                        .parse_args::<LitStr>()
+                        .expect("Couldn't parse the crate_path")
+                        .parse()
+                        .expect("Expected crate_path to be a valid path");
+                }
+                _ => panic!("Expected one of these tags: ['name', 'strict', 'crate_path']"),
+            });
+        });
+
+    ToolAttrs {
+        name,
+        strict,
+        crate_path: crate_path.to_token_stream(),
+    }
+}
+
+/// The `#[serde(...)]` attributes changing the names serde deserializes, or the fields it reads.
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    rename_all: Option<RenameRule>,
+    skip: bool,
+}
+
+impl SerdeAttrs {
+    fn parse(attrs: &[Attribute]) -> Self {
+        attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("serde"))
+            .filter_map(|attr| {
+                attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+                    .ok()
+            })
+            .flatten()
+            .fold(Self::default(), |mut serde, meta| {
+                if meta.path().is_ident("rename") {
+                    serde.rename = deserialize_name(&meta);
+                } else if meta.path().is_ident("rename_all") {
+                    serde.rename_all = deserialize_name(&meta).map(|rule| {
+                        RenameRule::from_str(&rule)
+                            .unwrap_or_else(|| panic!("Unknown rename_all rule \"{rule}\""))
+                    });
+                } else if meta.path().is_ident("skip") || meta.path().is_ident("skip_deserializing")
+                {
+                    serde.skip = true;
+                }
+
+                serde
+            })
+    }
+}
+
+/// The name of `rename = "..."`, or the `deserialize` one of `rename(deserialize = "...")`.
+fn deserialize_name(meta: &Meta) -> Option<String> {
+    match meta {
+        Meta::NameValue(meta) => lit_str(&meta.value),
+        Meta::List(meta) => meta
+            .parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+            .ok()?
+            .into_iter()
+            .find_map(|meta| match meta {
+                Meta::NameValue(meta) if meta.path.is_ident("deserialize") => lit_str(&meta.value),
+                _ => None,
+            }),
+        Meta::Path(_) => None,
+    }
+}
+
+fn lit_str(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(lit), ..
+        }) => Some(lit.value()),
+        _ => None,
+    }
+}
+
+/// The case conversions of `#[serde(rename_all = "...")]`, applied the way serde does.
+enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn from_str(rule: &str) -> Option<Self> {
+        Some(match rule {
+            "lowercase" => Self::Lower,
+            "UPPERCASE" => Self::Upper,
+            "PascalCase" => Self::Pascal,
+            "camelCase" => Self::Camel,
+            "snake_case" => Self::Snake,
+            "SCREAMING_SNAKE_CASE" => Self::ScreamingSnake,
+            "kebab-case" => Self::Kebab,
+            "SCREAMING-KEBAB-CASE" => Self::ScreamingKebab,
+            _ => return None,
+        })
+    }
+
+    /// Renames a snake_case field.
+    fn apply_to_field(&self, field: &str) -> String {
+        match self {
+            Self::Lower | Self::Snake => field.to_string(),
+            Self::Upper | Self::ScreamingSnake => field.to_ascii_uppercase(),
+            Self::Pascal => field
+                .split('_')
+                .map(|word| {
+                    let mut chars = word.chars();
+
+                    chars
+                        .next()
+                        .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                        .unwrap_or_default()
+                })
+                .collect(),
+            Self::Camel => {
+                let pascal = Self::Pascal.apply_to_field(field);
+
+                pascal[..1].to_ascii_lowercase() + &pascal[1..]
+            }
+            Self::Kebab => field.replace('_', "-"),
+            Self::ScreamingKebab => field.to_ascii_uppercase().replace('_', "-"),
+        }
+    }
+
+    /// Renames a PascalCase variant.
+    fn apply_to_variant(&self, variant: &str) -> String {
+        let snake = || {
+            variant
+                .char_indices()
+                .fold(String::new(), |mut snake, (i, c)| {
+                    if i > 0 && c.is_uppercase() {
+                        snake.push('_');
+                    }
+
+                    snake.push(c.to_ascii_lowercase());
+                    snake
+                })
+        };
+
+        match self {
+            Self::Lower => variant.to_ascii_lowercase(),
+            Self::Upper => variant.to_ascii_uppercase(),
+            Self::Pascal => variant.to_string(),
+            Self::Camel => variant[..1].to_ascii_lowercase() + &variant[1..],
+            Self::Snake => snake(),
+            Self::ScreamingSnake => snake().to_ascii_uppercase(),
+            Self::Kebab => snake().replace('_', "-"),
+            Self::ScreamingKebab => snake().to_ascii_uppercase().replace('_', "-"),
+        }
+    }
+}
+
+/// Joins all the `///` lines into a single description, [None] if there are none
+fn doc_string(attrs: &[Attribute]) -> Option<String> {
+    let lines = attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) if meta.path.is_ident("doc") => match &meta.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(doc), ..
+                }) => Some(doc.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let doc = lines.join("\n").trim().to_string();
+
+    (!doc.is_empty()).then_some(doc)
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(ty) => ty
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident == "Option")
+            .unwrap_or(false),
+        _ => false,
+    }
+}
@@ -0,0 +1,90 @@
+use async_trait::async_trait;
+
+use crate::{
+    client::Client,
+    error,
+    model::ModelId,
+    request::{
+        chat_completion::{ChatCompletionResponse, ChatMessage},
+        embedding::{EmbeddingInput, EmbeddingResponse},
+        moderation::{ModerationInput, ModerationResponse},
+        text_completion::{Prompt, TextCompletionResponse},
+        ChatCompletionRequest, EmbeddingRequest, ModerationRequest, Request, TextCompletionRequest,
+    },
+};
+
+/// The high-level operations of the API, implemented by [`Client`].
+///
+/// It is object safe, so applications can depend on a `dyn OpenAiApi` (or a generic) and inject a
+/// mock implementation in their tests instead of making network calls. The requests are sent with
+/// every optional parameter left to its default, build them directly when more control is needed.
+#[async_trait]
+pub trait OpenAiApi: Send + Sync {
+    async fn chat(
+        &self,
+        model: ModelId,
+        messages: Vec<ChatMessage>,
+    ) -> error::Result<ChatCompletionResponse>;
+
+    async fn complete(
+        &self,
+        model: ModelId,
+        prompt: Prompt,
+    ) -> error::Result<TextCompletionResponse>;
+
+    async fn embed(
+        &self,
+        model: ModelId,
+        input: EmbeddingInput,
+    ) -> error::Result<EmbeddingResponse>;
+
+    async fn moderate(
+        &self,
+        model: ModelId,
+        input: ModerationInput,
+    ) -> error::Result<ModerationResponse>;
+}
+
+#[async_trait]
+impl OpenAiApi for Client {
+    async fn chat(
+        &self,
+        model: ModelId,
+        messages: Vec<ChatMessage>,
+    ) -> error::Result<ChatCompletionResponse> {
+        ChatCompletionRequest::for_model(self, model, messages)
+            .execute()
+            .await
+    }
+
+    async fn complete(
+        &self,
+        model: ModelId,
+        prompt: Prompt,
+    ) -> error::Result<TextCompletionResponse> {
+        TextCompletionRequest::for_model(self, model)
+            .with_prompt(prompt)
+            .execute()
+            .await
+    }
+
+    async fn embed(
+        &self,
+        model: ModelId,
+        input: EmbeddingInput,
+    ) -> error::Result<EmbeddingResponse> {
+        EmbeddingRequest::for_model(self, model, input)
+            .execute()
+            .await
+    }
+
+    async fn moderate(
+        &self,
+        model: ModelId,
+        input: ModerationInput,
+    ) -> error::Result<ModerationResponse> {
+        ModerationRequest::for_model(self, model, input)
+            .execute()
+            .await
+    }
+}
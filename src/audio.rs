@@ -0,0 +1,196 @@
+use std::{io::Cursor, time::Duration};
+
+use hound::{SampleFormat, WavReader};
+
+use crate::{
+    error,
+    request::chat_completion::{AudioOutputFormat, MessageAudio},
+};
+
+/// Sample rate of the `pcm16` audio the API generates, which has no header to read it from.
+pub const PCM16_SAMPLE_RATE: u32 = 24_000;
+
+/// Decoded audio, 16-bit samples interleaved by channel, ready to be played with
+/// `rodio::buffer::SamplesBuffer::new(audio.channels, audio.sample_rate, audio.samples)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AudioSamples {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub samples: Vec<i16>,
+}
+
+/// Generated audio, decoded when it is uncompressed (`wav`, `pcm16`), as it was sent otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodedAudio {
+    Samples(AudioSamples),
+    /// The file of a compressed format (`mp3`, `opus`, `flac`), for a player or a decoder.
+    Encoded {
+        format: AudioOutputFormat,
+        bytes: Vec<u8>,
+    },
+}
+
+impl AudioSamples {
+    /// Reads a WAV file of integer or float samples.
+    pub fn from_wav(bytes: &[u8]) -> error::Result<Self> {
+        let reader = WavReader::new(Cursor::new(bytes))?;
+        let spec = reader.spec();
+        let samples = match (spec.sample_format, spec.bits_per_sample) {
+            (SampleFormat::Float, _) => reader
+                .into_samples::<f32>()
+                .map(|sample| sample.map(|sample| (sample * f32::from(i16::MAX)) as i16))
+                .collect::<Result<_, _>>()?,
+            (SampleFormat::Int, bits) if bits <= 16 => reader
+                .into_samples::<i16>()
+                .map(|sample| sample.map(|sample| sample << (16 - bits)))
+                .collect::<Result<_, _>>()?,
+            (SampleFormat::Int, bits) => reader
+                .into_samples::<i32>()
+                .map(|sample| sample.map(|sample| (sample >> (bits - 16)) as i16))
+                .collect::<Result<_, _>>()?,
+        };
+
+        Ok(Self {
+            sample_rate: spec.sample_rate,
+            channels: spec.channels,
+            samples,
+        })
+    }
+
+    /// Reads raw `pcm16` audio: mono little-endian 16-bit samples at [`PCM16_SAMPLE_RATE`]. A
+    /// trailing odd byte is dropped.
+    pub fn from_pcm16(bytes: &[u8]) -> Self {
+        Self {
+            sample_rate: PCM16_SAMPLE_RATE,
+            channels: 1,
+            samples: bytes
+                .chunks_exact(2)
+                .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+                .collect(),
+        }
+    }
+
+    /// The samples between -1 and 1, for the players taking float samples.
+    pub fn to_f32(&self) -> Vec<f32> {
+        self.samples
+            .iter()
+            .map(|&sample| f32::from(sample) / f32::from(i16::MAX))
+            .collect()
+    }
+
+    pub fn duration(&self) -> Duration {
+        let frames = self.samples.len() as u64 / u64::from(self.channels.max(1));
+
+        Duration::from_secs_f64(frames as f64 / f64::from(self.sample_rate.max(1)))
+    }
+}
+
+impl DecodedAudio {
+    /// Decodes `bytes` of audio generated in `format`.
+    pub fn new(bytes: Vec<u8>, format: AudioOutputFormat) -> error::Result<Self> {
+        Ok(match format {
+            AudioOutputFormat::Wav => Self::Samples(AudioSamples::from_wav(&bytes)?),
+            AudioOutputFormat::Pcm16 => Self::Samples(AudioSamples::from_pcm16(&bytes)),
+            AudioOutputFormat::Mp3 | AudioOutputFormat::Flac | AudioOutputFormat::Opus => {
+                Self::Encoded { format, bytes }
+            }
+        })
+    }
+}
+
+impl MessageAudio {
+    /// Decodes the audio, generated in the `format` of the `audio` parameter of the request.
+    pub fn decode_audio(&self, format: AudioOutputFormat) -> error::Result<DecodedAudio> {
+        DecodedAudio::new(self.decode()?, format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hound::{Sample, WavSpec, WavWriter};
+
+    use super::*;
+
+    /// A mono WAV file at 8 kHz.
+    fn wav<S: Sample + Copy>(
+        bits_per_sample: u16,
+        sample_format: SampleFormat,
+        samples: &[S],
+    ) -> Vec<u8> {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 8_000,
+            bits_per_sample,
+            sample_format,
+        };
+        let mut bytes = Cursor::new(Vec::new());
+        let mut writer = WavWriter::new(&mut bytes, spec).unwrap();
+
+        for &sample in samples {
+            writer.write_sample(sample).unwrap();
+        }
+
+        writer.finalize().unwrap();
+
+        bytes.into_inner()
+    }
+
+    fn decode(bytes: &[u8]) -> Vec<i16> {
+        let audio = AudioSamples::from_wav(bytes).unwrap();
+
+        assert_eq!((audio.sample_rate, audio.channels), (8_000, 1));
+
+        audio.samples
+    }
+
+    #[test]
+    fn decodes_8_bit_wav_files() {
+        let bytes = wav(8, SampleFormat::Int, &[i8::MIN, 0, i8::MAX]);
+
+        assert_eq!(decode(&bytes), [i16::MIN, 0, 127 << 8]);
+    }
+
+    #[test]
+    fn decodes_16_bit_wav_files() {
+        let bytes = wav(16, SampleFormat::Int, &[i16::MIN, -1, i16::MAX]);
+
+        assert_eq!(decode(&bytes), [i16::MIN, -1, i16::MAX]);
+    }
+
+    #[test]
+    fn decodes_24_bit_wav_files() {
+        let bytes = wav(24, SampleFormat::Int, &[-(1 << 23), 256, (1 << 23) - 1]);
+
+        assert_eq!(decode(&bytes), [i16::MIN, 1, i16::MAX]);
+    }
+
+    #[test]
+    fn decodes_float_wav_files() {
+        let bytes = wav(32, SampleFormat::Float, &[-1.0f32, 0.0, 0.5, 1.0]);
+
+        assert_eq!(decode(&bytes), [-i16::MAX, 0, i16::MAX / 2, i16::MAX]);
+    }
+
+    #[test]
+    fn drops_the_odd_trailing_byte_of_pcm16_audio() {
+        let audio = AudioSamples::from_pcm16(&[0x01, 0x00, 0xff, 0x7f, 0x80]);
+
+        assert_eq!(audio.samples, [1, i16::MAX]);
+        assert_eq!((audio.sample_rate, audio.channels), (PCM16_SAMPLE_RATE, 1));
+    }
+
+    #[test]
+    fn durations_count_the_frames_of_every_channel() {
+        let audio = AudioSamples {
+            sample_rate: PCM16_SAMPLE_RATE,
+            channels: 2,
+            samples: vec![0; 36_000],
+        };
+
+        assert_eq!(audio.duration(), Duration::from_millis(750));
+        assert_eq!(
+            AudioSamples::from_pcm16(&[0; 4_800]).duration(),
+            Duration::from_millis(100)
+        );
+    }
+}
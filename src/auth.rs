@@ -0,0 +1,295 @@
+use std::{
+    fmt::{Debug, Formatter},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use crate::error;
+
+/// What a [`TokenProvider`] callback returns.
+pub type TokenResult = Result<AccessToken, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Future returned by the callback of an async [`TokenProvider`].
+pub type TokenFuture = Pin<Box<dyn Future<Output = TokenResult> + Send>>;
+
+/// A bearer token, like the Azure AD (Entra ID) ones of the Azure OpenAI deployments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessToken {
+    pub token: String,
+    /// [None] if it never expires.
+    pub expires_at: Option<SystemTime>,
+}
+
+impl AccessToken {
+    pub fn new(token: impl AsRef<str>) -> Self {
+        Self {
+            token: token.as_ref().to_string(),
+            expires_at: None,
+        }
+    }
+
+    /// Expiring `expires_in` from now, as the `expires_in` of the OAuth token responses.
+    pub fn expires_in(mut self, expires_in: Duration) -> Self {
+        self.expires_at = Some(SystemTime::now() + expires_in);
+        self
+    }
+}
+
+#[derive(Clone)]
+enum Fetch {
+    Blocking(Arc<dyn Fn() -> TokenResult + Send + Sync>),
+    Async(Arc<dyn Fn() -> TokenFuture + Send + Sync>),
+}
+
+/// Authenticates the requests with bearer tokens from a callback instead of a static API key,
+/// see [`crate::client::Client::with_token_provider`].
+///
+/// The token is kept until it is about to expire, the callback is then called again before
+/// sending the next request. A blocking callback ([`TokenProvider::new`]) runs on the thread
+/// sending it, so prefer [`TokenProvider::new_async`] for callbacks doing I/O with async
+/// requests. A `401 Unauthorized` answer drops the token, to fetch a new one for the next request.
+/// Cloning a provider shares its token.
+#[derive(Clone)]
+pub struct TokenProvider {
+    fetch: Fetch,
+    refresh_margin: Duration,
+    token: Arc<Mutex<Option<AccessToken>>>,
+    /// Held while fetching, so concurrent requests wait for a single refresh
+    refreshing: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl TokenProvider {
+    pub const DEFAULT_REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+    pub fn new(fetch: impl Fn() -> TokenResult + Send + Sync + 'static) -> Self {
+        Self::with_fetch(Fetch::Blocking(Arc::new(fetch)))
+    }
+
+    /// A provider fetching its tokens with an async callback, awaited by the async requests.
+    /// Blocking requests run it to completion on a runtime of their own.
+    pub fn new_async<F, Fut>(fetch: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = TokenResult> + Send + 'static,
+    {
+        Self::with_fetch(Fetch::Async(Arc::new(move || Box::pin(fetch()))))
+    }
+
+    fn with_fetch(fetch: Fetch) -> Self {
+        Self {
+            fetch,
+            refresh_margin: Self::DEFAULT_REFRESH_MARGIN,
+            token: Default::default(),
+            refreshing: Default::default(),
+        }
+    }
+
+    /// How long before it expires a token is refreshed.
+    pub fn refresh_margin(mut self, refresh_margin: Duration) -> Self {
+        self.refresh_margin = refresh_margin;
+        self
+    }
+
+    /// The current token, fetching a new one first if there is none or it is about to expire.
+    pub async fn token(&self) -> Result<String, error::ClientError> {
+        if let Some(valid) = self.valid() {
+            return Ok(valid);
+        }
+
+        let _refreshing = self.refreshing.lock().await;
+
+        // Refreshed by another request while waiting
+        if let Some(valid) = self.valid() {
+            return Ok(valid);
+        }
+
+        let fetched = match &self.fetch {
+            Fetch::Blocking(fetch) => fetch(),
+            Fetch::Async(fetch) => fetch().await,
+        };
+
+        self.store(fetched)
+    }
+
+    /// (Blocking) The current token, fetching a new one first if there is none or it is about to
+    /// expire.
+    #[cfg(feature = "blocking")]
+    pub fn token_blocking(&self) -> Result<String, error::ClientError> {
+        if let Some(valid) = self.valid() {
+            return Ok(valid);
+        }
+
+        let _refreshing = self.refreshing.blocking_lock();
+
+        // Refreshed by another request while waiting
+        if let Some(valid) = self.valid() {
+            return Ok(valid);
+        }
+
+        let fetched = match &self.fetch {
+            Fetch::Blocking(fetch) => fetch(),
+            Fetch::Async(fetch) => tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map_err(|e| error::ClientError::TokenUnavailable(e.into()))?
+                .block_on(fetch()),
+        };
+
+        self.store(fetched)
+    }
+
+    /// Drops the current token, the next request fetches a new one.
+    pub fn invalidate(&self) {
+        *self.token.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// The current token, if it is not about to expire.
+    fn valid(&self) -> Option<String> {
+        let token = self.token.lock().unwrap_or_else(|e| e.into_inner());
+        let refresh_at = SystemTime::now() + self.refresh_margin;
+
+        token
+            .as_ref()
+            .filter(|token| {
+                token
+                    .expires_at
+                    .is_none_or(|expires_at| expires_at > refresh_at)
+            })
+            .map(|token| token.token.clone())
+    }
+
+    fn store(&self, fetched: TokenResult) -> Result<String, error::ClientError> {
+        let fetched = fetched.map_err(error::ClientError::TokenUnavailable)?;
+        let value = fetched.token.clone();
+
+        *self.token.lock().unwrap_or_else(|e| e.into_inner()) = Some(fetched);
+
+        Ok(value)
+    }
+}
+
+impl Debug for TokenProvider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenProvider")
+            .field("refresh_margin", &self.refresh_margin)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{
+        client::Client,
+        test_util::{
+            error_response, fixtures, json_response,
+            wiremock::{
+                matchers::{header, path},
+                Mock, MockServer,
+            },
+        },
+    };
+
+    /// A provider numbering its tokens, expiring after `expires_in`.
+    fn counting(expires_in: Duration) -> (TokenProvider, Arc<AtomicUsize>) {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let counter = fetches.clone();
+
+        let provider = TokenProvider::new_async(move || {
+            let fetched = counter.fetch_add(1, Ordering::SeqCst) + 1;
+
+            async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+
+                Ok(AccessToken::new(format!("token-{fetched}")).expires_in(expires_in))
+            }
+        });
+
+        (provider, fetches)
+    }
+
+    #[tokio::test]
+    async fn refreshes_the_tokens_about_to_expire() {
+        let (provider, fetches) = counting(Duration::from_secs(60 * 60));
+
+        assert_eq!(provider.token().await.unwrap(), "token-1");
+        assert_eq!(provider.token().await.unwrap(), "token-1");
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        let provider = provider.refresh_margin(Duration::from_secs(2 * 60 * 60));
+
+        assert_eq!(provider.token().await.unwrap(), "token-2");
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+
+        provider.invalidate();
+
+        assert_eq!(provider.token().await.unwrap(), "token-3");
+    }
+
+    #[tokio::test]
+    async fn concurrent_requests_fetch_once() {
+        let (provider, fetches) = counting(Duration::from_secs(60 * 60));
+
+        let tokens = futures_util::future::join_all((0..5).map(|_| provider.token())).await;
+
+        assert!(tokens
+            .iter()
+            .all(|token| token.as_deref().ok() == Some("token-1")));
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn blocking_requests_run_the_async_fetches() {
+        let (provider, fetches) = counting(Duration::from_secs(60 * 60));
+
+        assert_eq!(provider.token_blocking().unwrap(), "token-1");
+        assert_eq!(provider.token_blocking().unwrap(), "token-1");
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn reports_the_failed_fetches() {
+        let provider = TokenProvider::new(|| Err("no credentials".into()));
+
+        assert!(matches!(
+            provider.token().await,
+            Err(error::ClientError::TokenUnavailable(e)) if e.to_string() == "no credentials"
+        ));
+    }
+
+    #[tokio::test]
+    async fn sends_the_token_and_drops_it_when_rejected() {
+        let server = MockServer::start().await;
+
+        Mock::given(path("/chat/completions"))
+            .and(header("authorization", "Bearer token-1"))
+            .respond_with(error_response(
+                401,
+                "invalid_request_error",
+                "Expired token",
+            ))
+            .mount(&server)
+            .await;
+
+        Mock::given(path("/chat/completions"))
+            .and(header("authorization", "Bearer token-2"))
+            .respond_with(json_response(fixtures::chat_completion("Hello")))
+            .mount(&server)
+            .await;
+
+        let (provider, fetches) = counting(Duration::from_secs(60 * 60));
+        let client = Client::with_token_provider(provider)
+            .base_url(server.uri())
+            .allow_http();
+        let url = format!("{}/chat/completions", server.uri());
+
+        assert!(client.send(client.async_client().post(&url)).await.is_err());
+        assert!(client.send(client.async_client().post(&url)).await.is_ok());
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,63 @@
+use futures_util::{stream, Stream, StreamExt};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    error,
+    request::Request,
+    retry::{self, RetryPolicy},
+};
+
+/// Executes `requests` with at most `concurrency` of them in flight, retrying the failed ones
+/// with the default [`RetryPolicy`]. The results are in the order of the requests.
+pub async fn execute_many<'model, 'client, R, Response>(
+    requests: impl IntoIterator<Item = R>,
+    concurrency: usize,
+) -> Vec<error::Result<Response>>
+where
+    R: Request<'model, 'client, Response> + Sync,
+    Response: DeserializeOwned,
+    'client: 'model,
+{
+    execute_many_with_retry(requests, concurrency, RetryPolicy::default()).await
+}
+
+/// Same as [`execute_many`], with the given retry policy.
+pub async fn execute_many_with_retry<'model, 'client, R, Response>(
+    requests: impl IntoIterator<Item = R>,
+    concurrency: usize,
+    policy: RetryPolicy,
+) -> Vec<error::Result<Response>>
+where
+    R: Request<'model, 'client, Response> + Sync,
+    Response: DeserializeOwned,
+    'client: 'model,
+{
+    let mut results = execute_many_stream(requests, concurrency, policy)
+        .collect::<Vec<_>>()
+        .await;
+
+    results.sort_by_key(|(index, _)| *index);
+
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Same as [`execute_many_with_retry`], streaming each result along with the index of its
+/// request as soon as it is done, in completion order.
+pub fn execute_many_stream<'model, 'client, R, Response>(
+    requests: impl IntoIterator<Item = R>,
+    concurrency: usize,
+    policy: RetryPolicy,
+) -> impl Stream<Item = (usize, error::Result<Response>)>
+where
+    R: Request<'model, 'client, Response> + Sync,
+    Response: DeserializeOwned,
+    'client: 'model,
+{
+    let requests = requests.into_iter().enumerate().collect::<Vec<_>>();
+
+    stream::iter(requests)
+        .map(move |(index, request)| async move {
+            (index, retry::execute_with_retry(&request, &policy).await)
+        })
+        .buffer_unordered(concurrency.max(1))
+}
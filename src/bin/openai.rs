@@ -0,0 +1,271 @@
+//! `openai`, a small command line client on top of the crate, to smoke-test API keys and models.
+//!
+//! The model checks of the crate are disabled, leaving them to the API, so that any model can be
+//! tried.
+
+use std::{io::Read, path::PathBuf, process::ExitCode};
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+use openai_api_rs::{
+    client::Client,
+    error,
+    model::{Capability, ModelFilter, ModelOrder},
+    request::{
+        chat_completion::ChatMessage, ChatCompletionRequest, EmbeddingRequest, Request,
+        TextCompletionRequest,
+    },
+    transcription::{TranscriptionRequest, TRANSCRIPTIONS_URL},
+};
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "openai",
+    version,
+    about = "Command line client of the OpenAI API"
+)]
+struct Cli {
+    #[arg(long, env = "OPENAI_API_KEY", hide_env_values = true)]
+    api_key: String,
+
+    #[arg(long, env = "OPENAI_ORG_ID")]
+    organization: Option<String>,
+
+    /// Base URL of an OpenAI compatible API.
+    #[arg(long, env = "OPENAI_BASE_URL")]
+    base_url: Option<String>,
+
+    /// Allows a plain `http://` base URL, for local servers.
+    #[arg(long)]
+    allow_http: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Answers a prompt (read from stdin when missing) with a chat model.
+    Chat {
+        #[arg(short, long, default_value = "gpt-4o-mini")]
+        model: String,
+
+        /// Instructions sent as a system message.
+        #[arg(short, long)]
+        system: Option<String>,
+
+        #[arg(long)]
+        max_tokens: Option<u64>,
+
+        prompt: Vec<String>,
+    },
+    /// Completes a prompt (read from stdin when missing) with a text completion model.
+    Complete {
+        #[arg(short, long, default_value = "gpt-3.5-turbo-instruct")]
+        model: String,
+
+        #[arg(long)]
+        max_tokens: Option<u64>,
+
+        prompt: Vec<String>,
+    },
+    /// Prints the embedding of each input as a JSON array, one per line.
+    Embed {
+        #[arg(short, long, default_value = "text-embedding-3-small")]
+        model: String,
+
+        #[arg(long)]
+        dimensions: Option<u32>,
+
+        #[arg(required = true)]
+        inputs: Vec<String>,
+    },
+    /// Lists the models available to the API key, the newest first.
+    Models {
+        #[arg(long)]
+        owner: Option<String>,
+
+        /// Prefix of the model ids, like `gpt-4o` or `ft:`.
+        #[arg(long)]
+        prefix: Option<String>,
+
+        #[arg(long, value_enum)]
+        capability: Option<CapabilityArg>,
+    },
+    /// Transcribes an audio file.
+    Transcribe {
+        #[arg(short, long, default_value = "whisper-1")]
+        model: String,
+
+        /// Language of the audio as an ISO-639-1 code.
+        #[arg(short, long)]
+        language: Option<String>,
+
+        file: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum CapabilityArg {
+    Chat,
+    Vision,
+    Tools,
+}
+
+impl From<CapabilityArg> for Capability {
+    fn from(capability: CapabilityArg) -> Self {
+        match capability {
+            CapabilityArg::Chat => Self::Chat,
+            CapabilityArg::Vision => Self::Vision,
+            CapabilityArg::Tools => Self::Tools,
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> error::Result<()> {
+    let mut client = Client::new(cli.api_key);
+
+    if let Some(organization) = cli.organization {
+        client = client.organization(organization);
+    }
+
+    if let Some(base_url) = cli.base_url {
+        client = client.base_url(base_url);
+    }
+
+    if cli.allow_http {
+        client = client.allow_http();
+    }
+
+    for url in [
+        ChatCompletionRequest::URL,
+        TextCompletionRequest::URL,
+        EmbeddingRequest::URL,
+        TRANSCRIPTIONS_URL,
+    ] {
+        client.compatibility().allow_all(url);
+    }
+
+    match cli.command {
+        Command::Chat {
+            model,
+            system,
+            max_tokens,
+            prompt,
+        } => {
+            let messages = system
+                .map(ChatMessage::system)
+                .into_iter()
+                .chain([ChatMessage::user(prompt_or_stdin(prompt)?)])
+                .collect();
+            let mut request = ChatCompletionRequest::for_model(&client, model, messages);
+
+            if let Some(max_tokens) = max_tokens {
+                request = request.with_max_tokens(max_tokens);
+            }
+
+            for choice in request.execute().await?.choices {
+                println!("{}", choice.message.content.text());
+            }
+        }
+        Command::Complete {
+            model,
+            max_tokens,
+            prompt,
+        } => {
+            let mut request = TextCompletionRequest::for_model(&client, model)
+                .with_prompt(prompt_or_stdin(prompt)?.into());
+
+            if let Some(max_tokens) = max_tokens {
+                request = request.with_max_tokens(max_tokens);
+            }
+
+            for choice in request.execute().await?.choices {
+                println!("{}", choice.text);
+            }
+        }
+        Command::Embed {
+            model,
+            dimensions,
+            inputs,
+        } => {
+            let mut request = EmbeddingRequest::for_model(&client, model, inputs.into());
+
+            if let Some(dimensions) = dimensions {
+                request = request.with_dimensions(dimensions);
+            }
+
+            let mut embeddings = request.execute().await?.data;
+
+            embeddings.sort_by_key(|embedding| embedding.index);
+
+            for embedding in embeddings {
+                println!("{}", serde_json::to_string(&embedding.embedding)?);
+            }
+        }
+        Command::Models {
+            owner,
+            prefix,
+            capability,
+        } => {
+            let mut filter = ModelFilter::new().order(ModelOrder::NewestFirst);
+
+            if let Some(owner) = owner {
+                filter = filter.owned_by(owner);
+            }
+
+            if let Some(prefix) = prefix {
+                filter = filter.id_prefix(prefix);
+            }
+
+            if let Some(capability) = capability {
+                filter = filter.capability(capability.into());
+            }
+
+            for model in client.list_models_filtered(&filter).await? {
+                println!("{}\t{}\t{}", model.id(), model.owned_by(), model.created());
+            }
+        }
+        Command::Transcribe {
+            model,
+            language,
+            file,
+        } => {
+            let mut request = TranscriptionRequest::from_path(model, file)?;
+
+            if let Some(language) = language {
+                request = request.with_language(language);
+            }
+
+            println!("{}", request.execute(&client).await?.text);
+        }
+    }
+
+    Ok(())
+}
+
+/// The words of `prompt` joined back together, or stdin when there are none.
+fn prompt_or_stdin(prompt: Vec<String>) -> error::Result<String> {
+    if !prompt.is_empty() {
+        return Ok(prompt.join(" "));
+    }
+
+    let mut prompt = String::new();
+
+    std::io::stdin().read_to_string(&mut prompt)?;
+
+    Ok(prompt)
+}
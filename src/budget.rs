@@ -0,0 +1,240 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::error;
+
+/// A cap on the tokens, and optionally the USD, spent over a sliding window (an hour, a day,
+/// ...), so a bug or a runaway loop fails locally with [`error::BudgetExceeded`] instead of
+/// silently burning money.
+///
+/// The tokens of every response are recorded, and a request is refused once the tokens spent
+/// in the window plus an estimate of its prompt and of the output it asks for (its
+/// `max_tokens`) go over the cap. The costs are estimated with the
+/// [`crate::pricing::PricingTable`] of the client, the models without a price counting as free.
+/// Cloning a budget shares what was spent, see [`crate::client::Client::budget`].
+#[derive(Debug, Clone)]
+pub struct Budget {
+    max_tokens: u64,
+    max_cost: Option<f64>,
+    window: Duration,
+    spent: Arc<Mutex<VecDeque<Spending>>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Spending {
+    at: Instant,
+    tokens: u64,
+    cost: f64,
+}
+
+impl Budget {
+    pub fn tokens(max_tokens: u64, window: Duration) -> Self {
+        Self {
+            max_tokens,
+            max_cost: None,
+            window,
+            spent: Default::default(),
+        }
+    }
+
+    /// A cap on the USD spent only, whatever the tokens.
+    pub fn cost(max_cost: f64, window: Duration) -> Self {
+        Self::tokens(u64::MAX, window).max_cost(max_cost)
+    }
+
+    pub fn cost_per_day(max_cost: f64) -> Self {
+        Self::cost(max_cost, Duration::from_secs(24 * 60 * 60))
+    }
+
+    /// Caps the USD spent in the window as well as the tokens.
+    pub fn max_cost(mut self, max_cost: f64) -> Self {
+        self.max_cost = Some(max_cost);
+        self
+    }
+
+    pub fn tokens_per_hour(max_tokens: u64) -> Self {
+        Self::tokens(max_tokens, Duration::from_secs(60 * 60))
+    }
+
+    pub fn tokens_per_day(max_tokens: u64) -> Self {
+        Self::tokens(max_tokens, Duration::from_secs(24 * 60 * 60))
+    }
+
+    /// Tokens spent in the current window.
+    pub fn spent(&self) -> u64 {
+        self.spent_entries()
+            .iter()
+            .map(|spending| spending.tokens)
+            .sum()
+    }
+
+    /// Estimated USD spent in the current window.
+    pub fn spent_cost(&self) -> f64 {
+        self.spent_entries()
+            .iter()
+            .map(|spending| spending.cost)
+            .sum()
+    }
+
+    /// Tokens left in the current window.
+    pub fn remaining(&self) -> u64 {
+        self.max_tokens.saturating_sub(self.spent())
+    }
+
+    /// Records tokens spent outside of the client sharing this budget.
+    pub fn record(&self, tokens: u64) {
+        self.record_cost(tokens, 0.0);
+    }
+
+    /// Records tokens spent outside of the client sharing this budget, with what they cost in
+    /// USD.
+    pub fn record_cost(&self, tokens: u64, cost: f64) {
+        self.spent_entries().push_back(Spending {
+            at: Instant::now(),
+            tokens,
+            cost,
+        });
+    }
+
+    /// Refuses a request estimated to use `estimated_tokens` and cost `estimated_cost` if it
+    /// would go over a cap.
+    pub(crate) fn check(
+        &self,
+        estimated_tokens: u64,
+        estimated_cost: f64,
+    ) -> Result<(), error::BudgetExceeded> {
+        let spent = self.spent();
+
+        if spent.saturating_add(estimated_tokens) > self.max_tokens {
+            return Err(error::BudgetExceeded::Tokens {
+                spent,
+                estimated: estimated_tokens,
+                max_tokens: self.max_tokens,
+                window: self.window,
+            });
+        }
+
+        let spent = self.spent_cost();
+
+        match self.max_cost {
+            Some(max_cost) if spent + estimated_cost > max_cost => {
+                Err(error::BudgetExceeded::Cost {
+                    spent,
+                    estimated: estimated_cost,
+                    max_cost,
+                    window: self.window,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The spendings of the current window, the older ones dropped.
+    fn spent_entries(&self) -> std::sync::MutexGuard<'_, VecDeque<Spending>> {
+        let mut spent = self.spent.lock().unwrap_or_else(|e| e.into_inner());
+
+        while spent
+            .front()
+            .is_some_and(|spending| spending.at.elapsed() >= self.window)
+        {
+            spent.pop_front();
+        }
+
+        spent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        model::ModelId,
+        pricing::{ModelPrice, PricingTable},
+        request::{chat_completion::ChatMessage, ChatCompletionRequest, Request},
+        test_util::{self, chat_completions, fixtures, json_response, wiremock::MockServer},
+    };
+
+    #[test]
+    fn refuses_going_over_the_tokens() {
+        let budget = Budget::tokens_per_hour(100);
+
+        budget.record(60);
+
+        assert_eq!(budget.remaining(), 40);
+        assert!(budget.check(40, 0.0).is_ok());
+        assert!(matches!(
+            budget.check(41, 0.0),
+            Err(error::BudgetExceeded::Tokens {
+                spent: 60,
+                estimated: 41,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn refuses_going_over_the_cost() {
+        let budget = Budget::cost_per_day(1.0);
+
+        budget.record_cost(1_000_000, 0.75);
+
+        assert!(budget.check(u64::MAX / 2, 0.25).is_ok());
+        assert!(matches!(
+            budget.check(0, 0.5),
+            Err(error::BudgetExceeded::Cost { max_cost, .. }) if max_cost == 1.0
+        ));
+    }
+
+    #[test]
+    fn forgets_what_was_spent_before_the_window() {
+        let budget = Budget::tokens(10, Duration::from_millis(50));
+
+        budget.record(10);
+
+        assert!(budget.check(1, 0.0).is_err());
+
+        std::thread::sleep(Duration::from_millis(60));
+
+        assert_eq!(budget.spent(), 0);
+        assert!(budget.check(10, 0.0).is_ok());
+    }
+
+    #[tokio::test]
+    async fn counts_the_requested_output() {
+        let server = MockServer::start().await;
+
+        chat_completions()
+            .respond_with(json_response(fixtures::chat_completion("Hello")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let pricing = PricingTable::empty().set("gpt-4o", ModelPrice::new(0.0, 10.0));
+        let budget = Budget::tokens_per_hour(5_000).max_cost(0.01);
+        let client = test_util::client(&server)
+            .pricing(pricing)
+            .budget(budget.clone());
+        let model = client.model_from_id(ModelId::Gpt4o);
+        let request = |max_tokens| {
+            ChatCompletionRequest::init(&model, vec![ChatMessage::user("Hi")])
+                .with_max_tokens(max_tokens)
+        };
+
+        assert!(matches!(
+            request(10_000).execute().await.unwrap_err(),
+            error::Error::BudgetExceeded(error::BudgetExceeded::Tokens { .. })
+        ));
+        // 2000 output tokens at $10 per million
+        assert!(matches!(
+            request(2_000).execute().await.unwrap_err(),
+            error::Error::BudgetExceeded(error::BudgetExceeded::Cost { .. })
+        ));
+        assert!(request(100).execute().await.is_ok());
+        // The 9 completion tokens of the response
+        assert_eq!(budget.spent(), 21);
+        assert!((budget.spent_cost() - 0.00009).abs() < 1e-12);
+    }
+}
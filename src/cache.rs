@@ -0,0 +1,409 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Debug,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{request::canonicalize, response::ResponseMeta};
+
+/// An opt-in cache of responses, so identical requests (common in development and evaluation
+/// runs) aren't paid for twice. Requests are identical when they go to the same endpoint with
+/// the same canonical JSON body, see [`crate::request::Request::to_canonical_json`].
+///
+/// The responses are kept in a [`CacheStore`], in memory by default or on disk with
+/// [`FileStore`] to survive restarts. Cloning a cache shares its store, see
+/// [`crate::client::Client::response_cache`].
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    store: Arc<dyn CacheStore>,
+    ttl: Option<Duration>,
+}
+
+/// The body and metadata of a cached response.
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    pub body: Arc<[u8]>,
+    pub meta: ResponseMeta,
+    pub cached_at: SystemTime,
+}
+
+/// Where a [`ResponseCache`] keeps its responses.
+///
+/// A store failing to write is never an error for the request, which is then just not cached.
+pub trait CacheStore: Debug + Send + Sync {
+    fn get(&self, key: &str) -> Option<CachedResponse>;
+    fn insert(&self, key: String, response: CachedResponse) -> std::io::Result<()>;
+    fn remove(&self, key: &str) -> std::io::Result<()>;
+    fn clear(&self) -> std::io::Result<()>;
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl ResponseCache {
+    /// A cache in memory, evicting the least recently used entries once `max_entries` is
+    /// reached.
+    pub fn new(max_entries: usize) -> Self {
+        Self::with_store(MemoryStore::new(max_entries))
+    }
+
+    pub fn with_store(store: impl CacheStore + 'static) -> Self {
+        Self {
+            store: Arc::new(store),
+            ttl: None,
+        }
+    }
+
+    /// Time after which the entries expire, never by default.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.store.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.store.is_empty()
+    }
+
+    pub fn clear(&self) -> std::io::Result<()> {
+        self.store.clear()
+    }
+
+    /// The response cached for `key`, if it didn't expire.
+    pub fn get(&self, key: &str) -> Option<CachedResponse> {
+        let response = self.store.get(key)?;
+        let age = response.cached_at.elapsed().unwrap_or_default();
+
+        if self.ttl.is_some_and(|ttl| age >= ttl) {
+            let _ = self.store.remove(key);
+            return None;
+        }
+
+        Some(response)
+    }
+
+    pub fn insert(&self, key: String, body: impl Into<Arc<[u8]>>, meta: ResponseMeta) {
+        let response = CachedResponse {
+            body: body.into(),
+            meta,
+            cached_at: SystemTime::now(),
+        };
+
+        let _ = self.store.insert(key, response);
+    }
+
+    /// Key of a request body sent to `url`.
+    pub fn key(url: &str, json: &serde_json::Value) -> String {
+        format!("{url} {}", canonicalize(json.clone()))
+    }
+}
+
+/// Keeps the responses in memory, evicting the least recently used ones once `max_entries` is
+/// reached.
+#[derive(Debug)]
+pub struct MemoryStore {
+    max_entries: usize,
+    entries: Mutex<Entries>,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    responses: HashMap<String, CachedResponse>,
+    /// Keys from the least to the most recently used.
+    order: VecDeque<String>,
+}
+
+impl MemoryStore {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries: max_entries.max(1),
+            entries: Default::default(),
+        }
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl CacheStore for MemoryStore {
+    fn get(&self, key: &str) -> Option<CachedResponse> {
+        let mut entries = self.entries();
+        let response = entries.responses.get(key).cloned()?;
+
+        entries.order.retain(|k| k != key);
+        entries.order.push_back(key.to_string());
+
+        Some(response)
+    }
+
+    fn insert(&self, key: String, response: CachedResponse) -> std::io::Result<()> {
+        let mut entries = self.entries();
+
+        entries.remove(&key);
+
+        while entries.responses.len() >= self.max_entries {
+            let Some(oldest) = entries.order.pop_front() else {
+                break;
+            };
+
+            entries.responses.remove(&oldest);
+        }
+
+        entries.order.push_back(key.clone());
+        entries.responses.insert(key, response);
+
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> std::io::Result<()> {
+        self.entries().remove(key);
+
+        Ok(())
+    }
+
+    fn clear(&self) -> std::io::Result<()> {
+        let mut entries = self.entries();
+
+        entries.responses.clear();
+        entries.order.clear();
+
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.entries().responses.len()
+    }
+}
+
+impl Entries {
+    fn remove(&mut self, key: &str) {
+        if self.responses.remove(key).is_some() {
+            self.order.retain(|k| k != key);
+        }
+    }
+}
+
+/// Keeps the responses on disk, one JSON file per entry in a directory, so they survive
+/// restarts (cached embeddings for offline evaluations, ...).
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+struct FileEntry {
+    key: String,
+    /// The body as JSON rather than bytes, to keep the files readable.
+    body: serde_json::Value,
+    meta: ResponseMeta,
+    cached_at: SystemTime,
+}
+
+impl FileStore {
+    /// Stores the responses in `dir`, creating it if needed.
+    pub fn new(dir: impl AsRef<Path>) -> std::io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+
+        std::fs::create_dir_all(&dir)?;
+
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// File of a key, named after its FNV-1a hash. The key is stored in the file as well, in
+    /// case of a collision.
+    fn path(&self, key: &str) -> PathBuf {
+        let hash = key.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+
+        self.dir.join(format!("{hash:016x}.json"))
+    }
+
+    fn files(&self) -> impl Iterator<Item = PathBuf> {
+        std::fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "json")
+            })
+    }
+}
+
+impl CacheStore for FileStore {
+    fn get(&self, key: &str) -> Option<CachedResponse> {
+        let file = std::fs::read(self.path(key)).ok()?;
+        let entry = serde_json::from_slice::<FileEntry>(&file).ok()?;
+
+        (entry.key == key).then(|| CachedResponse {
+            body: serde_json::to_vec(&entry.body)
+                .expect("JSON values always serialize")
+                .into(),
+            meta: entry.meta,
+            cached_at: entry.cached_at,
+        })
+    }
+
+    fn insert(&self, key: String, response: CachedResponse) -> std::io::Result<()> {
+        let path = self.path(&key);
+        let file = serde_json::to_vec(&FileEntry {
+            key,
+            body: serde_json::from_slice(&response.body)?,
+            meta: response.meta,
+            cached_at: response.cached_at,
+        })?;
+
+        // Written aside first, so a concurrent reader never sees half a file
+        let partial = path.with_extension("partial");
+
+        std::fs::write(&partial, file)?;
+        std::fs::rename(partial, path)
+    }
+
+    fn remove(&self, key: &str) -> std::io::Result<()> {
+        match std::fs::remove_file(self.path(key)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn clear(&self) -> std::io::Result<()> {
+        self.files().try_for_each(std::fs::remove_file)
+    }
+
+    fn len(&self) -> usize {
+        self.files().count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        model::ModelId,
+        request::{chat_completion::ChatMessage, ChatCompletionRequest, Request},
+        test_util::{self, chat_completions, fixtures, json_response, wiremock::MockServer},
+    };
+
+    fn insert(cache: &ResponseCache, key: &str) {
+        cache.insert(key.to_string(), key.as_bytes(), ResponseMeta::default());
+    }
+
+    #[test]
+    fn keys_ignore_the_order_of_the_fields() {
+        let a = json!({ "model": "gpt-4o", "n": 1, "metadata": { "a": "1", "b": "2" } });
+        let b = json!({ "metadata": { "b": "2", "a": "1" }, "n": 1, "model": "gpt-4o" });
+
+        assert_eq!(
+            ResponseCache::key("/chat", &a),
+            ResponseCache::key("/chat", &b)
+        );
+        assert_ne!(
+            ResponseCache::key("/chat", &a),
+            ResponseCache::key("/completions", &a)
+        );
+        assert_ne!(
+            ResponseCache::key("/chat", &a),
+            ResponseCache::key("/chat", &json!({ "model": "gpt-4o", "n": 2 }))
+        );
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used() {
+        let cache = ResponseCache::new(2);
+
+        insert(&cache, "a");
+        insert(&cache, "b");
+        cache.get("a").unwrap();
+        insert(&cache, "c");
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("a").is_some());
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("c").is_some());
+
+        // Inserting a key again replaces it rather than taking a second slot
+        insert(&cache, "c");
+        insert(&cache, "d");
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("a").is_none());
+    }
+
+    #[test]
+    fn entries_expire_after_the_ttl() {
+        let cache = ResponseCache::new(8).ttl(Duration::from_millis(50));
+
+        insert(&cache, "a");
+
+        assert!(cache.get("a").is_some());
+
+        std::thread::sleep(Duration::from_millis(60));
+
+        assert!(cache.get("a").is_none());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn files_survive_the_store() {
+        let dir = std::env::temp_dir().join(format!("response-cache-{}", std::process::id()));
+        let cache = ResponseCache::with_store(FileStore::new(&dir).unwrap());
+        let body = json!({ "id": "chatcmpl-test" }).to_string();
+
+        cache.insert("a".to_string(), body.as_bytes(), ResponseMeta::default());
+
+        let reopened = ResponseCache::with_store(FileStore::new(&dir).unwrap());
+
+        assert_eq!(reopened.len(), 1);
+        assert_eq!(&*reopened.get("a").unwrap().body, body.as_bytes());
+        assert!(reopened.get("b").is_none());
+
+        reopened.clear().unwrap();
+
+        assert!(cache.is_empty());
+
+        std::fs::remove_dir(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn identical_requests_are_sent_once() {
+        let server = MockServer::start().await;
+
+        chat_completions()
+            .respond_with(json_response(fixtures::chat_completion("Hello")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = test_util::client(&server).response_cache(ResponseCache::new(8));
+        let model = client.model_from_id(ModelId::Gpt4o);
+        let request = ChatCompletionRequest::init(&model, vec![ChatMessage::user("Hi")]);
+
+        let sent = request.execute_with_meta().await.unwrap();
+        let cached = request.execute_with_meta().await.unwrap();
+
+        assert!(!sent.meta.cached());
+        assert!(cached.meta.cached());
+        assert_eq!(
+            cached.first_message().unwrap().content.text(),
+            sent.first_message().unwrap().content.text()
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::model::ModelInfo;
+
+/// The `/models` list, fetched on first use and kept for `ttl`, so that looking up models doesn't
+/// call the API every time. See [`crate::client::Client::model_catalog`].
+///
+/// Cloning a catalog shares its models.
+#[derive(Debug, Clone)]
+pub struct ModelCatalog {
+    ttl: Duration,
+    fetched: Arc<Mutex<Option<Fetched>>>,
+}
+
+#[derive(Debug)]
+struct Fetched {
+    at: Instant,
+    models: Arc<[ModelInfo]>,
+}
+
+impl ModelCatalog {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            fetched: Default::default(),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Drops the models, the next lookup fetches them again.
+    pub fn invalidate(&self) {
+        *self.lock() = None;
+    }
+
+    /// The models, [None] when they were never fetched or expired.
+    pub(crate) fn get(&self) -> Option<Arc<[ModelInfo]>> {
+        self.lock()
+            .as_ref()
+            .filter(|fetched| fetched.at.elapsed() < self.ttl)
+            .map(|fetched| fetched.models.clone())
+    }
+
+    pub(crate) fn store(&self, models: Vec<ModelInfo>) -> Arc<[ModelInfo]> {
+        let models = Arc::<[ModelInfo]>::from(models);
+
+        *self.lock() = Some(Fetched {
+            at: Instant::now(),
+            models: models.clone(),
+        });
+
+        models
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<Fetched>> {
+        self.fetched.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -0,0 +1,237 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::error;
+
+/// Stops sending requests to an endpoint after `failure_threshold` consecutive failures (server
+/// errors, timeouts and connection failures), failing them right away with
+/// [`error::CircuitOpen`] instead. After `cool_down`, a single request is let through as a probe:
+/// the circuit closes again if it succeeds, or stays open for another `cool_down` otherwise.
+///
+/// This protects applications (and the API) during outages. Cloning a breaker shares its
+/// state, see [`crate::client::Client::circuit_breaker`].
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cool_down: Duration,
+    endpoints: Arc<Mutex<HashMap<String, CircuitState>>>,
+}
+
+/// State of the circuit of an endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests are sent, `failures` being the number of consecutive failures so far.
+    Closed { failures: u32 },
+    /// Requests fail right away until `until`.
+    Open { until: Instant },
+    /// A probe was sent at `since`, the other requests fail right away until it is done.
+    HalfOpen { since: Instant },
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(5, Duration::from_secs(30))
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cool_down: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cool_down,
+            endpoints: Default::default(),
+        }
+    }
+
+    /// State of the circuit of `endpoint` (`/chat/completions`, ...).
+    pub fn state(&self, endpoint: &str) -> CircuitState {
+        self.endpoints()
+            .get(endpoint)
+            .copied()
+            .unwrap_or(CircuitState::Closed { failures: 0 })
+    }
+
+    /// Closes the circuit of every endpoint.
+    pub fn reset(&self) {
+        self.endpoints().clear();
+    }
+
+    /// Whether a request to `endpoint` can be sent now. A probe whose outcome was never recorded
+    /// (its request was cancelled) is replaced after `cool_down`.
+    pub(crate) fn check(&self, endpoint: &str) -> Result<(), error::CircuitOpen> {
+        let now = Instant::now();
+        let mut endpoints = self.endpoints();
+        let state = endpoints
+            .entry(endpoint.to_string())
+            .or_insert(CircuitState::Closed { failures: 0 });
+
+        let reopens_at = match *state {
+            CircuitState::Closed { .. } => return Ok(()),
+            CircuitState::Open { until } => until,
+            CircuitState::HalfOpen { since } => since + self.cool_down,
+        };
+
+        if reopens_at <= now {
+            *state = CircuitState::HalfOpen { since: now };
+
+            return Ok(());
+        }
+
+        Err(error::CircuitOpen {
+            endpoint: endpoint.to_string(),
+            retry_in: reopens_at - now,
+        })
+    }
+
+    /// Records the outcome of a request to `endpoint`.
+    pub(crate) fn record(&self, endpoint: &str, success: bool) {
+        let mut endpoints = self.endpoints();
+        let state = endpoints
+            .entry(endpoint.to_string())
+            .or_insert(CircuitState::Closed { failures: 0 });
+
+        *state = match (*state, success) {
+            (_, true) => CircuitState::Closed { failures: 0 },
+            (CircuitState::Closed { failures }, false) if failures + 1 < self.failure_threshold => {
+                CircuitState::Closed {
+                    failures: failures + 1,
+                }
+            }
+            (_, false) => CircuitState::Open {
+                until: Instant::now() + self.cool_down,
+            },
+        };
+    }
+
+    fn endpoints(&self) -> std::sync::MutexGuard<'_, HashMap<String, CircuitState>> {
+        self.endpoints.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        model::ModelId,
+        request::{ChatCompletionRequest, Request},
+        test_util::{self, chat_completions, error_response, wiremock::MockServer},
+    };
+
+    const ENDPOINT: &str = "/chat/completions";
+
+    #[test]
+    fn opens_after_consecutive_failures() {
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+
+        breaker.record(ENDPOINT, false);
+        breaker.record(ENDPOINT, false);
+
+        assert_eq!(
+            breaker.state(ENDPOINT),
+            CircuitState::Closed { failures: 2 }
+        );
+
+        breaker.record(ENDPOINT, true);
+        breaker.record(ENDPOINT, false);
+        breaker.record(ENDPOINT, false);
+
+        assert_eq!(
+            breaker.state(ENDPOINT),
+            CircuitState::Closed { failures: 2 }
+        );
+        assert!(breaker.check(ENDPOINT).is_ok());
+
+        breaker.record(ENDPOINT, false);
+
+        assert!(matches!(breaker.state(ENDPOINT), CircuitState::Open { .. }));
+        assert!(breaker.check(ENDPOINT).unwrap_err().retry_in > Duration::from_secs(59));
+        assert_eq!(
+            breaker.state("/embeddings"),
+            CircuitState::Closed { failures: 0 }
+        );
+    }
+
+    #[test]
+    fn lets_a_single_probe_through_after_cool_down() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(10));
+
+        breaker.record(ENDPOINT, false);
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert!(breaker.check(ENDPOINT).is_ok());
+        assert!(matches!(
+            breaker.state(ENDPOINT),
+            CircuitState::HalfOpen { .. }
+        ));
+        assert!(breaker.check(ENDPOINT).is_err());
+
+        breaker.record(ENDPOINT, true);
+
+        assert_eq!(
+            breaker.state(ENDPOINT),
+            CircuitState::Closed { failures: 0 }
+        );
+    }
+
+    #[test]
+    fn failed_probes_reopen_the_circuit() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(10));
+
+        breaker.record(ENDPOINT, false);
+        std::thread::sleep(Duration::from_millis(20));
+        breaker.check(ENDPOINT).unwrap();
+        breaker.record(ENDPOINT, false);
+
+        assert!(matches!(breaker.state(ENDPOINT), CircuitState::Open { .. }));
+        assert!(breaker.check(ENDPOINT).is_err());
+    }
+
+    #[test]
+    fn cancelled_probes_are_replaced_after_cool_down() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(10));
+
+        breaker.record(ENDPOINT, false);
+        std::thread::sleep(Duration::from_millis(20));
+        breaker.check(ENDPOINT).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert!(breaker.check(ENDPOINT).is_ok());
+
+        breaker.reset();
+
+        assert_eq!(
+            breaker.state(ENDPOINT),
+            CircuitState::Closed { failures: 0 }
+        );
+    }
+
+    #[tokio::test]
+    async fn open_circuits_fail_requests_without_sending_them() {
+        let server = MockServer::start().await;
+
+        chat_completions()
+            .respond_with(error_response(500, "server_error", "Oops"))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+        let client = test_util::client(&server).circuit_breaker(breaker.clone());
+        let request = ChatCompletionRequest::for_model(&client, ModelId::Gpt4o, vec![]);
+
+        for _ in 0..2 {
+            assert!(matches!(
+                request.execute().await,
+                Err(error::Error::ApiError(_))
+            ));
+        }
+
+        assert!(matches!(
+            request.execute().await,
+            Err(error::Error::CircuitOpen(e)) if e.endpoint == ENDPOINT
+        ));
+    }
+}
@@ -1,6 +1,37 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::{Debug, Formatter},
+    ops::Deref,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
 use const_format::concatcp;
 
-use crate::{error, model::Model, APIKeysAccess};
+use reqwest::header::{HeaderValue, AUTHORIZATION};
+
+use crate::{
+    auth::TokenProvider,
+    budget::Budget,
+    cache::ResponseCache,
+    catalog::ModelCatalog,
+    circuit_breaker::CircuitBreaker,
+    compat::CompatibilityRegistry,
+    concurrency::{ConcurrencyLimiter, Permit},
+    dedup::Deduplicator,
+    error,
+    metrics::{EndpointMetrics, Metrics},
+    model::{known, Model, ModelFilter, ModelId, ModelInfo, ModelList},
+    pagination::{PageParams, Paginated, Paginator},
+    pricing::PricingTable,
+    rate_limit::RateLimiter,
+    request::Usage,
+    response::ParsingMode,
+    timeout::TimeoutPolicy,
+    tls::TlsConfig,
+    usage::{UsageTotals, UsageTracker},
+    APIKeysAccess,
+};
 
 pub const BASE_URL: &str = "https://api.openai.com/v1";
 
@@ -8,10 +39,33 @@ const MODELS_LIST_URL: &str = concatcp!(BASE_URL, "/models");
 
 pub struct Client {
     api_key: String,
+    token_provider: Option<TokenProvider>,
     organization: Option<String>,
+    base_url: String,
+    allow_http: bool,
+    moderation_model: Option<ModelId>,
+    compatibility: CompatibilityRegistry,
+    model_catalog: Option<ModelCatalog>,
+    rate_limiter: Option<RateLimiter>,
+    concurrency_limiter: Option<ConcurrencyLimiter>,
+    response_cache: Option<ResponseCache>,
+    deduplicator: Option<Deduplicator>,
+    circuit_breaker: Option<CircuitBreaker>,
+    budget: Option<Budget>,
+    metrics: Metrics,
+    usage: UsageTracker,
+    pricing: PricingTable,
+    parsing_mode: ParsingMode,
+    timeout_policy: Option<TimeoutPolicy>,
+    /// Deprecated models already warned about.
+    deprecation_warnings: Arc<Mutex<HashSet<ModelId>>>,
 
+    /// Settings the blocking client is built with, see [`Client::tls`].
+    #[cfg(feature = "blocking")]
+    tls: Option<TlsConfig>,
+    /// Built on first use, as building it (and dropping it) panics in an async context.
     #[cfg(feature = "blocking")]
-    blocking_client: reqwest::blocking::Client,
+    blocking_client: std::sync::OnceLock<reqwest::blocking::Client>,
     async_client: reqwest::Client,
 }
 
@@ -19,71 +73,738 @@ impl Client {
     pub fn new(api_key: impl AsRef<str>) -> Self {
         Self {
             api_key: api_key.as_ref().to_string(),
+            token_provider: None,
             organization: None,
+            base_url: BASE_URL.to_string(),
+            allow_http: false,
+            moderation_model: None,
+            compatibility: CompatibilityRegistry::default(),
+            model_catalog: None,
+            rate_limiter: Some(RateLimiter::new()),
+            concurrency_limiter: None,
+            response_cache: None,
+            deduplicator: None,
+            circuit_breaker: None,
+            budget: None,
+            metrics: Metrics::default(),
+            usage: UsageTracker::default(),
+            pricing: PricingTable::bundled().clone(),
+            parsing_mode: ParsingMode::default(),
+            timeout_policy: None,
+            deprecation_warnings: Default::default(),
 
             #[cfg(feature = "blocking")]
-            blocking_client: reqwest::blocking::Client::new(),
+            tls: None,
+            #[cfg(feature = "blocking")]
+            blocking_client: std::sync::OnceLock::new(),
             async_client: reqwest::Client::new(),
         }
     }
 
+    /// A client authenticating with the bearer tokens of `token_provider` instead of an API key,
+    /// for Azure OpenAI deployments with key authentication disabled for example.
+    pub fn with_token_provider(token_provider: TokenProvider) -> Self {
+        Self {
+            token_provider: Some(token_provider),
+            ..Self::new("")
+        }
+    }
+
     pub fn organization(mut self, organization: impl AsRef<str>) -> Self {
         self.organization = Some(organization.as_ref().to_string());
         self
     }
 
+    /// Sends the requests to another server implementing the OpenAI API (a proxy, a mock server,
+    /// ...) instead of [`BASE_URL`]. The endpoints are resolved relative to it, so it should
+    /// include the version, like `https://api.openai.com/v1`.
+    pub fn base_url(mut self, base_url: impl AsRef<str>) -> Self {
+        self.base_url = base_url.as_ref().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Allows a plain `http://` base URL, for mock servers in tests and local gateways that don't
+    /// terminate TLS. Requests to one otherwise fail with [`error::ClientError::InsecureBaseUrl`],
+    /// as the API key would be sent in clear.
+    pub fn allow_http(mut self) -> Self {
+        self.allow_http = true;
+        self
+    }
+
+    /// Rebuilds the HTTP clients with `tls`, to present a client certificate or trust a private
+    /// certificate authority, see [`TlsConfig`]. The blocking client is built on first use, like
+    /// without `tls`. Fails if the TLS backend rejects the certificates.
+    pub fn tls(mut self, tls: TlsConfig) -> error::Result<Self> {
+        self.async_client = tls.async_client()?;
+        #[cfg(feature = "blocking")]
+        {
+            self.tls = Some(tls);
+            self.blocking_client = std::sync::OnceLock::new();
+        }
+
+        Ok(self)
+    }
+
+    /// How strictly the responses are parsed, [`ParsingMode::Relaxed`] for the providers that
+    /// deviate slightly from the schema of the OpenAI API.
+    pub fn parsing_mode(mut self, parsing_mode: ParsingMode) -> Self {
+        self.parsing_mode = parsing_mode;
+        self
+    }
+
+    pub(crate) fn parse<T: serde::de::DeserializeOwned>(
+        &self,
+        body: &[u8],
+    ) -> serde_json::Result<T> {
+        self.parsing_mode.parse(body)
+    }
+
+    /// Resolves an endpoint URL (one of the `URL`s of the requests) against the base URL.
+    pub fn resolve_url(&self, url: impl AsRef<str>) -> error::Result<String> {
+        let url = url.as_ref();
+        let url = match url.strip_prefix(BASE_URL) {
+            Some(endpoint) => format!("{}{endpoint}", self.base_url),
+            None => url.to_string(),
+        };
+
+        if url.starts_with("https://") {
+            Ok(url)
+        } else if url.starts_with("http://") {
+            match self.allow_http {
+                true => Ok(url),
+                false => Err(error::ClientError::InsecureBaseUrl(self.base_url.clone()).into()),
+            }
+        } else {
+            Err(error::ClientError::UnsupportedBaseUrl(self.base_url.clone()).into())
+        }
+    }
+
+    /// Checks the user content of the chat requests with the given moderation model before
+    /// sending them. Flagged content fails with [`error::Error::ContentFlagged`] instead of
+    /// being sent.
+    pub fn moderate_inputs(mut self, moderation_model: impl Into<ModelId>) -> Self {
+        self.moderation_model = Some(moderation_model.into());
+        self
+    }
+
+    pub(crate) fn moderation_model(&self) -> Option<&ModelId> {
+        self.moderation_model.as_ref()
+    }
+
+    /// Checks the models of the requests against `compatibility`, shared with other clients,
+    /// instead of a registry of its own.
+    pub fn compatibility_registry(mut self, compatibility: CompatibilityRegistry) -> Self {
+        self.compatibility = compatibility;
+        self
+    }
+
+    /// The models each endpoint accepts, to which models can be added, see
+    /// [`CompatibilityRegistry`].
+    pub fn compatibility(&self) -> &CompatibilityRegistry {
+        &self.compatibility
+    }
+
+    /// Keeps the `/models` list in `model_catalog` for [`Self::model`] and [`Self::models`],
+    /// instead of fetching it every time.
+    pub fn model_catalog(mut self, model_catalog: ModelCatalog) -> Self {
+        self.model_catalog = Some(model_catalog);
+        self
+    }
+
+    /// Adds the models available to the API key to the endpoints accepting them, see
+    /// [`CompatibilityRegistry::refresh`].
+    pub async fn refresh_compatibility(&self) -> error::Result<()> {
+        let models = self.list_models().await?;
+        let infos = models
+            .iter()
+            .map(|model| model.info().clone())
+            .collect::<Vec<_>>();
+
+        self.compatibility.refresh(&infos);
+
+        Ok(())
+    }
+
+    /// (Blocking) Same as [`Self::refresh_compatibility`].
+    #[cfg(feature = "blocking")]
+    pub fn refresh_compatibility_blocking(&self) -> error::Result<()> {
+        let models = self.list_models_blocking()?;
+        let infos = models
+            .iter()
+            .map(|model| model.info().clone())
+            .collect::<Vec<_>>();
+
+        self.compatibility.refresh(&infos);
+
+        Ok(())
+    }
+
+    /// Shares the rate limits tracked by `rate_limiter`, for clients using the same API key.
+    /// Every client tracks the limits of its key on its own otherwise.
+    pub fn rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Sends the requests right away even when the rate limits are exhausted, leaving the
+    /// `429` responses to the caller.
+    pub fn disable_rate_limiting(mut self) -> Self {
+        self.rate_limiter = None;
+        self
+    }
+
+    /// Limits the requests in flight at once, see [`ConcurrencyLimiter`].
+    pub fn concurrency_limiter(mut self, concurrency_limiter: ConcurrencyLimiter) -> Self {
+        self.concurrency_limiter = Some(concurrency_limiter);
+        self
+    }
+
+    /// Answers identical requests from `response_cache` instead of sending them again.
+    pub fn response_cache(mut self, response_cache: ResponseCache) -> Self {
+        self.response_cache = Some(response_cache);
+        self
+    }
+
+    pub(crate) fn cache(&self) -> Option<&ResponseCache> {
+        self.response_cache.as_ref()
+    }
+
+    /// Sends concurrent identical requests only once, see [`Deduplicator`].
+    pub fn deduplicate_requests(mut self, deduplicator: Deduplicator) -> Self {
+        self.deduplicator = Some(deduplicator);
+        self
+    }
+
+    pub(crate) fn deduplicator(&self) -> Option<&Deduplicator> {
+        self.deduplicator.as_ref()
+    }
+
+    /// Fails the requests to an endpoint right away while it keeps failing, see
+    /// [`CircuitBreaker`].
+    pub fn circuit_breaker(mut self, circuit_breaker: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
+    /// Refuses the requests going over `budget`, see [`Budget`].
+    pub fn budget(mut self, budget: Budget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Derives the timeout of each request from its `max_tokens` with `timeout_policy`, see
+    /// [`TimeoutPolicy`]. Without one, the requests without a timeout of their own have the one
+    /// of the underlying HTTP client, none for the async one.
+    pub fn timeout_policy(mut self, timeout_policy: TimeoutPolicy) -> Self {
+        self.timeout_policy = Some(timeout_policy);
+        self
+    }
+
+    pub(crate) fn timeout(&self, model: &ModelId, output_tokens: Option<u64>) -> Option<Duration> {
+        self.timeout_policy
+            .as_ref()
+            .map(|policy| policy.timeout(model.as_str(), output_tokens))
+    }
+
+    /// Counters and latencies of the requests sent so far, per endpoint.
+    pub fn metrics(&self) -> BTreeMap<String, EndpointMetrics> {
+        self.metrics.snapshot()
+    }
+
+    pub fn reset_metrics(&self) {
+        self.metrics.reset();
+    }
+
+    /// Tokens used by the responses so far, in total, per model and per tag.
+    pub fn usage(&self) -> UsageTotals {
+        self.usage.snapshot()
+    }
+
+    pub fn reset_usage(&self) {
+        self.usage.reset();
+    }
+
+    /// Prices the costs are estimated with, by [`Self::total_estimated_cost`] and the
+    /// [`Budget`], the bundled [`PricingTable`] by default.
+    pub fn pricing(mut self, pricing: PricingTable) -> Self {
+        self.pricing = pricing;
+        self
+    }
+
+    /// The prices the costs are estimated with, see [`Self::pricing`].
+    pub fn pricing_table(&self) -> &PricingTable {
+        &self.pricing
+    }
+
+    /// Estimated cost in USD of the [`Self::usage`] so far. The models without a price are left
+    /// out.
+    pub fn total_estimated_cost(&self) -> f64 {
+        self.usage()
+            .by_model
+            .iter()
+            .filter_map(|(model, usage)| self.pricing.cost(model, usage))
+            .sum()
+    }
+
+    /// Checks a request body for `model`, asking for up to `output_tokens`, against the budget
+    /// before sending it. The tokens of the body are counted with the `tokenizer` feature, and
+    /// estimated at a token per 4 bytes otherwise.
+    pub(crate) fn check_budget(
+        &self,
+        body: &[u8],
+        model: &ModelId,
+        output_tokens: Option<u64>,
+    ) -> error::Result<()> {
+        if let Some(budget) = &self.budget {
+            #[cfg(feature = "tokenizer")]
+            let prompt_tokens =
+                crate::tokens::count_tokens(&*String::from_utf8_lossy(body), model) as u64;
+            #[cfg(not(feature = "tokenizer"))]
+            let prompt_tokens = body.len() as u64 / 4;
+
+            let completion_tokens = output_tokens.unwrap_or_default();
+            let usage = Usage {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+            };
+            let cost = self
+                .pricing
+                .cost(model.as_str(), &usage)
+                .unwrap_or_default();
+
+            budget.check(usage.total_tokens, cost)?;
+        }
+
+        Ok(())
+    }
+
+    /// Warns once about `model` when it is past its announced deprecation date, see
+    /// [`known::KnownModel`].
+    pub(crate) fn warn_if_deprecated(&self, model: &ModelId) {
+        let Some(known) = model.known() else {
+            return;
+        };
+        let Some(deprecated_on) = known.deprecated_on else {
+            return;
+        };
+        let today = known::today();
+
+        if today.as_str() < deprecated_on
+            || !self
+                .deprecation_warnings
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(model.clone())
+        {
+            return;
+        }
+
+        match known.shutdown_on {
+            Some(shutdown_on) if known.is_shut_down_on(&today) => tracing::warn!(
+                model = %model,
+                deprecated_on,
+                shutdown_on,
+                "Model {model} is deprecated and was shut down on {shutdown_on}"
+            ),
+            Some(shutdown_on) => tracing::warn!(
+                model = %model,
+                deprecated_on,
+                shutdown_on,
+                "Model {model} is deprecated and will be shut down on {shutdown_on}"
+            ),
+            None => tracing::warn!(
+                model = %model,
+                deprecated_on,
+                "Model {model} is deprecated since {deprecated_on}"
+            ),
+        }
+    }
+
+    /// Accounts for a response body, with the `usage` most endpoints send.
+    pub(crate) fn record_response(&self, body: &[u8], usage_tag: Option<&str>) {
+        #[derive(serde::Deserialize)]
+        struct WithUsage {
+            usage: Option<Usage>,
+            model: Option<String>,
+        }
+
+        let Some((usage, model)) = serde_json::from_slice::<WithUsage>(body)
+            .ok()
+            .and_then(|response| Some((response.usage?, response.model)))
+        else {
+            return;
+        };
+
+        self.usage.record(usage, model.as_deref(), usage_tag);
+
+        if let Some(budget) = &self.budget {
+            let cost = model
+                .and_then(|model| self.pricing.cost(&model, &usage))
+                .unwrap_or_default();
+
+            budget.record_cost(usage.total_tokens, cost);
+        }
+    }
+
+    /// The `Authorization` header with the current token, when authenticating with a
+    /// [`TokenProvider`].
+    async fn authorization(&self) -> error::Result<Option<HeaderValue>> {
+        match &self.token_provider {
+            Some(token_provider) => bearer(&token_provider.token().await?).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// (Blocking) The `Authorization` header with the current token, when authenticating with a
+    /// [`TokenProvider`].
+    #[cfg(feature = "blocking")]
+    fn authorization_blocking(&self) -> error::Result<Option<HeaderValue>> {
+        match &self.token_provider {
+            Some(token_provider) => bearer(&token_provider.token_blocking()?).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// (Blocking) Sends a request, delaying it first if the rate limits are exhausted or too
+    /// many requests are in flight. Error statuses are returned as [`error::ApiError`].
+    #[cfg(feature = "blocking")]
+    pub(crate) fn send_blocking(
+        &self,
+        request: reqwest::blocking::RequestBuilder,
+    ) -> error::Result<SentResponse<reqwest::blocking::Response>> {
+        let mut request = request.build()?;
+        let endpoint = self.endpoint(request.url());
+
+        if let Some(authorization) = self.authorization_blocking()? {
+            request.headers_mut().insert(AUTHORIZATION, authorization);
+        }
+
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            circuit_breaker.check(&endpoint)?;
+        }
+
+        let permit = self
+            .concurrency_limiter
+            .as_ref()
+            .map(|limiter| limiter.acquire_blocking(&endpoint));
+
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.wait_blocking(&self.api_key);
+        }
+
+        let start = Instant::now();
+        let response = self.blocking_client()?.execute(request);
+
+        self.metrics.record(
+            &endpoint,
+            response.as_ref().map(|r| r.status().as_u16()).map_err(drop),
+            start.elapsed(),
+        );
+
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            circuit_breaker.record(&endpoint, is_healthy(response.as_ref().map(|r| r.status())));
+        }
+
+        let response = response?;
+
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.update(&self.api_key, response.headers());
+        }
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+
+            if status == 401 {
+                if let Some(token_provider) = &self.token_provider {
+                    token_provider.invalidate();
+                }
+            }
+
+            let headers = response.headers().clone();
+
+            return Err(error::ApiError::new(status, &headers, &response.text()?).into());
+        }
+
+        Ok(SentResponse {
+            response,
+            _permit: permit,
+        })
+    }
+
+    /// Sends a request, delaying it first if the rate limits are exhausted or too many requests
+    /// are in flight. Error statuses are returned as [`error::ApiError`].
+    pub(crate) async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> error::Result<SentResponse<reqwest::Response>> {
+        let mut request = request.build()?;
+        let endpoint = self.endpoint(request.url());
+
+        if let Some(authorization) = self.authorization().await? {
+            request.headers_mut().insert(AUTHORIZATION, authorization);
+        }
+
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            circuit_breaker.check(&endpoint)?;
+        }
+
+        let permit = match &self.concurrency_limiter {
+            Some(limiter) => Some(limiter.acquire(&endpoint).await),
+            None => None,
+        };
+
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.wait(&self.api_key).await;
+        }
+
+        let start = Instant::now();
+        let response = self.async_client.execute(request).await;
+
+        self.metrics.record(
+            &endpoint,
+            response.as_ref().map(|r| r.status().as_u16()).map_err(drop),
+            start.elapsed(),
+        );
+
+        if let Some(circuit_breaker) = &self.circuit_breaker {
+            circuit_breaker.record(&endpoint, is_healthy(response.as_ref().map(|r| r.status())));
+        }
+
+        let response = response?;
+
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.update(&self.api_key, response.headers());
+        }
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+
+            if status == 401 {
+                if let Some(token_provider) = &self.token_provider {
+                    token_provider.invalidate();
+                }
+            }
+
+            let headers = response.headers().clone();
+            let body = response.text().await?;
+
+            return Err(error::ApiError::new(status, &headers, &body).into());
+        }
+
+        Ok(SentResponse {
+            response,
+            _permit: permit,
+        })
+    }
+
+    /// The endpoint of `url` (`/chat/completions`, ...), its path without the one of the base
+    /// URL, as the limits, metrics and circuits are kept by.
+    fn endpoint(&self, url: &reqwest::Url) -> String {
+        let base_path = reqwest::Url::parse(&self.base_url)
+            .map(|base_url| base_url.path().trim_end_matches('/').to_string())
+            .unwrap_or_default();
+
+        url.path()
+            .strip_prefix(&base_path)
+            .unwrap_or(url.path())
+            .to_string()
+    }
+
+    #[cfg(feature = "blocking")]
+    pub(crate) fn blocking_client(&self) -> error::Result<&reqwest::blocking::Client> {
+        if let Some(client) = self.blocking_client.get() {
+            return Ok(client);
+        }
+
+        let client = match &self.tls {
+            Some(tls) => tls.blocking_client()?,
+            None => reqwest::blocking::Client::new(),
+        };
+
+        Ok(self.blocking_client.get_or_init(|| client))
+    }
+
+    pub(crate) fn async_client(&self) -> &reqwest::Client {
+        &self.async_client
+    }
+
+    /// Opens `connections` connections to the API ahead of the first requests, so they don't
+    /// pay for the DNS lookup and TLS handshake. The connections are kept in the pool of the
+    /// client until they idle out.
+    pub async fn warm_up(&self, connections: usize) -> error::Result<()> {
+        let url = self.resolve_url(BASE_URL)?;
+
+        // Any answer will do, the request is only sent to open the connection
+        futures_util::future::join_all(
+            (0..connections.max(1)).map(|_| self.async_client.head(&url).send()),
+        )
+        .await
+        .into_iter()
+        .try_for_each(|response| response.map(drop))?;
+
+        Ok(())
+    }
+
+    /// (Blocking) Opens a connection to the API ahead of the first request, see
+    /// [`Client::warm_up`].
+    #[cfg(feature = "blocking")]
+    pub fn warm_up_blocking(&self) -> error::Result<()> {
+        let url = self.resolve_url(BASE_URL)?;
+
+        self.blocking_client()?.head(url).send()?;
+
+        Ok(())
+    }
+
+    /// Walks a cursor paginated list endpoint, see [`Paginator`].
+    pub fn paginate<T: Paginated>(
+        &self,
+        url: impl AsRef<str>,
+        params: PageParams,
+    ) -> Paginator<'_, T> {
+        Paginator::new(self, url, params)
+    }
+
     /// (Blocking) Lists the currently available models, and provides basic information about each one such as the owner and availability.
     #[cfg(feature = "blocking")]
-    pub fn list_models_blocking(&self) -> error::Result<Vec<Model>> {
+    pub fn list_models_blocking(&self) -> error::Result<Vec<Model<'_>>> {
+        let url = self.resolve_url(MODELS_LIST_URL)?;
         let common_headers = self.common_headers();
 
-        let models_response = self
-            .blocking_client
-            .get(MODELS_LIST_URL)
-            .headers(common_headers)
-            .send()?;
+        let models_response =
+            self.send_blocking(self.blocking_client()?.get(url).headers(common_headers))?;
 
-        let json = models_response.json::<serde_json::Value>()?;
-        let data = self.models_from_response_json(json)?;
+        let list = self.parse::<ModelList>(&models_response.bytes()?)?;
 
-        Ok(data)
+        Ok(self.models_from_list(list))
     }
 
     /// Lists the currently available models, and provides basic information about each one such as the owner and availability.
-    pub async fn list_models(&self) -> error::Result<Vec<Model>> {
+    pub async fn list_models(&self) -> error::Result<Vec<Model<'_>>> {
+        let url = self.resolve_url(MODELS_LIST_URL)?;
         let common_headers = self.common_headers();
 
         let models_response = self
-            .async_client
-            .get(MODELS_LIST_URL)
-            .headers(common_headers)
-            .send()
+            .send(self.async_client.get(url).headers(common_headers))
             .await?;
 
-        let json = models_response.json::<serde_json::Value>().await?;
-        let data = self.models_from_response_json(json)?;
+        let list = self.parse::<ModelList>(&models_response.bytes().await?)?;
+
+        Ok(self.models_from_list(list))
+    }
+
+    /// The models available to the API key, from the [`ModelCatalog`] of the client if it has
+    /// one and they haven't expired, from [`Self::list_models`] otherwise. Fetching them adds
+    /// them to the [`Self::compatibility`] registry.
+    pub async fn models(&self) -> error::Result<Vec<Model<'_>>> {
+        let Some(catalog) = &self.model_catalog else {
+            return self.list_models().await;
+        };
+        let infos = match catalog.get() {
+            Some(infos) => infos,
+            None => self.store_models(catalog, self.list_models().await?),
+        };
+
+        Ok(self.models_from_infos(&infos))
+    }
+
+    /// (Blocking) Same as [`Self::models`].
+    #[cfg(feature = "blocking")]
+    pub fn models_blocking(&self) -> error::Result<Vec<Model<'_>>> {
+        let Some(catalog) = &self.model_catalog else {
+            return self.list_models_blocking();
+        };
+        let infos = match catalog.get() {
+            Some(infos) => infos,
+            None => self.store_models(catalog, self.list_models_blocking()?),
+        };
+
+        Ok(self.models_from_infos(&infos))
+    }
+
+    /// The models of [`Self::models`] matching `filter` (by owner, id prefix and capability), in
+    /// its order.
+    pub async fn list_models_filtered(
+        &self,
+        filter: &ModelFilter,
+    ) -> error::Result<Vec<Model<'_>>> {
+        Ok(filter.apply(self.models().await?))
+    }
+
+    /// (Blocking) Same as [`Self::list_models_filtered`].
+    #[cfg(feature = "blocking")]
+    pub fn list_models_filtered_blocking(
+        &self,
+        filter: &ModelFilter,
+    ) -> error::Result<Vec<Model<'_>>> {
+        Ok(filter.apply(self.models_blocking()?))
+    }
+
+    /// The model `model_id` from [`Self::models`] with a [`ModelCatalog`], failing with
+    /// [`error::ModelError::NotFound`] if it isn't available. Retrieved with
+    /// [`Self::retrieve_model_info`] without one.
+    pub async fn model(&self, model_id: impl Into<ModelId>) -> error::Result<Model<'_>> {
+        let model_id = model_id.into();
+
+        if self.model_catalog.is_none() {
+            return self.retrieve_model_info(model_id).await;
+        }
+
+        self.models()
+            .await?
+            .into_iter()
+            .find(|model| *model.id() == model_id)
+            .ok_or_else(|| error::ModelError::NotFound(model_id).into())
+    }
+
+    /// (Blocking) Same as [`Self::model`].
+    #[cfg(feature = "blocking")]
+    pub fn model_blocking(&self, model_id: impl Into<ModelId>) -> error::Result<Model<'_>> {
+        let model_id = model_id.into();
+
+        if self.model_catalog.is_none() {
+            return self.retrieve_model_info_blocking(model_id);
+        }
+
+        self.models_blocking()?
+            .into_iter()
+            .find(|model| *model.id() == model_id)
+            .ok_or_else(|| error::ModelError::NotFound(model_id).into())
+    }
+
+    fn store_models(&self, catalog: &ModelCatalog, models: Vec<Model<'_>>) -> Arc<[ModelInfo]> {
+        let infos = models.into_iter().map(Model::into_info).collect::<Vec<_>>();
+
+        self.compatibility.refresh(&infos);
 
-        Ok(data)
+        catalog.store(infos)
     }
 
-    fn models_from_response_json(&self, json: serde_json::Value) -> error::Result<Vec<Model>> {
-        json.get("data")
-            .and_then(|v| v.as_array())
-            .ok_or(error::ParseError::FieldNotFound("data".to_string()).into())
-            .and_then(|arr| {
-                arr.iter()
-                    .map(|v| {
-                        Model::new_parse_json(
-                            &self.api_key,
-                            &self.organization,
-                            #[cfg(feature = "blocking")]
-                            &self.blocking_client,
-                            &self.async_client,
-                            v,
-                        )
-                    })
-                    .collect::<error::Result<Vec<Model>>>()
-            })
+    fn models_from_infos(&self, infos: &[ModelInfo]) -> Vec<Model<'_>> {
+        infos
+            .iter()
+            .map(|info| self.model_from_info(info.clone()))
+            .collect()
+    }
+
+    /// Creates a model handle for the given id without contacting the API.
+    ///
+    /// Only the id of the returned model is known, see [`ModelInfo::from_id`]. Use
+    /// [`Client::retrieve_model_info`] when the rest of the info is needed.
+    pub fn model_from_id(&self, model_id: impl Into<ModelId>) -> Model<'_> {
+        self.model_from_info(ModelInfo::from_id(model_id))
+    }
+
+    fn models_from_list(&self, list: ModelList) -> Vec<Model<'_>> {
+        list.data
+            .into_iter()
+            .map(|info| self.model_from_info(info))
+            .collect()
+    }
+
+    fn model_from_info(&self, info: ModelInfo) -> Model<'_> {
+        Model::new(self, info)
     }
 
     /// (Blocking) Retrieves a model instance, providing basic information about the model such as the owner
@@ -94,26 +815,19 @@ impl Client {
     /// * `model_id`: The ID of the model to use for this request
     ///
     #[cfg(feature = "blocking")]
-    pub fn retrieve_model_info_blocking(&self, model_id: impl AsRef<str>) -> error::Result<Model> {
-        let url = format!("{MODELS_LIST_URL}/{}", model_id.as_ref());
+    pub fn retrieve_model_info_blocking(
+        &self,
+        model_id: impl AsRef<str>,
+    ) -> error::Result<Model<'_>> {
+        let url = self.resolve_url(format!("{MODELS_LIST_URL}/{}", model_id.as_ref()))?;
         let common_headers = self.common_headers();
 
-        let json = self
-            .blocking_client
-            .get(url)
-            .headers(common_headers)
-            .send()?
-            .json::<serde_json::Value>()?;
-        let data = Model::new_parse_json(
-            &self.api_key,
-            &self.organization,
-            #[cfg(feature = "blocking")]
-            &self.blocking_client,
-            &self.async_client,
-            &json,
-        )?;
+        let body = self
+            .send_blocking(self.blocking_client()?.get(url).headers(common_headers))?
+            .bytes()?;
+        let info = self.parse::<ModelInfo>(&body)?;
 
-        Ok(data)
+        Ok(self.model_from_info(info))
     }
 
     /// Retrieves a model instance, providing basic information about the model such as the owner
@@ -123,28 +837,83 @@ impl Client {
     ///
     /// * `model_id`: The ID of the model to use for this request
     ///
-    pub async fn retrieve_model_info(&self, model_id: impl AsRef<str>) -> error::Result<Model> {
-        let url = format!("{MODELS_LIST_URL}/{}", model_id.as_ref());
+    pub async fn retrieve_model_info(&self, model_id: impl AsRef<str>) -> error::Result<Model<'_>> {
+        let url = self.resolve_url(format!("{MODELS_LIST_URL}/{}", model_id.as_ref()))?;
         let common_headers = self.common_headers();
 
-        let json = self
-            .async_client
-            .get(url)
-            .headers(common_headers)
-            .send()
+        let body = self
+            .send(self.async_client.get(url).headers(common_headers))
             .await?
-            .json::<serde_json::Value>()
+            .bytes()
             .await?;
-        let data = Model::new_parse_json(
-            &self.api_key,
-            &self.organization,
-            #[cfg(feature = "blocking")]
-            &self.blocking_client,
-            &self.async_client,
-            &json,
-        )?;
+        let info = self.parse::<ModelInfo>(&body)?;
 
-        Ok(data)
+        Ok(self.model_from_info(info))
+    }
+}
+
+/// A successful response, holding its slot of the [`ConcurrencyLimiter`] until its body is read.
+pub(crate) struct SentResponse<R> {
+    response: R,
+    _permit: Option<Permit>,
+}
+
+impl<R> Deref for SentResponse<R> {
+    type Target = R;
+
+    fn deref(&self) -> &Self::Target {
+        &self.response
+    }
+}
+
+impl SentResponse<reqwest::Response> {
+    pub(crate) async fn bytes(self) -> reqwest::Result<Vec<u8>> {
+        self.response.bytes().await.map(Vec::from)
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl SentResponse<reqwest::blocking::Response> {
+    pub(crate) fn bytes(self) -> reqwest::Result<Vec<u8>> {
+        self.response.bytes().map(Vec::from)
+    }
+}
+
+/// The sensitive `Authorization` header of a bearer `token`.
+fn bearer(token: &str) -> error::Result<HeaderValue> {
+    let mut authorization = HeaderValue::from_str(&format!("Bearer {token}"))
+        .map_err(|e| error::ClientError::TokenUnavailable(e.into()))?;
+
+    authorization.set_sensitive(true);
+
+    Ok(authorization)
+}
+
+/// Whether the outcome of a request says the endpoint works, client errors (4xx) being the
+/// fault of the request.
+fn is_healthy(status: Result<reqwest::StatusCode, &reqwest::Error>) -> bool {
+    status.is_ok_and(|status| !status.is_server_error())
+}
+
+impl Debug for Client {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Client")
+            .field("token_provider", &self.token_provider)
+            .field("organization", &self.organization)
+            .field("base_url", &self.base_url)
+            .field("allow_http", &self.allow_http)
+            .field("moderation_model", &self.moderation_model)
+            .field("model_catalog", &self.model_catalog)
+            .field("rate_limiter", &self.rate_limiter.is_some())
+            .field("concurrency_limiter", &self.concurrency_limiter)
+            .field("response_cache", &self.response_cache)
+            .field("deduplicator", &self.deduplicator)
+            .field("circuit_breaker", &self.circuit_breaker)
+            .field("budget", &self.budget)
+            .field("pricing", &self.pricing)
+            .field("parsing_mode", &self.parsing_mode)
+            .field("timeout_policy", &self.timeout_policy)
+            .finish_non_exhaustive()
     }
 }
 
@@ -157,3 +926,96 @@ impl APIKeysAccess for Client {
         &self.organization
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::{
+        request::{Request, TextCompletionRequest},
+        test_util::{self, fixtures, json_response, wiremock::MockServer},
+    };
+
+    /// Logs written by the current thread, until the guard is dropped.
+    #[derive(Clone, Default)]
+    struct Logs(Arc<Mutex<Vec<u8>>>);
+
+    impl Logs {
+        fn capture() -> (Self, tracing::subscriber::DefaultGuard) {
+            let logs = Self::default();
+            let writer = logs.clone();
+            let subscriber = tracing_subscriber::fmt()
+                .with_writer(move || writer.clone())
+                .with_ansi(false)
+                .finish();
+
+            (logs, tracing::subscriber::set_default(subscriber))
+        }
+
+        fn text(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    impl Write for Logs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn warns_once_per_deprecated_model() {
+        let client = Client::new("sk-test");
+        let (logs, _guard) = Logs::capture();
+
+        client.warn_if_deprecated(&ModelId::TextDavinci003);
+        client.warn_if_deprecated(&ModelId::TextDavinci003);
+        client.warn_if_deprecated(&ModelId::from("ft:text-davinci-003:org::id".to_string()));
+
+        let logs = logs.text();
+
+        assert_eq!(logs.lines().count(), 2, "{logs}");
+        assert!(logs.contains("WARN"));
+        assert!(
+            logs.contains("Model text-davinci-003 is deprecated and was shut down on 2024-01-04")
+        );
+        assert!(logs.contains("Model ft:text-davinci-003:org::id is deprecated"));
+    }
+
+    #[test]
+    fn doesnt_warn_about_current_models() {
+        let client = Client::new("sk-test");
+        let (logs, _guard) = Logs::capture();
+
+        client.warn_if_deprecated(&ModelId::Gpt4o);
+        client.warn_if_deprecated(&ModelId::from("gpt-4o-2024-08-06".to_string()));
+        client.warn_if_deprecated(&ModelId::from("unknown-model".to_string()));
+
+        assert_eq!(logs.text(), "");
+    }
+
+    #[tokio::test]
+    async fn requests_to_deprecated_models_warn() {
+        let server = MockServer::start().await;
+
+        test_util::completions()
+            .respond_with(json_response(fixtures::text_completion("Hi")))
+            .mount(&server)
+            .await;
+
+        let client = test_util::client(&server);
+        let (logs, _guard) = Logs::capture();
+
+        TextCompletionRequest::for_model(&client, ModelId::TextDavinci003)
+            .execute()
+            .await
+            .unwrap();
+
+        assert!(logs.text().contains("text-davinci-003 is deprecated"));
+    }
+}
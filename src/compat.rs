@@ -0,0 +1,309 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    images::IMAGE_GENERATIONS_URL,
+    model::{ModelId, ModelInfo},
+    request::{
+        ChatCompletionRequest, EditRequest, EmbeddingRequest, ModerationRequest, Request,
+        TextCompletionRequest,
+    },
+    transcription::TRANSCRIPTIONS_URL,
+};
+
+/// The endpoints serving the models of a family (the ids starting with it), for the ones the
+/// chat capabilities don't cover.
+const FAMILY_ENDPOINTS: &[(&str, &str)] = &[
+    ("text-embedding-", EmbeddingRequest::URL),
+    ("text-moderation-", ModerationRequest::URL),
+    ("omni-moderation-", ModerationRequest::URL),
+    ("gpt-3.5-turbo-instruct", TextCompletionRequest::URL),
+    ("davinci-002", TextCompletionRequest::URL),
+    ("babbage-002", TextCompletionRequest::URL),
+    ("whisper-", TRANSCRIPTIONS_URL),
+    ("dall-e-", IMAGE_GENERATIONS_URL),
+    ("gpt-image-", IMAGE_GENERATIONS_URL),
+];
+
+/// The models each endpoint accepts, checked before a request is sent. See
+/// [`crate::client::Client::compatibility`].
+///
+/// An endpoint starts with the `COMPATIBLE_MODELS` of its request type, to which models can be
+/// added at runtime: newly released ones, the ones of a compatible provider, or all of them. The
+/// dated snapshots (`gpt-4o-2024-08-06`) and the fine-tuned models (`ft:gpt-4o-mini:org::id`)
+/// of an accepted model are accepted as well.
+///
+/// Cloning a registry shares its models.
+#[derive(Debug, Clone)]
+pub struct CompatibilityRegistry {
+    endpoints: Arc<RwLock<HashMap<String, EndpointModels>>>,
+}
+
+#[derive(Debug, Default)]
+struct EndpointModels {
+    models: HashSet<ModelId>,
+    any: bool,
+    /// Whether the `COMPATIBLE_MODELS` of the request type were added.
+    seeded: bool,
+}
+
+impl Default for CompatibilityRegistry {
+    fn default() -> Self {
+        let endpoints = [
+            (
+                TextCompletionRequest::URL,
+                TextCompletionRequest::COMPATIBLE_MODELS,
+            ),
+            (
+                ChatCompletionRequest::URL,
+                ChatCompletionRequest::COMPATIBLE_MODELS,
+            ),
+            (EditRequest::URL, EditRequest::COMPATIBLE_MODELS),
+            (ModerationRequest::URL, ModerationRequest::COMPATIBLE_MODELS),
+            (EmbeddingRequest::URL, EmbeddingRequest::COMPATIBLE_MODELS),
+        ]
+        .into_iter()
+        .map(|(url, models)| (url.to_string(), EndpointModels::new(models)))
+        .collect();
+
+        Self {
+            endpoints: Arc::new(RwLock::new(endpoints)),
+        }
+    }
+}
+
+impl CompatibilityRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accepts `model` on the endpoint `url`, the `URL` of a request type.
+    pub fn allow(&self, url: &str, model: impl Into<ModelId>) {
+        self.write()
+            .entry(url.to_string())
+            .or_default()
+            .models
+            .insert(model.into());
+    }
+
+    /// Accepts every model on the endpoint `url`, leaving the check to the server.
+    pub fn allow_all(&self, url: &str) {
+        self.write().entry(url.to_string()).or_default().any = true;
+    }
+
+    /// Whether the endpoint `url`, seeded with `defaults` the first time, accepts `model`.
+    pub fn is_compatible(&self, url: &str, defaults: &[ModelId], model: &ModelId) -> bool {
+        if let Some(endpoint) = self.read().get(url).filter(|endpoint| endpoint.seeded) {
+            return endpoint.accepts(model.as_str());
+        }
+
+        let mut endpoints = self.write();
+        let endpoint = endpoints.entry(url.to_string()).or_default();
+
+        if !endpoint.seeded {
+            endpoint.models.extend(defaults.iter().cloned());
+            endpoint.seeded = true;
+        }
+
+        endpoint.accepts(model.as_str())
+    }
+
+    /// Adds the models of a `/models` listing to the endpoints serving them: the chat models
+    /// (see [`ModelId::capabilities`]) to the chat completions, the `text-embedding-*` ones to the
+    /// embeddings, and so on. The models an endpoint accepts the parent of are added to it too,
+    /// the fine-tuned models the API names without their base model for example.
+    pub fn refresh(&self, models: &[ModelInfo]) {
+        let mut endpoints = self.write();
+
+        for info in models {
+            for url in serving_endpoints(info.id()) {
+                endpoints
+                    .entry(url.to_string())
+                    .or_default()
+                    .models
+                    .insert(info.id().clone());
+            }
+        }
+
+        for endpoint in endpoints.values_mut() {
+            let accepted = models
+                .iter()
+                .filter(|info| {
+                    info.parent()
+                        .as_deref()
+                        .is_some_and(|parent| endpoint.accepts(parent))
+                })
+                .map(|info| info.id().clone())
+                .collect::<Vec<_>>();
+
+            endpoint.models.extend(accepted);
+        }
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, HashMap<String, EndpointModels>> {
+        self.endpoints.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, HashMap<String, EndpointModels>> {
+        self.endpoints.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The endpoints serving `model`, from its capabilities and family.
+fn serving_endpoints(model: &ModelId) -> impl Iterator<Item = &'static str> + '_ {
+    let chat = model
+        .capabilities()
+        .is_some_and(|capabilities| capabilities.supports_chat)
+        .then_some(ChatCompletionRequest::URL);
+
+    chat.into_iter().chain(
+        FAMILY_ENDPOINTS
+            .iter()
+            .filter(|(family, _)| model.as_str().starts_with(family))
+            .map(|(_, url)| *url),
+    )
+}
+
+impl EndpointModels {
+    fn new(models: &[ModelId]) -> Self {
+        Self {
+            models: models.iter().cloned().collect(),
+            any: false,
+            seeded: true,
+        }
+    }
+
+    fn accepts(&self, id: &str) -> bool {
+        if self.any || self.models.iter().any(|model| model.as_str() == id) {
+            return true;
+        }
+
+        if let Some(base) = id
+            .strip_prefix("ft:")
+            .and_then(|fine_tuned| fine_tuned.split(':').next())
+        {
+            return self.accepts(base);
+        }
+
+        // Dated snapshots, like `gpt-4o-2024-08-06` or `gpt-4-0613`
+        self.models.iter().any(|model| {
+            id.strip_prefix(model.as_str())
+                .and_then(|rest| rest.strip_prefix('-'))
+                .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        request::chat_completion::ChatMessage,
+        test_util::{self, fixtures, json_response, wiremock::MockServer},
+    };
+
+    fn accepts(registry: &CompatibilityRegistry, url: &str, id: &str) -> bool {
+        registry.is_compatible(url, &[], &ModelId::from(id.to_string()))
+    }
+
+    #[test]
+    fn refreshes_every_serving_endpoint() {
+        let registry = CompatibilityRegistry::new();
+        let ids = [
+            "gpt-4.1",
+            "text-embedding-4-small",
+            "omni-moderation-2024-09-26",
+            "gpt-3.5-turbo-instruct",
+            "whisper-2",
+            "gpt-image-2",
+            "some-unknown-model",
+        ];
+
+        assert!(!accepts(&registry, ChatCompletionRequest::URL, "gpt-4.1"));
+
+        registry.refresh(&ids.map(ModelInfo::from_id));
+
+        assert!(accepts(&registry, ChatCompletionRequest::URL, "gpt-4.1"));
+        assert!(accepts(
+            &registry,
+            ChatCompletionRequest::URL,
+            "gpt-4.1-2025-04-14"
+        ));
+        assert!(!accepts(&registry, EmbeddingRequest::URL, "gpt-4.1"));
+        assert!(accepts(
+            &registry,
+            EmbeddingRequest::URL,
+            "text-embedding-4-small"
+        ));
+        assert!(accepts(
+            &registry,
+            ModerationRequest::URL,
+            "omni-moderation-2024-09-26"
+        ));
+        assert!(accepts(
+            &registry,
+            TextCompletionRequest::URL,
+            "gpt-3.5-turbo-instruct"
+        ));
+        assert!(accepts(&registry, TRANSCRIPTIONS_URL, "whisper-2"));
+        assert!(accepts(&registry, IMAGE_GENERATIONS_URL, "gpt-image-2"));
+
+        for url in [
+            ChatCompletionRequest::URL,
+            TextCompletionRequest::URL,
+            EmbeddingRequest::URL,
+        ] {
+            assert!(!accepts(&registry, url, "some-unknown-model"));
+        }
+    }
+
+    #[test]
+    fn lazily_seeded_endpoints_keep_their_defaults() {
+        let registry = CompatibilityRegistry::new();
+
+        registry.refresh(&[ModelInfo::from_id("whisper-2")]);
+
+        assert!(registry.is_compatible(
+            TRANSCRIPTIONS_URL,
+            &[ModelId::Whisper1],
+            &ModelId::Whisper1
+        ));
+        assert!(accepts(&registry, TRANSCRIPTIONS_URL, "whisper-2"));
+    }
+
+    #[tokio::test]
+    async fn new_models_are_executable_after_a_refresh() {
+        let server = MockServer::start().await;
+
+        test_util::models()
+            .respond_with(json_response(fixtures::model_list([ModelId::from(
+                "gpt-4.1".to_string(),
+            )])))
+            .mount(&server)
+            .await;
+        test_util::mock_chat_completion(&server, "Hello").await;
+
+        let client = test_util::client(&server);
+        let request = || {
+            ChatCompletionRequest::for_model(
+                &client,
+                "gpt-4.1".to_string(),
+                vec![ChatMessage::user("Hi")],
+            )
+        };
+
+        assert!(matches!(
+            request().execute().await,
+            Err(crate::error::Error::ModelError(_))
+        ));
+
+        client.refresh_compatibility().await.unwrap();
+
+        assert_eq!(
+            request().execute().await.unwrap().first_text().as_deref(),
+            Some("Hello")
+        );
+    }
+}
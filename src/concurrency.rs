@@ -0,0 +1,293 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Condvar, Mutex},
+};
+
+use tokio::sync::Notify;
+
+/// Limits how many requests are in flight at once, in total and per endpoint, so bulk jobs
+/// (embedding a whole corpus, ...) don't exhaust the connection pool or the requests per minute
+/// of the account. The requests over the limits wait for a slot before being sent.
+///
+/// No limit is set by default. Cloning a limiter shares its slots, to limit several clients
+/// together, see [`crate::client::Client::concurrency_limiter`].
+#[derive(Debug, Clone, Default)]
+pub struct ConcurrencyLimiter {
+    total: Option<Arc<Slots>>,
+    per_endpoint: Option<usize>,
+    endpoints: Arc<Mutex<HashMap<String, Arc<Slots>>>>,
+}
+
+/// Holds the slots of a request, releasing them when dropped.
+#[derive(Debug)]
+pub struct Permit {
+    slots: Vec<Arc<Slots>>,
+}
+
+#[derive(Debug)]
+struct Slots {
+    max: usize,
+    in_flight: Mutex<usize>,
+    released: Condvar,
+    notify: Notify,
+}
+
+impl ConcurrencyLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// At most `max` requests in flight, whatever their endpoint.
+    pub fn max_in_flight(mut self, max: usize) -> Self {
+        self.total = Some(Arc::new(Slots::new(max)));
+        self
+    }
+
+    /// At most `max` requests in flight to each endpoint (`/chat/completions`, `/embeddings`,
+    /// ...).
+    pub fn max_in_flight_per_endpoint(mut self, max: usize) -> Self {
+        self.per_endpoint = Some(max);
+        self
+    }
+
+    /// Number of requests in flight, in total if there is a limit for it.
+    pub fn in_flight(&self) -> Option<usize> {
+        self.total.as_ref().map(|slots| slots.in_flight())
+    }
+
+    /// Waits for a slot to send a request to `endpoint`. Cancelling the wait gives back the slots
+    /// already taken.
+    pub async fn acquire(&self, endpoint: &str) -> Permit {
+        let mut permit = Permit { slots: Vec::new() };
+
+        for slot in self.slots(endpoint) {
+            slot.take().await;
+            permit.slots.push(slot);
+        }
+
+        permit
+    }
+
+    /// (Blocking) Waits for a slot to send a request to `endpoint`.
+    #[cfg(feature = "blocking")]
+    pub fn acquire_blocking(&self, endpoint: &str) -> Permit {
+        let slots = self.slots(endpoint);
+
+        for slot in &slots {
+            slot.take_blocking();
+        }
+
+        Permit { slots }
+    }
+
+    /// The slots to take for `endpoint`, always the ones of the endpoint first: the requests
+    /// queued for a busy endpoint then don't hold total slots the other endpoints could use, and
+    /// two requests never wait on each other.
+    ///
+    /// The slots of the endpoints without requests in flight or waiting are dropped on the way,
+    /// so paths with ids (`/models/{id}`, ...) don't pile up.
+    fn slots(&self, endpoint: &str) -> Vec<Arc<Slots>> {
+        let endpoint_slots = self.per_endpoint.map(|max| {
+            let mut endpoints = self.endpoints.lock().unwrap_or_else(|e| e.into_inner());
+
+            endpoints.retain(|_, slots| Arc::strong_count(slots) > 1);
+            endpoints
+                .entry(endpoint.to_string())
+                .or_insert_with(|| Arc::new(Slots::new(max)))
+                .clone()
+        });
+
+        endpoint_slots
+            .into_iter()
+            .chain(self.total.iter().cloned())
+            .collect()
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.slots.iter().rev().for_each(|slots| slots.release());
+    }
+}
+
+impl Slots {
+    fn new(max: usize) -> Self {
+        Self {
+            max: max.max(1),
+            in_flight: Mutex::new(0),
+            released: Condvar::new(),
+            notify: Notify::new(),
+        }
+    }
+
+    fn in_flight(&self) -> usize {
+        *self.in_flight.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn try_take(&self) -> bool {
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+
+        if *in_flight < self.max {
+            *in_flight += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    async fn take(&self) {
+        loop {
+            // Created before trying, a release in between is then not missed
+            let released = self.notify.notified();
+
+            if self.try_take() {
+                return;
+            }
+
+            released.await;
+        }
+    }
+
+    #[cfg(feature = "blocking")]
+    fn take_blocking(&self) {
+        let in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        let mut in_flight = self
+            .released
+            .wait_while(in_flight, |in_flight| *in_flight >= self.max)
+            .unwrap_or_else(|e| e.into_inner());
+
+        *in_flight += 1;
+    }
+
+    fn release(&self) {
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+
+        *in_flight = in_flight.saturating_sub(1);
+
+        drop(in_flight);
+
+        self.released.notify_one();
+        self.notify.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::test_util::{
+        self, fixtures, json_response,
+        wiremock::{matchers::path, Mock, MockServer},
+    };
+
+    async fn acquired(limiter: &ConcurrencyLimiter, endpoint: &str) -> Option<Permit> {
+        tokio::time::timeout(Duration::from_millis(50), limiter.acquire(endpoint))
+            .await
+            .ok()
+    }
+
+    #[tokio::test]
+    async fn waits_for_a_slot() {
+        let limiter = ConcurrencyLimiter::new()
+            .max_in_flight(2)
+            .max_in_flight_per_endpoint(1);
+
+        let chat = acquired(&limiter, "/chat/completions").await.unwrap();
+        let embeddings = acquired(&limiter, "/embeddings").await.unwrap();
+
+        assert_eq!(limiter.in_flight(), Some(2));
+        assert!(acquired(&limiter, "/moderations").await.is_none());
+
+        drop(embeddings);
+
+        assert!(acquired(&limiter, "/chat/completions").await.is_none());
+        assert!(acquired(&limiter, "/moderations").await.is_some());
+
+        drop(chat);
+
+        assert_eq!(limiter.in_flight(), Some(0));
+    }
+
+    #[tokio::test]
+    async fn requests_queued_for_an_endpoint_dont_block_the_others() {
+        let limiter = ConcurrencyLimiter::new()
+            .max_in_flight(2)
+            .max_in_flight_per_endpoint(1);
+        let chat = acquired(&limiter, "/chat/completions").await.unwrap();
+        let queued = tokio::spawn({
+            let limiter = limiter.clone();
+
+            async move { limiter.acquire("/chat/completions").await }
+        });
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let embeddings = acquired(&limiter, "/embeddings").await.unwrap();
+
+        assert_eq!(limiter.in_flight(), Some(2));
+        assert!(!queued.is_finished());
+
+        drop(chat);
+        drop(embeddings);
+        drop(queued.await.unwrap());
+
+        assert_eq!(limiter.in_flight(), Some(0));
+    }
+
+    #[tokio::test]
+    async fn drops_the_idle_endpoints() {
+        let limiter = ConcurrencyLimiter::new().max_in_flight_per_endpoint(1);
+        let held = acquired(&limiter, "/models/held").await.unwrap();
+
+        for id in 0..100 {
+            drop(acquired(&limiter, &format!("/models/{id}")).await.unwrap());
+        }
+
+        let endpoints = limiter
+            .endpoints
+            .lock()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        assert_eq!(endpoints.len(), 2);
+        assert!(endpoints.contains(&"/models/held".to_string()));
+
+        drop(held);
+    }
+
+    #[tokio::test]
+    async fn holds_the_slot_until_the_body_is_read() {
+        let server = MockServer::start().await;
+
+        Mock::given(path("/v1/chat/completions"))
+            .respond_with(json_response(fixtures::chat_completion("Hello")))
+            .mount(&server)
+            .await;
+
+        let limiter = ConcurrencyLimiter::new()
+            .max_in_flight(1)
+            .max_in_flight_per_endpoint(1);
+        let client = test_util::client(&server)
+            .base_url(format!("{}/v1", server.uri()))
+            .concurrency_limiter(limiter.clone());
+        let url = format!("{}/v1/chat/completions", server.uri());
+
+        let response = client.send(client.async_client().post(url)).await.unwrap();
+
+        assert_eq!(limiter.in_flight(), Some(1));
+        // Kept by endpoint, without the path of the base URL
+        assert!(limiter
+            .endpoints
+            .lock()
+            .unwrap()
+            .contains_key("/chat/completions"));
+
+        response.bytes().await.unwrap();
+
+        assert_eq!(limiter.in_flight(), Some(0));
+        assert!(client.metrics().contains_key("/chat/completions"));
+    }
+}
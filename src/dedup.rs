@@ -0,0 +1,239 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Condvar, Mutex},
+};
+
+use tokio::sync::Notify;
+
+use crate::response::ResponseMeta;
+
+/// Coalesces concurrent identical requests, so only the first one is sent and the others get
+/// its response once it arrives, like the same prompt sent by several UI components at once.
+/// Identical means the same as for [`crate::cache::ResponseCache`].
+///
+/// When the first request fails (or is cancelled), the others are sent on their own instead.
+/// Cloning a deduplicator shares the requests in flight, see
+/// [`crate::client::Client::deduplicate_requests`].
+#[derive(Debug, Clone, Default)]
+pub struct Deduplicator {
+    in_flight: Arc<Mutex<HashMap<String, Arc<Call>>>>,
+}
+
+/// Body and metadata of the response of a shared call.
+pub(crate) type Shared = (Arc<[u8]>, ResponseMeta);
+
+#[derive(Debug, Default)]
+pub(crate) struct Call {
+    /// [None] while in flight, then the response if the call succeeded.
+    outcome: Mutex<Option<Option<Shared>>>,
+    done: Condvar,
+    notify: Notify,
+}
+
+/// The part a request plays in a call.
+pub(crate) enum Role {
+    /// Sends the request and shares its response.
+    Leader(Leader),
+    /// Waits for the response of the leader.
+    Follower(Arc<Call>),
+}
+
+/// Completes the call when dropped, without a response if [`Leader::complete`] wasn't called.
+pub(crate) struct Leader {
+    deduplicator: Deduplicator,
+    key: String,
+    call: Arc<Call>,
+}
+
+impl Deduplicator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of distinct requests in flight.
+    pub fn in_flight(&self) -> usize {
+        self.calls().len()
+    }
+
+    pub(crate) fn join(&self, key: &str) -> Role {
+        let mut calls = self.calls();
+
+        if let Some(call) = calls.get(key) {
+            return Role::Follower(call.clone());
+        }
+
+        let call = Arc::new(Call::default());
+
+        calls.insert(key.to_string(), call.clone());
+
+        Role::Leader(Leader {
+            deduplicator: self.clone(),
+            key: key.to_string(),
+            call,
+        })
+    }
+
+    fn calls(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<Call>>> {
+        self.in_flight.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Call {
+    fn outcome(&self) -> std::sync::MutexGuard<'_, Option<Option<Shared>>> {
+        self.outcome.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The response of the leader, [None] if it failed.
+    pub(crate) async fn wait(&self) -> Option<Shared> {
+        loop {
+            // Created before checking, so the completion in between is not missed
+            let done = self.notify.notified();
+
+            if let Some(outcome) = self.outcome().clone() {
+                return outcome;
+            }
+
+            done.await;
+        }
+    }
+
+    /// (Blocking) The response of the leader, [None] if it failed.
+    #[cfg(feature = "blocking")]
+    pub(crate) fn wait_blocking(&self) -> Option<Shared> {
+        let outcome = self
+            .done
+            .wait_while(self.outcome(), |outcome| outcome.is_none())
+            .unwrap_or_else(|e| e.into_inner());
+
+        outcome.clone().flatten()
+    }
+}
+
+impl Leader {
+    pub(crate) fn complete(self, shared: Shared) {
+        self.finish(Some(shared));
+    }
+
+    fn finish(&self, shared: Option<Shared>) {
+        let mut outcome = self.call.outcome();
+
+        if outcome.is_some() {
+            return;
+        }
+
+        *outcome = Some(shared);
+
+        drop(outcome);
+
+        self.deduplicator.calls().remove(&self.key);
+        self.call.done.notify_all();
+        self.call.notify.notify_waiters();
+    }
+}
+
+impl Drop for Leader {
+    fn drop(&mut self) {
+        self.finish(None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures_util::future::join_all;
+
+    use super::*;
+    use crate::{
+        client::Client,
+        model::ModelId,
+        request::{chat_completion::ChatMessage, ChatCompletionRequest, Request},
+        test_util::wiremock::MockServer,
+        test_util::{self, chat_completions, error_response, fixtures, json_response},
+    };
+
+    const DELAY: Duration = Duration::from_millis(200);
+
+    fn client(server: &MockServer, deduplicator: &Deduplicator) -> Client {
+        test_util::client(server).deduplicate_requests(deduplicator.clone())
+    }
+
+    fn request(client: &Client) -> ChatCompletionRequest<'_, '_> {
+        ChatCompletionRequest::for_model(client, ModelId::Gpt4oMini, vec![ChatMessage::user("Hi")])
+    }
+
+    #[tokio::test]
+    async fn concurrent_identical_requests_are_sent_once() {
+        let server = MockServer::start().await;
+
+        chat_completions()
+            .respond_with(json_response(fixtures::chat_completion("Hello")).set_delay(DELAY))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let deduplicator = Deduplicator::new();
+        let client = client(&server, &deduplicator);
+        let requests = (0..5).map(|_| request(&client)).collect::<Vec<_>>();
+        let responses = join_all(requests.iter().map(|request| request.execute())).await;
+
+        assert!(responses
+            .into_iter()
+            .all(|response| response.unwrap().choices[0].message.content.text() == "Hello"));
+        assert_eq!(deduplicator.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn followers_send_their_own_request_when_the_leader_fails() {
+        let server = MockServer::start().await;
+
+        chat_completions()
+            .respond_with(error_response(400, "invalid_request_error", "Nope").set_delay(DELAY))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        chat_completions()
+            .respond_with(json_response(fixtures::chat_completion("Hello")))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let deduplicator = Deduplicator::new();
+        let client = client(&server, &deduplicator);
+        let requests = (0..3).map(|_| request(&client)).collect::<Vec<_>>();
+        let responses = join_all(requests.iter().map(|request| request.execute())).await;
+
+        assert!(responses[0].is_err());
+        assert!(responses[1..].iter().all(Result::is_ok));
+        assert_eq!(deduplicator.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn cancelled_leaders_dont_hang_their_followers() {
+        let server = MockServer::start().await;
+
+        chat_completions()
+            .respond_with(json_response(fixtures::chat_completion("Hello")).set_delay(DELAY))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let deduplicator = Deduplicator::new();
+        let client = client(&server, &deduplicator);
+        let (leader, follower) = (request(&client), request(&client));
+
+        let (cancelled, followed) =
+            tokio::join!(tokio::time::timeout(DELAY / 2, leader.execute()), async {
+                while deduplicator.in_flight() == 0 {
+                    tokio::task::yield_now().await;
+                }
+
+                tokio::time::timeout(DELAY * 5, follower.execute()).await
+            },);
+
+        assert!(cancelled.is_err());
+        assert!(followed.unwrap().is_ok());
+        assert_eq!(deduplicator.in_flight(), 0);
+    }
+}
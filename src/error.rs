@@ -1,22 +1,79 @@
 use std::fmt::{Display, Formatter};
 
-use crate::request::{ChatCompletionRequest, EditRequest, Request, TextCompletionRequest};
+use crate::{
+    model::ModelId,
+    request::{
+        chat_completion::ChatCompletionResponse, moderation::ModerationResult,
+        ChatCompletionRequest, EditRequest, EmbeddingRequest, ModerationRequest, Request,
+        TextCompletionRequest,
+    },
+};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug, thiserror::Error, miette::Diagnostic)]
 pub enum Error {
     SerializationError(serde_json::Error),
+    IoError(std::io::Error),
     ReqwestError(Box<dyn std::error::Error>),
     ParseError(Box<dyn std::error::Error>),
     ModelError(Box<dyn std::error::Error>),
+    ValidationError(Box<dyn std::error::Error>),
+    ToolError(Box<dyn std::error::Error>),
+    ClientError(Box<dyn std::error::Error>),
+    DatasetError(Box<dyn std::error::Error>),
+    #[cfg(feature = "image")]
+    ImageError(Box<dyn std::error::Error>),
+    #[cfg(feature = "audio")]
+    AudioError(Box<dyn std::error::Error>),
+    StructuredOutputError(StructuredOutputError),
+    ContentFlagged(ContentFlagged),
+    ContentFiltered(ContentFiltered),
+    ApiError(ApiError),
+    CircuitOpen(CircuitOpen),
+    BudgetExceeded(BudgetExceeded),
+}
+
+impl Error {
+    /// Whether sending the request again may succeed: rate limits, server errors, timeouts and
+    /// failures to connect.
+    ///
+    /// A connection dropped after the request was sent isn't retried, the API may have carried
+    /// it out already and the requests aren't idempotent (a fine-tuning job would be created
+    /// twice, ...).
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::ApiError(e) => e.is_retryable(),
+            Self::ReqwestError(e) => e
+                .downcast_ref::<reqwest::Error>()
+                .is_some_and(|e| e.is_timeout() || e.is_connect()),
+            _ => false,
+        }
+    }
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::SerializationError(e) => e.fmt(f),
-            Self::ReqwestError(e) | Self::ParseError(e) | Self::ModelError(e) => e.fmt(f),
+            Self::IoError(e) => e.fmt(f),
+            Self::StructuredOutputError(e) => e.fmt(f),
+            Self::ContentFlagged(e) => e.fmt(f),
+            Self::ContentFiltered(e) => e.fmt(f),
+            Self::ApiError(e) => e.fmt(f),
+            Self::CircuitOpen(e) => e.fmt(f),
+            Self::BudgetExceeded(e) => e.fmt(f),
+            Self::ReqwestError(e)
+            | Self::ParseError(e)
+            | Self::ModelError(e)
+            | Self::ValidationError(e)
+            | Self::ToolError(e)
+            | Self::ClientError(e)
+            | Self::DatasetError(e) => e.fmt(f),
+            #[cfg(feature = "image")]
+            Self::ImageError(e) => e.fmt(f),
+            #[cfg(feature = "audio")]
+            Self::AudioError(e) => e.fmt(f),
         }
     }
 }
@@ -25,6 +82,7 @@ impl Display for Error {
 pub enum ParseError {
     FieldNotFound(String),
     FailedToParseFromValue,
+    InvalidBase64(base64::DecodeError),
 }
 
 impl Display for ParseError {
@@ -36,6 +94,9 @@ impl Display for ParseError {
             ParseError::FailedToParseFromValue => {
                 write!(f, "Failed to parse from value")
             }
+            ParseError::InvalidBase64(e) => {
+                write!(f, "Invalid base64 data: {e}")
+            }
         }
     }
 }
@@ -45,19 +106,478 @@ pub enum ModelError {
     NotCompatibleWithTextCompletion,
     NotCompatibleWithChatCompletion,
     NotCompatibleWithEdit,
+    NotCompatibleWithModeration,
+    NotCompatibleWithEmbedding,
+    NotCompatible {
+        url: &'static str,
+        compatible_models: &'static [ModelId],
+    },
+    /// The model isn't in the `/models` list of the API key.
+    NotFound(ModelId),
 }
 
 impl Display for ModelError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::NotCompatibleWithTextCompletion => {
-                write!(f, "Model is not compatible with text completion endpoint, please use one of these models: {:?}", TextCompletionRequest::COMPATIBLE_MODELS)
+                write!(f, "Model is not compatible with text completion endpoint, please use one of these models: {}", ModelId::join(TextCompletionRequest::COMPATIBLE_MODELS))
             }
             Self::NotCompatibleWithChatCompletion => {
-                write!(f, "Model is not compatible with chat completion endpoint, please use one of these models: {:?}", ChatCompletionRequest::COMPATIBLE_MODELS)
+                write!(f, "Model is not compatible with chat completion endpoint, please use one of these models: {}", ModelId::join(ChatCompletionRequest::COMPATIBLE_MODELS))
             }
             Self::NotCompatibleWithEdit => {
-                write!(f, "Model is not compatible with edit endpoint, please use one of these models: {:?}", EditRequest::COMPATIBLE_MODELS)
+                write!(f, "Model is not compatible with edit endpoint, please use one of these models: {}", ModelId::join(EditRequest::COMPATIBLE_MODELS))
+            }
+            Self::NotCompatibleWithModeration => {
+                write!(f, "Model is not compatible with moderation endpoint, please use one of these models: {}", ModelId::join(ModerationRequest::COMPATIBLE_MODELS))
+            }
+            Self::NotCompatibleWithEmbedding => {
+                write!(f, "Model is not compatible with embedding endpoint, please use one of these models: {}", ModelId::join(EmbeddingRequest::COMPATIBLE_MODELS))
+            }
+            Self::NotCompatible {
+                url,
+                compatible_models,
+            } => {
+                write!(f, "Model is not compatible with the {url} endpoint, please use one of these models: {}", ModelId::join(compatible_models))
+            }
+            Self::NotFound(model_id) => {
+                write!(f, "Model {model_id} isn't available to this API key")
+            }
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub enum ValidationError {
+    /// Every issue found by [`Request::validate`], or the one found building a parameter.
+    Issues(Vec<ValidationIssue>),
+    UnsupportedImageType,
+    UnsupportedAudioType,
+}
+
+impl From<ValidationIssue> for ValidationError {
+    fn from(issue: ValidationIssue) -> Self {
+        Self::Issues(vec![issue])
+    }
+}
+
+impl From<Vec<ValidationIssue>> for ValidationError {
+    fn from(issues: Vec<ValidationIssue>) -> Self {
+        Self::Issues(issues)
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Issues(issues) => {
+                for (i, issue) in issues.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+
+                    issue.fmt(f)?;
+                }
+
+                Ok(())
+            }
+            Self::UnsupportedImageType => {
+                write!(f, "Unsupported image type, expected PNG, JPEG, GIF or WEBP")
+            }
+            Self::UnsupportedAudioType => {
+                write!(f, "Unsupported audio type, expected WAV or MP3")
+            }
+        }
+    }
+}
+
+/// A constraint violation found by [`Request::validate`], which the API would reject with a 400.
+#[derive(Debug, Clone, PartialEq, thiserror::Error, miette::Diagnostic)]
+pub enum ValidationIssue {
+    TooManyStopSequences(usize),
+    OutOfRange {
+        parameter: &'static str,
+        min: f64,
+        max: f64,
+    },
+    BestOfLessThanN {
+        best_of: u64,
+        n: u64,
+    },
+    BestOfWithStream,
+    ContextLengthExceeded {
+        prompt_tokens: u64,
+        completion_tokens: u64,
+        context_length: u64,
+    },
+    MutuallyExclusive {
+        parameter: &'static str,
+        other: &'static str,
+    },
+}
+
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooManyStopSequences(count) => {
+                write!(f, "Expected up to 4 stop sequences, got {count}")
+            }
+            Self::OutOfRange {
+                parameter,
+                min,
+                max,
+            } => {
+                write!(f, "\"{parameter}\" must be between {min} and {max}")
+            }
+            Self::BestOfLessThanN { best_of, n } => {
+                write!(f, "\"best_of\" ({best_of}) can't be less than \"n\" ({n})")
+            }
+            Self::BestOfWithStream => {
+                write!(f, "\"best_of\" results can't be streamed")
+            }
+            Self::ContextLengthExceeded {
+                prompt_tokens,
+                completion_tokens,
+                context_length,
+            } => {
+                write!(
+                    f,
+                    "{prompt_tokens} prompt tokens and {completion_tokens} completion tokens exceed \
+                    the context length of the model ({context_length})"
+                )
+            }
+            Self::MutuallyExclusive { parameter, other } => {
+                write!(f, "\"{parameter}\" and \"{other}\" can't be set together")
+            }
+        }
+    }
+}
+
+/// The model's output didn't parse into the requested type.
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub struct StructuredOutputError {
+    /// The text the model returned.
+    pub raw: String,
+    pub source: serde_json::Error,
+    /// The failed first attempt, when this output was a repair of it.
+    pub first_attempt: Option<Box<StructuredOutputError>>,
+}
+
+impl Display for StructuredOutputError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed to parse the structured output: {}", self.source)
+    }
+}
+
+/// The moderations endpoint flagged the content of a request, which wasn't sent.
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub struct ContentFlagged {
+    /// Names of the flagged categories, like `harassment` or `violence/graphic`.
+    pub categories: Vec<String>,
+    /// The full moderation results, one per input.
+    pub results: Vec<ModerationResult>,
+}
+
+impl Display for ContentFlagged {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Content flagged by moderation: {}",
+            self.categories.join(", ")
+        )
+    }
+}
+
+/// Every attempt of [`ChatCompletionRequest::execute_with_content_filter_retry`] was stopped by
+/// the content filter of the API (`finish_reason` is `content_filter`).
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub struct ContentFiltered {
+    /// The responses, the one to the original request first.
+    pub attempts: Vec<ChatCompletionResponse>,
+}
+
+impl Display for ContentFiltered {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Answer stopped by the content filter, in all {} attempts",
+            self.attempts.len()
+        )
+    }
+}
+
+/// The API answered with an error status.
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub struct ApiError {
+    pub status: u16,
+    pub message: String,
+    /// `type` of the error, like `invalid_request_error`.
+    pub kind: Option<String>,
+    pub code: Option<String>,
+    /// The parameter the error is about, if any.
+    pub param: Option<String>,
+    /// `retry-after`, sent with the rate limit errors.
+    pub retry_after: Option<std::time::Duration>,
+}
+
+impl ApiError {
+    /// Reads the error from the status, headers and body of a response. Bodies other than the
+    /// `{"error": {...}}` of the API (from a proxy, ...) are kept whole as the message.
+    pub(crate) fn new(status: u16, headers: &reqwest::header::HeaderMap, body: &str) -> Self {
+        let error = serde_json::from_str::<serde_json::Value>(body)
+            .ok()
+            .and_then(|mut json| json.get_mut("error").map(serde_json::Value::take))
+            .filter(serde_json::Value::is_object);
+
+        let field = |name: &str| {
+            error
+                .as_ref()
+                .and_then(|error| error.get(name))
+                .and_then(|value| match value {
+                    serde_json::Value::Null => None,
+                    serde_json::Value::String(value) => Some(value.clone()),
+                    value => Some(value.to_string()),
+                })
+        };
+
+        Self {
+            status,
+            message: field("message").unwrap_or_else(|| body.trim().to_string()),
+            kind: field("type"),
+            code: field("code"),
+            param: field("param"),
+            retry_after: crate::response::parse_retry_after(headers),
+        }
+    }
+
+    /// Rate limits (429), timeouts (408), conflicts (409) and server errors (5xx).
+    pub fn is_retryable(&self) -> bool {
+        matches!(self.status, 408 | 409 | 429) || self.status >= 500
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The API answered {}: {}", self.status, self.message)
+    }
+}
+
+/// The circuit of the endpoint is open after too many failures, so the request wasn't sent, see
+/// [`crate::circuit_breaker::CircuitBreaker`].
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub struct CircuitOpen {
+    pub endpoint: String,
+    /// Time until a request is let through again.
+    pub retry_in: std::time::Duration,
+}
+
+impl Display for CircuitOpen {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The circuit of {} is open after repeated failures, retry in {:?}",
+            self.endpoint, self.retry_in
+        )
+    }
+}
+
+/// The request would go over the [`crate::budget::Budget`] of the client, so it wasn't sent.
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub enum BudgetExceeded {
+    Tokens {
+        /// Tokens spent in the current window.
+        spent: u64,
+        /// Estimated tokens of the refused request, its prompt and requested output.
+        estimated: u64,
+        max_tokens: u64,
+        window: std::time::Duration,
+    },
+    Cost {
+        /// USD spent in the current window.
+        spent: f64,
+        /// Estimated USD of the refused request.
+        estimated: f64,
+        max_cost: f64,
+        window: std::time::Duration,
+    },
+}
+
+impl Display for BudgetExceeded {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tokens {
+                spent,
+                estimated,
+                max_tokens,
+                window,
+            } => write!(
+                f,
+                "Budget exceeded: {spent} tokens spent and about {estimated} more requested, out \
+                 of {max_tokens} per {window:?}"
+            ),
+            Self::Cost {
+                spent,
+                estimated,
+                max_cost,
+                window,
+            } => write!(
+                f,
+                "Budget exceeded: ${spent:.4} spent and about ${estimated:.4} more requested, out \
+                 of ${max_cost:.2} per {window:?}"
+            ),
+        }
+    }
+}
+
+/// The configuration of the [`crate::client::Client`] doesn't allow the request.
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub enum ClientError {
+    /// A plain `http://` base URL was set without [`crate::client::Client::allow_http`].
+    InsecureBaseUrl(String),
+    UnsupportedBaseUrl(String),
+    /// The callback of the [`crate::auth::TokenProvider`] failed.
+    TokenUnavailable(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl Display for ClientError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InsecureBaseUrl(url) => {
+                write!(
+                    f,
+                    "Refusing to send requests over plain HTTP to {url}, use an https:// base URL \
+                    or opt in with Client::allow_http"
+                )
+            }
+            Self::UnsupportedBaseUrl(url) => {
+                write!(f, "Expected an http:// or https:// base URL, got {url}")
+            }
+            Self::TokenUnavailable(e) => {
+                write!(f, "Couldn't get a token from the token provider: {e}")
+            }
+        }
+    }
+}
+
+/// A fine-tuning example or dataset the API would reject, see
+/// [`crate::fine_tuning::TrainingDatasetBuilder`]. `example` is the index of the example.
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub enum DatasetError {
+    NoAssistantMessage {
+        example: usize,
+    },
+    UnsupportedRole {
+        example: usize,
+        role: String,
+    },
+    MisplacedSystemMessage {
+        example: usize,
+    },
+    EmptyMessage {
+        example: usize,
+        message: usize,
+    },
+    TooLong {
+        example: usize,
+        estimated_tokens: u64,
+        max_tokens: u64,
+    },
+    TooFewExamples {
+        count: usize,
+        min: usize,
+    },
+}
+
+impl Display for DatasetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoAssistantMessage { example } => {
+                write!(
+                    f,
+                    "Example {example} has no assistant message to learn from"
+                )
+            }
+            Self::UnsupportedRole { example, role } => {
+                write!(
+                    f,
+                    "Example {example} has a \"{role}\" message, which fine-tuning doesn't accept"
+                )
+            }
+            Self::MisplacedSystemMessage { example } => {
+                write!(
+                    f,
+                    "Example {example} has a system message after the first message"
+                )
+            }
+            Self::EmptyMessage { example, message } => {
+                write!(f, "Message {message} of example {example} is empty")
+            }
+            Self::TooLong {
+                example,
+                estimated_tokens,
+                max_tokens,
+            } => {
+                write!(
+                    f,
+                    "Example {example} is about {estimated_tokens} tokens long, over the {max_tokens} accepted"
+                )
+            }
+            Self::TooFewExamples { count, min } => {
+                write!(f, "Expected at least {min} examples, got {count}")
+            }
+        }
+    }
+}
+
+/// A generated image couldn't be decoded or saved, see [`crate::images::ImageData`].
+#[cfg(feature = "image")]
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub enum ImageError {
+    /// Only the URL of the image is in the response, it has to be downloaded.
+    NotInResponse,
+    Image(image::ImageError),
+}
+
+#[cfg(feature = "image")]
+impl Display for ImageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotInResponse => {
+                write!(
+                    f,
+                    "The image isn't in the response, download it from its URL"
+                )
+            }
+            Self::Image(e) => e.fmt(f),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
+pub enum ToolError {
+    UnknownTool(String),
+    NoChoices,
+    MaxIterationsReached(usize),
+    Timeout(std::time::Duration),
+}
+
+impl Display for ToolError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownTool(name) => {
+                write!(
+                    f,
+                    "The model called \"{name}\", which isn't a registered tool"
+                )
+            }
+            Self::NoChoices => {
+                write!(f, "The response doesn't have any choices")
+            }
+            Self::MaxIterationsReached(max_iterations) => {
+                write!(f, "No final answer after {max_iterations} iterations")
+            }
+            Self::Timeout(timeout) => {
+                write!(f, "No final answer after {timeout:?}")
             }
         }
     }
@@ -79,10 +599,135 @@ from_err!(
     ReqwestError[reqwest::Error],
     ParseError[ParseError],
     ModelError[ModelError],
+    ValidationError[ValidationError],
+    ToolError[ToolError],
+    ClientError[ClientError],
+    DatasetError[DatasetError],
 );
 
+#[cfg(feature = "image")]
+from_err!(ImageError[ImageError]);
+
+#[cfg(feature = "audio")]
+from_err!(AudioError[hound::Error]);
+
+impl From<StructuredOutputError> for Error {
+    fn from(e: StructuredOutputError) -> Self {
+        Self::StructuredOutputError(e)
+    }
+}
+
+impl From<ApiError> for Error {
+    fn from(e: ApiError) -> Self {
+        Self::ApiError(e)
+    }
+}
+
+impl From<BudgetExceeded> for Error {
+    fn from(e: BudgetExceeded) -> Self {
+        Self::BudgetExceeded(e)
+    }
+}
+
+impl From<CircuitOpen> for Error {
+    fn from(e: CircuitOpen) -> Self {
+        Self::CircuitOpen(e)
+    }
+}
+
+impl From<ContentFlagged> for Error {
+    fn from(e: ContentFlagged) -> Self {
+        Self::ContentFlagged(e)
+    }
+}
+
+impl From<ContentFiltered> for Error {
+    fn from(e: ContentFiltered) -> Self {
+        Self::ContentFiltered(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Self::IoError(e)
+    }
+}
+
 impl From<serde_json::Error> for Error {
     fn from(e: serde_json::Error) -> Self {
         Self::SerializationError(e)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{
+        faults::{Fault, FaultConfig, FaultProxy},
+        wiremock::MockServer,
+    };
+
+    #[test]
+    fn api_errors_are_retried_on_transient_statuses() {
+        let headers = reqwest::header::HeaderMap::new();
+        let retryable = |status| ApiError::new(status, &headers, "").is_retryable();
+
+        assert!([408, 409, 429, 500, 502, 503].into_iter().all(retryable));
+        assert!(![400, 401, 403, 404, 422].into_iter().any(retryable));
+    }
+
+    #[test]
+    fn api_errors_read_retry_after_in_range() {
+        let retry_after = |value: &str| {
+            let headers = [(
+                reqwest::header::RETRY_AFTER,
+                reqwest::header::HeaderValue::from_str(value).unwrap(),
+            )]
+            .into_iter()
+            .collect();
+
+            ApiError::new(429, &headers, "").retry_after
+        };
+
+        assert_eq!(
+            retry_after(" 1.5 "),
+            Some(std::time::Duration::from_millis(1_500))
+        );
+        assert_eq!(retry_after("0"), Some(std::time::Duration::ZERO));
+
+        for value in ["-1", "NaN", "inf", "1e300", "Wed, 21 Oct 2015 07:28:00 GMT"] {
+            assert_eq!(retry_after(value), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn failures_to_connect_are_retried() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        drop(listener);
+
+        let error = Error::from(reqwest::get(url).await.unwrap_err());
+
+        assert!(error.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn connections_dropped_after_sending_are_not_retried() {
+        let server = MockServer::start().await;
+        let proxy = FaultProxy::start(server.uri(), FaultConfig::new())
+            .await
+            .unwrap();
+
+        proxy.inject(Fault::ConnectionReset);
+
+        let error = reqwest::Client::new()
+            .post(format!("{}/fine_tuning/jobs", proxy.uri()))
+            .body("{}")
+            .send()
+            .await
+            .unwrap_err();
+
+        assert!(!Error::from(error).is_retryable());
+    }
+}
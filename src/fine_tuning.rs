@@ -0,0 +1,270 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::Client,
+    error,
+    request::{
+        chat_completion::{ChatMessage, ChatRole, MessageContent},
+        ObjectType,
+    },
+    APIKeysAccess,
+};
+
+pub const FILES_URL: &str = "https://api.openai.com/v1/files";
+
+/// Fewest examples a fine-tuning job accepts.
+pub const MIN_EXAMPLES: usize = 10;
+
+const TRAINING_FILE_NAME: &str = "training.jsonl";
+const TRAINING_FILE_MIME: &str = "application/jsonl";
+
+/// A conversation the model is trained to answer like, a line of the dataset.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrainingExample {
+    pub messages: Vec<ChatMessage>,
+}
+
+/// Collects chat-format fine-tuning examples, checking each one the way the API does, and writes
+/// them as the JSONL file fine-tuning jobs are created from.
+///
+/// The tokens of the examples are counted with the `tokenizer` feature, with the tokenizer of the
+/// `gpt-4o` models, and estimated at a token per 4 bytes of their JSON otherwise.
+#[derive(Debug, Clone)]
+pub struct TrainingDatasetBuilder {
+    examples: Vec<TrainingExample>,
+    max_tokens_per_example: u64,
+}
+
+/// A file uploaded with the Files API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadedFile {
+    pub id: String,
+    pub object: ObjectType,
+    /// Size of the file.
+    pub bytes: u64,
+    pub created_at: u64,
+    pub filename: String,
+    pub purpose: String,
+}
+
+impl Default for TrainingDatasetBuilder {
+    fn default() -> Self {
+        Self {
+            examples: Vec::new(),
+            max_tokens_per_example: 65_536,
+        }
+    }
+}
+
+impl TrainingDatasetBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Longest example accepted, the context length of the model fine-tuned.
+    pub fn max_tokens_per_example(mut self, max_tokens: u64) -> Self {
+        self.max_tokens_per_example = max_tokens;
+        self
+    }
+
+    /// Adds an example, unless the API would reject it: it needs an assistant message to learn
+    /// from, only system, user, assistant and tool messages, a system message first if any, no
+    /// empty message and a length under [`Self::max_tokens_per_example`].
+    pub fn push(
+        &mut self,
+        messages: impl IntoIterator<Item = ChatMessage>,
+    ) -> Result<(), error::DatasetError> {
+        let example = TrainingExample {
+            messages: messages.into_iter().collect(),
+        };
+
+        self.check(&example)?;
+        self.examples.push(example);
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.examples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.examples.is_empty()
+    }
+
+    pub fn examples(&self) -> &[TrainingExample] {
+        &self.examples
+    }
+
+    /// The dataset, one example per line. Fails with fewer than [`MIN_EXAMPLES`] examples.
+    pub fn to_jsonl(&self) -> error::Result<String> {
+        if self.examples.len() < MIN_EXAMPLES {
+            return Err(error::DatasetError::TooFewExamples {
+                count: self.examples.len(),
+                min: MIN_EXAMPLES,
+            }
+            .into());
+        }
+
+        self.examples
+            .iter()
+            .map(|example| Ok(serde_json::to_string(example)? + "\n"))
+            .collect()
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> error::Result<()> {
+        Ok(std::fs::write(path, self.to_jsonl()?)?)
+    }
+
+    /// Uploads the dataset with the purpose `fine-tune`, the id of the returned file being the
+    /// `training_file` of a fine-tuning job.
+    pub async fn upload(&self, client: &Client) -> error::Result<UploadedFile> {
+        let file = reqwest::multipart::Part::text(self.to_jsonl()?)
+            .file_name(TRAINING_FILE_NAME)
+            .mime_str(TRAINING_FILE_MIME)?;
+        let form = reqwest::multipart::Form::new()
+            .text("purpose", "fine-tune")
+            .part("file", file);
+        let request = client
+            .async_client()
+            .post(client.resolve_url(FILES_URL)?)
+            .headers(client.common_headers())
+            .multipart(form);
+        let body = client.send(request).await?.bytes().await?;
+
+        Ok(client.parse(&body)?)
+    }
+
+    /// (Blocking) Same as [`Self::upload`].
+    #[cfg(feature = "blocking")]
+    pub fn upload_blocking(&self, client: &Client) -> error::Result<UploadedFile> {
+        let file = reqwest::blocking::multipart::Part::text(self.to_jsonl()?)
+            .file_name(TRAINING_FILE_NAME)
+            .mime_str(TRAINING_FILE_MIME)?;
+        let form = reqwest::blocking::multipart::Form::new()
+            .text("purpose", "fine-tune")
+            .part("file", file);
+        let request = client
+            .blocking_client()?
+            .post(client.resolve_url(FILES_URL)?)
+            .headers(client.common_headers())
+            .multipart(form);
+        let body = client.send_blocking(request)?.bytes()?;
+
+        Ok(client.parse(&body)?)
+    }
+
+    fn check(&self, example: &TrainingExample) -> Result<(), error::DatasetError> {
+        let index = self.examples.len();
+
+        for (position, message) in example.messages.iter().enumerate() {
+            match &message.role {
+                ChatRole::System if position > 0 => {
+                    return Err(error::DatasetError::MisplacedSystemMessage { example: index });
+                }
+                ChatRole::System | ChatRole::User | ChatRole::Assistant | ChatRole::Tool => {}
+                role => {
+                    return Err(error::DatasetError::UnsupportedRole {
+                        example: index,
+                        role: role.as_str().to_string(),
+                    });
+                }
+            }
+
+            let empty = match &message.content {
+                MessageContent::Text(text) => text.is_empty(),
+                MessageContent::Parts(parts) => parts.is_empty(),
+            };
+
+            if empty && message.tool_calls.is_none() && message.function_call.is_none() {
+                return Err(error::DatasetError::EmptyMessage {
+                    example: index,
+                    message: position,
+                });
+            }
+        }
+
+        if !example
+            .messages
+            .iter()
+            .any(|message| message.role == ChatRole::Assistant)
+        {
+            return Err(error::DatasetError::NoAssistantMessage { example: index });
+        }
+
+        #[cfg(feature = "tokenizer")]
+        let estimated_tokens =
+            crate::tokens::count_tokens(&example.messages, &crate::model::ModelId::Gpt4oMini)
+                as u64;
+        #[cfg(not(feature = "tokenizer"))]
+        let estimated_tokens = serde_json::to_vec(example).map_or(0, |json| json.len() as u64 / 4);
+
+        if estimated_tokens > self.max_tokens_per_example {
+            return Err(error::DatasetError::TooLong {
+                example: index,
+                estimated_tokens,
+                max_tokens: self.max_tokens_per_example,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_util::{
+        self, json_response,
+        wiremock::{
+            matchers::{header_regex, method, path},
+            Mock, MockServer,
+        },
+    };
+
+    #[tokio::test]
+    async fn uploads_the_dataset_as_a_form() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/files"))
+            .and(header_regex(
+                "content-type",
+                "^multipart/form-data; boundary=",
+            ))
+            .respond_with(json_response(json!({
+                "id": "file-abc123",
+                "object": "file",
+                "bytes": 1_024,
+                "created_at": 1_700_000_000,
+                "filename": "training.jsonl",
+                "purpose": "fine-tune",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut dataset = TrainingDatasetBuilder::new();
+        for _ in 0..MIN_EXAMPLES {
+            dataset
+                .push([
+                    ChatMessage::user("Hi"),
+                    ChatMessage::assistant("Hello, crab"),
+                ])
+                .unwrap();
+        }
+        let file = dataset.upload(&test_util::client(&server)).await.unwrap();
+        let requests = server.received_requests().await.unwrap();
+        let body = String::from_utf8_lossy(&requests[0].body);
+
+        assert_eq!(file.id, "file-abc123");
+        assert!(body.contains("name=\"purpose\"\r\n\r\nfine-tune\r\n"));
+        assert!(body.contains("name=\"file\"; filename=\"training.jsonl\""));
+        assert!(body.contains("Content-Type: application/jsonl"));
+        assert!(body.contains(&dataset.to_jsonl().unwrap()));
+    }
+}
@@ -0,0 +1,314 @@
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "image")]
+use crate::error::ImageError;
+use crate::{
+    client::Client,
+    error,
+    model::{Model, ModelId},
+    APIKeysAccess,
+};
+
+pub const IMAGE_GENERATIONS_URL: &str = "https://api.openai.com/v1/images/generations";
+
+/// Creates images from a text prompt.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImageGenerationRequest {
+    model: ModelId,
+    prompt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    quality: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    style: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ImageResponseFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<String>,
+}
+
+/// How the images of an [`ImagesResponse`] are returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageResponseFormat {
+    /// A link valid for an hour.
+    Url,
+    /// The image itself, base64 encoded.
+    B64Json,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImagesResponse {
+    pub created: u64,
+    pub data: Vec<ImageData>,
+}
+
+/// An image generated by the Images API, an item of the `data` of its responses. Depending on
+/// the `response_format` of the request, either `b64_json` or `url` is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageData {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub b64_json: Option<String>,
+    /// Where the image can be downloaded from, for an hour after it was generated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// The prompt the image was generated from, when the model rewrote the one of the request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revised_prompt: Option<String>,
+}
+
+impl ImageGenerationRequest {
+    pub fn new(model: impl Into<ModelId>, prompt: impl AsRef<str>) -> Self {
+        Self {
+            model: model.into(),
+            prompt: prompt.as_ref().to_string(),
+            n: None,
+            size: None,
+            quality: None,
+            style: None,
+            response_format: None,
+            user: None,
+        }
+    }
+
+    /// Number of images to generate, 1 by default. `dall-e-3` only generates one at a time.
+    pub fn with_n(mut self, n: u64) -> Self {
+        self.n = Some(n);
+        self
+    }
+
+    /// Dimensions of the images, like `1024x1024`, the ones accepted depending on the model.
+    pub fn with_size(mut self, size: impl AsRef<str>) -> Self {
+        self.size = Some(size.as_ref().to_string());
+        self
+    }
+
+    /// `standard` or `hd` for `dall-e-3`, `low`, `medium` or `high` for `gpt-image-1`.
+    pub fn with_quality(mut self, quality: impl AsRef<str>) -> Self {
+        self.quality = Some(quality.as_ref().to_string());
+        self
+    }
+
+    /// `vivid` or `natural`, for `dall-e-3`.
+    pub fn with_style(mut self, style: impl AsRef<str>) -> Self {
+        self.style = Some(style.as_ref().to_string());
+        self
+    }
+
+    /// Not accepted by `gpt-image-1`, which always returns base64 encoded images.
+    pub fn with_response_format(mut self, response_format: ImageResponseFormat) -> Self {
+        self.response_format = Some(response_format);
+        self
+    }
+
+    /// A unique identifier representing the end-user, to help the API monitor abuse.
+    pub fn with_user(mut self, user: impl AsRef<str>) -> Self {
+        self.user = Some(user.as_ref().to_string());
+        self
+    }
+
+    pub async fn execute(&self, client: &Client) -> error::Result<ImagesResponse> {
+        self.check_model(client)?;
+
+        let request = client
+            .async_client()
+            .post(client.resolve_url(IMAGE_GENERATIONS_URL)?)
+            .headers(client.common_headers())
+            .json(self);
+        let body = client.send(request).await?.bytes().await?;
+
+        Ok(client.parse(&body)?)
+    }
+
+    /// (Blocking) Same as [`Self::execute`].
+    #[cfg(feature = "blocking")]
+    pub fn execute_blocking(&self, client: &Client) -> error::Result<ImagesResponse> {
+        self.check_model(client)?;
+
+        let request = client
+            .blocking_client()?
+            .post(client.resolve_url(IMAGE_GENERATIONS_URL)?)
+            .headers(client.common_headers())
+            .json(self);
+        let body = client.send_blocking(request)?.bytes()?;
+
+        Ok(client.parse(&body)?)
+    }
+
+    fn check_model(&self, client: &Client) -> Result<(), error::ModelError> {
+        if client.compatibility().is_compatible(
+            IMAGE_GENERATIONS_URL,
+            Model::IMAGE_GENERATIONS,
+            &self.model,
+        ) {
+            return Ok(());
+        }
+
+        Err(error::ModelError::NotCompatible {
+            url: IMAGE_GENERATIONS_URL,
+            compatible_models: Model::IMAGE_GENERATIONS,
+        })
+    }
+}
+
+impl ImageData {
+    /// The bytes of the image when they are in the response, from `b64_json` or a `data:` URL.
+    pub fn decode(&self) -> error::Result<Option<Vec<u8>>> {
+        let data = match (&self.b64_json, &self.url) {
+            (Some(b64_json), _) => b64_json.as_str(),
+            (None, Some(url)) => match url
+                .strip_prefix("data:")
+                .and_then(|url| url.split_once(";base64,"))
+            {
+                Some((_, data)) => data,
+                None => return Ok(None),
+            },
+            (None, None) => return Ok(None),
+        };
+
+        base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .map(Some)
+            .map_err(|e| error::ParseError::InvalidBase64(e).into())
+    }
+
+    /// Decodes the image in the response. A `url` result has to be downloaded instead, see
+    /// [`Self::download`].
+    #[cfg(feature = "image")]
+    pub fn to_dynamic_image(&self) -> error::Result<image::DynamicImage> {
+        let bytes = self.decode()?.ok_or(ImageError::NotInResponse)?;
+
+        Ok(image::load_from_memory(&bytes).map_err(ImageError::Image)?)
+    }
+
+    /// The image, downloaded from `url` with the HTTP client of `client` if it isn't in the
+    /// response. The API key isn't sent along.
+    #[cfg(feature = "image")]
+    pub async fn download(&self, client: &Client) -> error::Result<image::DynamicImage> {
+        let bytes = match (self.decode()?, &self.url) {
+            (Some(bytes), _) => bytes,
+            (None, Some(url)) => client
+                .async_client()
+                .get(url)
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await?
+                .to_vec(),
+            (None, None) => return Err(ImageError::NotInResponse.into()),
+        };
+
+        Ok(image::load_from_memory(&bytes).map_err(ImageError::Image)?)
+    }
+
+    /// (Blocking) Same as [`Self::download`].
+    #[cfg(all(feature = "image", feature = "blocking"))]
+    pub fn download_blocking(&self, client: &Client) -> error::Result<image::DynamicImage> {
+        let bytes = match (self.decode()?, &self.url) {
+            (Some(bytes), _) => bytes,
+            (None, Some(url)) => client
+                .blocking_client()?
+                .get(url)
+                .send()?
+                .error_for_status()?
+                .bytes()?
+                .to_vec(),
+            (None, None) => return Err(ImageError::NotInResponse.into()),
+        };
+
+        Ok(image::load_from_memory(&bytes).map_err(ImageError::Image)?)
+    }
+
+    /// Saves the image in the response to `path`, in the format of its extension.
+    #[cfg(feature = "image")]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> error::Result<()> {
+        Ok(self
+            .to_dynamic_image()?
+            .save(path)
+            .map_err(ImageError::Image)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_util::{
+        self, json_response,
+        wiremock::{
+            matchers::{body_json, method, path},
+            Mock, MockServer,
+        },
+    };
+
+    #[tokio::test]
+    async fn generates_images() {
+        let server = MockServer::start().await;
+        let encoded = base64::engine::general_purpose::STANDARD.encode(b"not really a png");
+
+        Mock::given(method("POST"))
+            .and(path("/images/generations"))
+            .and(body_json(json!({
+                "model": "dall-e-3",
+                "prompt": "A crab in a top hat",
+                "size": "1024x1024",
+                "response_format": "b64_json",
+            })))
+            .respond_with(json_response(json!({
+                "created": 1_700_000_000,
+                "data": [{ "b64_json": encoded, "revised_prompt": "A crab wearing a top hat" }],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let response = ImageGenerationRequest::new(ModelId::DallE3, "A crab in a top hat")
+            .with_size("1024x1024")
+            .with_response_format(ImageResponseFormat::B64Json)
+            .execute(&test_util::client(&server))
+            .await
+            .unwrap();
+
+        assert_eq!(response.data.len(), 1);
+        assert_eq!(
+            response.data[0].decode().unwrap().as_deref(),
+            Some(&b"not really a png"[..])
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_the_models_that_dont_draw() {
+        let server = MockServer::start().await;
+        let result = ImageGenerationRequest::new(ModelId::Gpt4o, "A crab")
+            .execute(&test_util::client(&server))
+            .await;
+
+        assert!(matches!(result, Err(error::Error::ModelError(_))));
+        assert!(server.received_requests().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn decodes_data_urls_only() {
+        let image = |url: &str| ImageData {
+            b64_json: None,
+            url: Some(url.to_string()),
+            revised_prompt: None,
+        };
+
+        assert_eq!(
+            image("data:image/png;base64,aGk=").decode().unwrap(),
+            Some(b"hi".to_vec())
+        );
+        assert_eq!(
+            image("https://example.com/crab.png").decode().unwrap(),
+            None
+        );
+        assert!(image("data:image/png;base64,!!").decode().is_err());
+    }
+}
@@ -1,9 +1,95 @@
 use reqwest::header::HeaderMap;
 
+// Lets the derive macros refer to `::openai_api_rs` from inside the crate as well
+extern crate self as openai_api_rs;
+
+pub mod api;
+#[cfg(feature = "audio")]
+pub mod audio;
+pub mod auth;
+pub mod batch;
+pub mod budget;
+pub mod cache;
+pub mod catalog;
+pub mod circuit_breaker;
 pub mod client;
+pub mod compat;
+pub mod concurrency;
+pub mod dedup;
 pub mod error;
+pub mod fine_tuning;
+pub mod images;
+pub mod metrics;
 pub mod model;
+pub mod pagination;
+pub mod pricing;
+pub mod rate_limit;
 pub mod request;
+pub mod response;
+pub mod retry;
+pub mod session;
+#[cfg(feature = "tokenizer")]
+pub mod splitter;
+pub mod stream;
+#[cfg(feature = "tokenizer")]
+pub mod summarize;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+pub mod timeout;
+pub mod tls;
+#[cfg(feature = "tokenizer")]
+pub mod tokens;
+pub mod tool;
+pub mod transcription;
+pub mod usage;
+pub mod vector_index;
+
+/// Declares request types from the fields of a body struct, the way the ones of
+/// [`request`] are. Outside of this crate, `crate_path` names it for the generated code:
+///
+/// ```
+/// use openai_api_rs::{client::Client, model::ModelId, request::Request, rq};
+///
+/// #[derive(Debug, serde::Deserialize)]
+/// pub struct AnswerResponse {
+///     pub answer: String,
+/// }
+///
+/// #[rq(
+///     crate_path("openai_api_rs"),
+///     Answer(
+///         doc("Answers a question."),
+///         url("https://gateway.example.com/v1/answers"),
+///         compatible_models(Gpt4o, "answerer-1")
+///     )
+/// )]
+/// pub struct RequestBody {
+///     /// Required.
+///     #[rq(on(Answer(req)))]
+///     question: String,
+///     /// Optional.
+///     #[rq(on(Answer))]
+///     max_sources: Option<u32>,
+/// }
+///
+/// let client = Client::new("sk-test");
+/// let request = AnswerRequest::for_model(&client, ModelId::Gpt4o, "Why?".to_string())
+///     .with_max_sources(3);
+///
+/// assert_eq!(
+///     request.to_json().unwrap(),
+///     serde_json::json!({ "model": "gpt-4o", "question": "Why?", "max_sources": 3 })
+/// );
+/// assert!(AnswerRequest::COMPATIBLE_MODELS.contains(&ModelId::Gpt4o));
+/// ```
+pub use macros::rq;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use getset;
+    pub use serde;
+    pub use serde_json;
+}
 
 trait APIKeysAccess {
     fn get_api_key(&self) -> &String;
@@ -0,0 +1,231 @@
+use std::{collections::BTreeMap, sync::Mutex, time::Duration};
+
+/// Counters and latencies of the requests sent by a client, per endpoint, to export to a
+/// monitoring system without setting up tracing. See [`crate::client::Client::metrics`].
+#[derive(Debug, Default)]
+pub struct Metrics {
+    endpoints: Mutex<BTreeMap<String, EndpointMetrics>>,
+}
+
+/// The requests sent to an endpoint.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EndpointMetrics {
+    pub requests: u64,
+    pub successes: u64,
+    /// Requests the API answered with an error status, by status.
+    pub api_errors: BTreeMap<u16, u64>,
+    /// Requests that got no answer: timeouts, connection failures, ...
+    pub transport_errors: u64,
+    /// Time until the response headers arrived, for the answered requests.
+    pub latency: LatencyHistogram,
+}
+
+/// Latencies counted in buckets, like a Prometheus histogram.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyHistogram {
+    /// Count of each bucket of [`Self::BOUNDS`], the last one being for the slower latencies.
+    counts: [u64; Self::BOUNDS.len() + 1],
+    sum: Duration,
+}
+
+impl Metrics {
+    pub(crate) fn record(&self, endpoint: &str, outcome: Result<u16, ()>, latency: Duration) {
+        let mut endpoints = self.endpoints();
+        let metrics = endpoints.entry(endpoint.to_string()).or_default();
+
+        metrics.requests += 1;
+
+        match outcome {
+            Ok(status) if (200..300).contains(&status) => metrics.successes += 1,
+            Ok(status) => *metrics.api_errors.entry(status).or_default() += 1,
+            Err(()) => metrics.transport_errors += 1,
+        }
+
+        if outcome.is_ok() {
+            metrics.latency.record(latency);
+        }
+    }
+
+    /// The metrics of every endpoint requested so far, by path (`/chat/completions`, ...).
+    pub fn snapshot(&self) -> BTreeMap<String, EndpointMetrics> {
+        self.endpoints().clone()
+    }
+
+    pub fn reset(&self) {
+        self.endpoints().clear();
+    }
+
+    fn endpoints(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, EndpointMetrics>> {
+        self.endpoints.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl EndpointMetrics {
+    /// Requests that failed, answered or not.
+    pub fn errors(&self) -> u64 {
+        self.api_errors.values().sum::<u64>() + self.transport_errors
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            counts: [0; Self::BOUNDS.len() + 1],
+            sum: Duration::ZERO,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    /// Upper bounds of the buckets.
+    pub const BOUNDS: [Duration; 12] = [
+        Duration::from_millis(50),
+        Duration::from_millis(100),
+        Duration::from_millis(250),
+        Duration::from_millis(500),
+        Duration::from_secs(1),
+        Duration::from_millis(2500),
+        Duration::from_secs(5),
+        Duration::from_secs(10),
+        Duration::from_secs(20),
+        Duration::from_secs(30),
+        Duration::from_secs(60),
+        Duration::from_secs(120),
+    ];
+
+    fn record(&mut self, latency: Duration) {
+        let bucket = Self::BOUNDS
+            .iter()
+            .position(|bound| latency <= *bound)
+            .unwrap_or(Self::BOUNDS.len());
+
+        self.counts[bucket] += 1;
+        self.sum += latency;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        let count = self.count();
+
+        (count > 0).then(|| self.sum / count as u32)
+    }
+
+    /// Each bucket with its upper bound ([None] for the last, unbounded one) and count.
+    pub fn buckets(&self) -> impl Iterator<Item = (Option<Duration>, u64)> + '_ {
+        Self::BOUNDS
+            .iter()
+            .copied()
+            .map(Some)
+            .chain(std::iter::once(None))
+            .zip(self.counts.iter().copied())
+    }
+
+    /// Upper bound of the bucket holding the `quantile` (between 0 and 1) of the latencies,
+    /// [None] if there are none or it is in the unbounded bucket.
+    pub fn quantile(&self, quantile: f64) -> Option<Duration> {
+        let count = self.count();
+
+        if count == 0 {
+            return None;
+        }
+
+        let rank = ((count as f64 * quantile.clamp(0.0, 1.0)).ceil() as u64).max(1);
+
+        self.buckets()
+            .scan(0, |seen, (bound, bucket_count)| {
+                *seen += bucket_count;
+
+                Some((bound, *seen))
+            })
+            .find(|(_, seen)| *seen >= rank)
+            .and_then(|(bound, _)| bound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NANOSECOND: Duration = Duration::from_nanos(1);
+
+    fn histogram(latencies: &[Duration]) -> LatencyHistogram {
+        let mut histogram = LatencyHistogram::default();
+
+        latencies
+            .iter()
+            .for_each(|&latency| histogram.record(latency));
+
+        histogram
+    }
+
+    fn counts(histogram: &LatencyHistogram) -> Vec<u64> {
+        histogram.buckets().map(|(_, count)| count).collect()
+    }
+
+    #[test]
+    fn latencies_at_a_bound_are_in_its_bucket() {
+        let first = LatencyHistogram::BOUNDS[0];
+        let last = LatencyHistogram::BOUNDS[LatencyHistogram::BOUNDS.len() - 1];
+        let histogram = histogram(&[
+            Duration::ZERO,
+            first,
+            first + NANOSECOND,
+            last,
+            last + NANOSECOND,
+        ]);
+
+        assert_eq!(counts(&histogram), [2, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1]);
+        assert_eq!(histogram.count(), 5);
+        assert_eq!(histogram.sum(), 2 * first + 2 * last + 2 * NANOSECOND);
+    }
+
+    #[test]
+    fn quantiles_are_the_bounds_of_their_buckets() {
+        let histogram = histogram(&[
+            Duration::from_millis(10),
+            Duration::from_millis(80),
+            Duration::from_millis(90),
+            Duration::from_secs(600),
+        ]);
+
+        assert_eq!(histogram.quantile(0.0), Some(Duration::from_millis(50)));
+        assert_eq!(histogram.quantile(0.5), Some(Duration::from_millis(100)));
+        assert_eq!(histogram.quantile(0.75), Some(Duration::from_millis(100)));
+        assert_eq!(histogram.quantile(0.99), None);
+        assert_eq!(LatencyHistogram::default().quantile(0.5), None);
+    }
+
+    #[test]
+    fn answered_and_unanswered_errors_are_counted_apart() {
+        let metrics = Metrics::default();
+        let latency = Duration::from_millis(200);
+
+        metrics.record("/chat/completions", Ok(200), latency);
+        metrics.record("/chat/completions", Ok(429), latency);
+        metrics.record("/chat/completions", Ok(429), latency);
+        metrics.record("/chat/completions", Ok(500), latency);
+        metrics.record("/chat/completions", Err(()), latency);
+        metrics.record("/embeddings", Ok(201), latency);
+
+        let snapshot = metrics.snapshot();
+        let chat = &snapshot["/chat/completions"];
+
+        assert_eq!((chat.requests, chat.successes), (5, 1));
+        assert_eq!(chat.api_errors, BTreeMap::from([(429, 2), (500, 1)]));
+        assert_eq!(chat.transport_errors, 1);
+        assert_eq!(chat.errors(), 4);
+        assert_eq!(chat.latency.count(), 4);
+        assert_eq!(snapshot["/embeddings"].successes, 1);
+
+        metrics.reset();
+
+        assert!(metrics.snapshot().is_empty());
+    }
+}
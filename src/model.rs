@@ -1,101 +1,316 @@
-use crate::{error, APIKeysAccess};
+use std::{
+    borrow::Cow,
+    convert::Infallible,
+    fmt::{Display, Formatter},
+    hash::{Hash, Hasher},
+    ops::Deref,
+    str::FromStr,
+};
 
-#[derive(Debug, getset::Getters)]
-pub struct Model<'client> {
-    api_key: &'client String,
-    org_id: &'client Option<String>,
+use serde::{Deserialize, Serialize};
 
-    #[cfg(feature = "blocking")]
-    #[get = "pub"]
-    blocking_client: &'client reqwest::blocking::Client,
-    #[get = "pub"]
-    async_client: &'client reqwest::Client,
+use crate::{client::Client, APIKeysAccess};
 
+pub mod capabilities;
+pub mod filter;
+pub mod known;
+
+pub use capabilities::{Capability, ModelCapabilities};
+pub use filter::{ModelFilter, ModelOrder};
+
+macro_rules! model_ids {
+    ($($(#[$meta:meta])* $name:ident => $id:literal),* $(,)*) => {
+        /// Identifier of a model known to this crate, or [`ModelId::Other`] for anything else
+        /// (fine-tuned models, newer releases, ...).
+        ///
+        /// Compares by the string id, so `ModelId::Other("gpt-4".into())` equals [`ModelId::Gpt4`].
+        #[derive(Debug, Clone, Serialize, Deserialize)]
+        #[serde(from = "String", into = "String")]
+        #[allow(non_camel_case_types)]
+        pub enum ModelId {
+            $(
+                $(#[$meta])*
+                #[doc = concat!("`", $id, "`")]
+                $name,
+            )*
+            Other(Cow<'static, str>),
+        }
+
+        impl ModelId {
+            pub const KNOWN: &'static [ModelId] = &[$(Self::$name),*];
+
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(Self::$name => $id,)*
+                    Self::Other(id) => id,
+                }
+            }
+        }
+
+        impl From<String> for ModelId {
+            fn from(value: String) -> Self {
+                match value.as_str() {
+                    $($id => Self::$name,)*
+                    _ => Self::Other(Cow::Owned(value)),
+                }
+            }
+        }
+    };
+}
+
+model_ids!(
+    Gpt4o => "gpt-4o",
+    Gpt4oMini => "gpt-4o-mini",
+    Gpt4oAudioPreview => "gpt-4o-audio-preview",
+    Gpt4oSearchPreview => "gpt-4o-search-preview",
+    Gpt4oMiniSearchPreview => "gpt-4o-mini-search-preview",
+    Gpt4Turbo => "gpt-4-turbo",
+    O1 => "o1",
+    O1Mini => "o1-mini",
+    O3 => "o3",
+    O3Mini => "o3-mini",
+    O4Mini => "o4-mini",
+    Gpt4 => "gpt-4",
+    Gpt4_0314 => "gpt-4-0314",
+    Gpt4_32k => "gpt-4-32k",
+    Gpt4_32k_0314 => "gpt-4-32k-0314",
+    Gpt35Turbo => "gpt-3.5-turbo",
+    Gpt35Turbo0301 => "gpt-3.5-turbo-0301",
+    TextDavinci003 => "text-davinci-003",
+    TextDavinci002 => "text-davinci-002",
+    TextCurie001 => "text-curie-001",
+    TextBabbage001 => "text-babbage-001",
+    TextAda001 => "text-ada-001",
+    Davinci => "davinci",
+    Curie => "curie",
+    Babbage => "babbage",
+    Ada => "ada",
+    TextDavinciEdit001 => "text-davinci-edit-001",
+    CodeDavinciEdit001 => "code-davinci-edit-001",
+    Whisper1 => "whisper-1",
+    DallE2 => "dall-e-2",
+    DallE3 => "dall-e-3",
+    GptImage1 => "gpt-image-1",
+    TextEmbedding3Small => "text-embedding-3-small",
+    TextEmbedding3Large => "text-embedding-3-large",
+    TextEmbeddingAda002 => "text-embedding-ada-002",
+    TextSearchAdaDoc001 => "text-search-ada-doc-001",
+    TextModerationStable => "text-moderation-stable",
+    TextModerationLatest => "text-moderation-latest",
+    OmniModerationLatest => "omni-moderation-latest",
+);
+
+impl ModelId {
+    /// Whether this is a reasoning model (o-series), which takes `max_completion_tokens` instead
+    /// of `max_tokens`.
+    pub fn is_reasoning(&self) -> bool {
+        let id = self.as_str();
+
+        ["o1", "o3", "o4"]
+            .iter()
+            .any(|family| id == *family || id.starts_with(&format!("{family}-")))
+    }
+
+    /// The id of the base model of a fine-tuned model (`ft:gpt-4o-mini-2024-07-18:org::id`),
+    /// the id itself otherwise.
+    fn base_id(&self) -> &str {
+        let id = self.as_str();
+
+        id.strip_prefix("ft:")
+            .and_then(|fine_tuned| fine_tuned.split(':').next())
+            .unwrap_or(id)
+    }
+
+    /// Comma separated list of the ids, for error messages.
+    pub fn join(ids: &[ModelId]) -> String {
+        ids.iter()
+            .map(ModelId::as_str)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Whether `id` is `family` or one of its snapshots (`gpt-4o-2024-08-06` for `gpt-4o`, ...).
+pub(crate) fn is_family(id: &str, family: &str) -> bool {
+    id.strip_prefix(family)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('-'))
+}
+
+/// The entry of `entries` with the longest id `id` is equal to or a snapshot of, see
+/// [`is_family`]. Shared by the tables kept by model family (prices, speeds, capabilities, ...).
+pub(crate) fn longest_family<T>(
+    entries: impl IntoIterator<Item = T>,
+    id: &str,
+    family: impl Fn(&T) -> &str,
+) -> Option<T> {
+    entries
+        .into_iter()
+        .filter(|entry| is_family(id, family(entry)))
+        .max_by_key(|entry| family(entry).len())
+}
+
+impl From<&str> for ModelId {
+    fn from(value: &str) -> Self {
+        value.to_string().into()
+    }
+}
+
+impl From<ModelId> for String {
+    fn from(value: ModelId) -> Self {
+        value.as_str().to_string()
+    }
+}
+
+impl FromStr for ModelId {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(s.into())
+    }
+}
+
+impl Display for ModelId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl AsRef<str> for ModelId {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq for ModelId {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for ModelId {}
+
+impl Hash for ModelId {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+/// A model returned by the API, bound to the [`crate::client::Client`] it was retrieved with so
+/// requests can be made with it.
+///
+/// Dereferences to its [`ModelInfo`].
+#[derive(Debug, Clone, getset::Getters)]
+pub struct Model<'client> {
     #[get = "pub"]
-    created: u64,
-    #[get = "pub"]
-    id: String,
-    #[get = "pub"]
-    owned_by: String,
-    #[get = "pub"]
-    parent: serde_json::Value, // TODO: parse this
+    client: &'client Client,
+
     #[get = "pub"]
-    permission: Vec<ModelPermission>,
+    info: ModelInfo,
 }
 
 impl<'client> Model<'client> {
-    pub const AUDIO_TRANSCRIPTIONS: &'static [&'static str] = &["whisper-1"];
-    pub const FINE_TUNES_COMPATIBLE: &'static [&'static str] =
-        &["davinci", "curie", "babbage", "ada"];
-    pub const EMBEDDINGS_COMPATIBLE: &'static [&'static str] =
-        &["text-embedding-ada-002", "text-search-ada-doc-001"];
-    pub const MODERATIONS_COMPATIBLE: &'static [&'static str] =
-        &["	text-moderation-stable", "text-moderation-latest"];
-
-    pub fn new_parse_json(
-        api_key: &'client String,
-        org_id: &'client Option<String>,
-
-        #[cfg(feature = "blocking")] blocking_client: &'client reqwest::blocking::Client,
-        async_client: &'client reqwest::Client,
-        json: &serde_json::Value,
-    ) -> error::Result<Self> {
-        let created = json
-            .get("created")
-            .and_then(|v| v.as_u64())
-            .ok_or(error::ParseError::FieldNotFound("created".to_string()))?;
-        let id = json
-            .get("id")
-            .and_then(|v| v.as_str())
-            .ok_or(error::ParseError::FieldNotFound("id".to_string()))?
-            .to_string();
-        let owned_by = json
-            .get("owned_by")
-            .and_then(|v| v.as_str())
-            .ok_or(error::ParseError::FieldNotFound("owned_by".to_string()))?
-            .to_string();
-        let parent = json
-            .get("parent")
-            .cloned()
-            .ok_or(error::ParseError::FieldNotFound("parent".to_string()))?;
-        let permission = json
-            .get("permission")
-            .and_then(|v| v.as_array())
-            .ok_or(error::ParseError::FieldNotFound("permission".to_string()).into())
-            .and_then(|arr| {
-                arr.iter()
-                    .map(ModelPermission::parse)
-                    .collect::<error::Result<Vec<ModelPermission>>>()
-            })?;
-
-        Ok(Self {
-            api_key,
-            org_id,
-
-            #[cfg(feature = "blocking")]
-            blocking_client,
-            async_client,
-
-            created,
-            id,
-            owned_by,
-            parent,
-            permission,
+    pub const AUDIO_TRANSCRIPTIONS: &'static [ModelId] = &[ModelId::Whisper1];
+    pub const IMAGE_GENERATIONS: &'static [ModelId] =
+        &[ModelId::DallE2, ModelId::DallE3, ModelId::GptImage1];
+    pub const FINE_TUNES_COMPATIBLE: &'static [ModelId] = &[
+        ModelId::Davinci,
+        ModelId::Curie,
+        ModelId::Babbage,
+        ModelId::Ada,
+    ];
+    pub const EMBEDDINGS_COMPATIBLE: &'static [ModelId] = &[
+        ModelId::TextEmbedding3Small,
+        ModelId::TextEmbedding3Large,
+        ModelId::TextEmbeddingAda002,
+        ModelId::TextSearchAdaDoc001,
+    ];
+    pub const MODERATIONS_COMPATIBLE: &'static [ModelId] = &[
+        ModelId::TextModerationStable,
+        ModelId::TextModerationLatest,
+        ModelId::OmniModerationLatest,
+    ];
+
+    pub fn new(client: &'client Client, info: ModelInfo) -> Self {
+        Self { client, info }
+    }
+
+    #[cfg(feature = "blocking")]
+    pub fn blocking_client(&self) -> crate::error::Result<&'client reqwest::blocking::Client> {
+        self.client.blocking_client()
+    }
+
+    pub fn async_client(&self) -> &'client reqwest::Client {
+        self.client.async_client()
+    }
+
+    pub fn into_info(self) -> ModelInfo {
+        self.info
+    }
+
+    /// The capabilities of the model, or of its parent when it is unknown (like a fine-tuned
+    /// model), see [`ModelId::capabilities`].
+    pub fn capabilities(&self) -> Option<ModelCapabilities> {
+        self.info.id.capabilities().or_else(|| {
+            self.info
+                .parent
+                .as_deref()
+                .and_then(|parent| ModelId::from(parent).capabilities())
         })
     }
 }
 
+impl<'client> Deref for Model<'client> {
+    type Target = ModelInfo;
+
+    fn deref(&self) -> &Self::Target {
+        &self.info
+    }
+}
+
 impl<'client> APIKeysAccess for Model<'client> {
     fn get_api_key(&self) -> &String {
-        self.api_key
+        self.client.get_api_key()
     }
 
     fn get_org_id(&self) -> &Option<String> {
-        self.org_id
+        self.client.get_org_id()
     }
 }
 
-#[derive(Debug, getset::Getters)]
+/// Data of a model as returned by the models endpoints.
+#[derive(Debug, Clone, Serialize, Deserialize, getset::Getters)]
+pub struct ModelInfo {
+    #[get = "pub"]
+    created: u64,
+    #[get = "pub"]
+    id: ModelId,
+    #[get = "pub"]
+    owned_by: String,
+    /// Id of the model this one was derived from, for example the base of a fine-tuned model.
+    #[get = "pub"]
+    #[serde(default)]
+    parent: Option<String>,
+    #[get = "pub"]
+    #[serde(default)]
+    permission: Vec<ModelPermission>,
+}
+
+impl ModelInfo {
+    /// Info with only the id known, for models that weren't retrieved from the API.
+    ///
+    /// `created` is 0, `owned_by` is empty and there is no parent nor permissions.
+    pub fn from_id(id: impl Into<ModelId>) -> Self {
+        Self {
+            created: 0,
+            id: id.into(),
+            owned_by: String::new(),
+            parent: None,
+            permission: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, getset::Getters)]
 pub struct ModelPermission {
     #[get = "pub"]
     allow_create_engine: bool,
@@ -111,8 +326,10 @@ pub struct ModelPermission {
     allow_view: bool,
     #[get = "pub"]
     created: u64,
+    /// Group the permission applies to, `None` when it isn't restricted to one.
     #[get = "pub"]
-    group: serde_json::Value, // TODO: parse this
+    #[serde(default)]
+    group: Option<String>,
     #[get = "pub"]
     id: String,
     #[get = "pub"]
@@ -121,71 +338,8 @@ pub struct ModelPermission {
     organization: String,
 }
 
-impl ModelPermission {
-    pub fn parse(json: &serde_json::Value) -> error::Result<Self> {
-        let allow_create_engine = json
-            .get("allow_create_engine")
-            .and_then(|v| v.as_bool())
-            .ok_or(error::ParseError::FieldNotFound(
-                "allow_create_engine".to_string(),
-            ))?;
-        let allow_fine_tuning = json
-            .get("allow_fine_tuning")
-            .and_then(|v| v.as_bool())
-            .ok_or(error::ParseError::FieldNotFound(
-                "allow_fine_tuning".to_string(),
-            ))?;
-        let allow_logprobs = json.get("allow_logprobs").and_then(|v| v.as_bool()).ok_or(
-            error::ParseError::FieldNotFound("allow_logprobs".to_string()),
-        )?;
-        let allow_sampling = json.get("allow_sampling").and_then(|v| v.as_bool()).ok_or(
-            error::ParseError::FieldNotFound("allow_sampling".to_string()),
-        )?;
-        let allow_search_indices = json
-            .get("allow_search_indices")
-            .and_then(|v| v.as_bool())
-            .ok_or(error::ParseError::FieldNotFound(
-                "allow_search_indices".to_string(),
-            ))?;
-        let allow_view = json
-            .get("allow_view")
-            .and_then(|v| v.as_bool())
-            .ok_or(error::ParseError::FieldNotFound("allow_view".to_string()))?;
-        let created = json
-            .get("created")
-            .and_then(|v| v.as_u64())
-            .ok_or(error::ParseError::FieldNotFound("created".to_string()))?;
-        let group = json
-            .get("group")
-            .cloned()
-            .ok_or(error::ParseError::FieldNotFound("group".to_string()))?;
-        let id = json
-            .get("id")
-            .and_then(|v| v.as_str())
-            .ok_or(error::ParseError::FieldNotFound("id".to_string()))?
-            .to_string();
-        let is_blocking = json
-            .get("is_blocking")
-            .and_then(|v| v.as_bool())
-            .ok_or(error::ParseError::FieldNotFound("is_blocking".to_string()))?;
-        let organization = json
-            .get("organization")
-            .and_then(|v| v.as_str())
-            .ok_or(error::ParseError::FieldNotFound("organization".to_string()))?
-            .to_string();
-
-        Ok(Self {
-            allow_create_engine,
-            allow_fine_tuning,
-            allow_logprobs,
-            allow_sampling,
-            allow_search_indices,
-            allow_view,
-            created,
-            group,
-            id,
-            is_blocking,
-            organization,
-        })
-    }
+/// Response of the models list endpoint.
+#[derive(Debug, Deserialize)]
+pub(crate) struct ModelList {
+    pub(crate) data: Vec<ModelInfo>,
 }
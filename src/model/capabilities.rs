@@ -0,0 +1,199 @@
+use serde::{Deserialize, Serialize};
+
+use super::{known, longest_family, ModelId};
+
+/// What a model can do, to enable features depending on the model at runtime. See
+/// [`ModelId::capabilities`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelCapabilities {
+    /// Whether the model works with the chat completions endpoint.
+    pub supports_chat: bool,
+    /// Whether the messages can contain images.
+    pub supports_vision: bool,
+    /// Whether the model can call `tools` (or the legacy `functions`).
+    pub supports_tools: bool,
+    /// Most tokens in a request, prompt and completion together.
+    pub context_window: Option<u64>,
+    /// Most tokens the model generates in a completion.
+    pub max_output_tokens: Option<u64>,
+}
+
+/// A feature of a model, see [`ModelCapabilities::supports`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    Chat,
+    Vision,
+    Tools,
+}
+
+impl ModelCapabilities {
+    pub fn supports(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::Chat => self.supports_chat,
+            Capability::Vision => self.supports_vision,
+            Capability::Tools => self.supports_tools,
+        }
+    }
+}
+
+/// Chat, vision and tool support of a model family, see [`REGISTRY`].
+struct Family {
+    id: &'static str,
+    supports_chat: bool,
+    supports_vision: bool,
+    supports_tools: bool,
+}
+
+impl Family {
+    const fn vision(mut self) -> Self {
+        self.supports_vision = true;
+        self
+    }
+
+    const fn tools(mut self) -> Self {
+        self.supports_tools = true;
+        self
+    }
+}
+
+/// A family working with the chat completions endpoint, without vision nor tools.
+const fn chat(id: &'static str) -> Family {
+    Family {
+        supports_chat: true,
+        ..other(id)
+    }
+}
+
+/// A family of completion, embedding, moderation, audio or image models.
+const fn other(id: &'static str) -> Family {
+    Family {
+        id,
+        supports_chat: false,
+        supports_vision: false,
+        supports_tools: false,
+    }
+}
+
+/// The model families, a family also covering its dated snapshots (`gpt-4o` covers
+/// `gpt-4o-2024-08-06`).
+const REGISTRY: &[Family] = &[
+    chat("gpt-4o").vision().tools(),
+    chat("gpt-4o-mini").vision().tools(),
+    chat("gpt-4o-audio-preview").tools(),
+    chat("gpt-4o-mini-audio-preview").tools(),
+    chat("gpt-4o-search-preview"),
+    chat("gpt-4o-mini-search-preview"),
+    chat("gpt-4.1").vision().tools(),
+    chat("gpt-4.1-mini").vision().tools(),
+    chat("gpt-4.1-nano").vision().tools(),
+    chat("gpt-4.5-preview").vision().tools(),
+    chat("gpt-4-turbo").vision().tools(),
+    chat("gpt-4-turbo-preview").tools(),
+    chat("gpt-4-1106-preview").tools(),
+    chat("gpt-4-0125-preview").tools(),
+    chat("gpt-4-vision-preview").vision(),
+    chat("gpt-4").tools(),
+    chat("gpt-4-0314"),
+    chat("gpt-4-32k").tools(),
+    chat("gpt-4-32k-0314"),
+    chat("gpt-3.5-turbo").tools(),
+    chat("gpt-3.5-turbo-0301"),
+    other("gpt-3.5-turbo-instruct"),
+    chat("o1").vision().tools(),
+    chat("o1-mini"),
+    chat("o1-preview"),
+    chat("o3").vision().tools(),
+    chat("o3-mini").tools(),
+    chat("o4-mini").vision().tools(),
+    other("text-davinci-003"),
+    other("text-davinci-002"),
+    other("text-curie-001"),
+    other("text-babbage-001"),
+    other("text-ada-001"),
+    other("davinci"),
+    other("curie"),
+    other("babbage"),
+    other("ada"),
+    other("davinci-002"),
+    other("babbage-002"),
+    other("text-davinci-edit-001"),
+    other("code-davinci-edit-001"),
+    other("text-embedding-3-small"),
+    other("text-embedding-3-large"),
+    other("text-embedding-ada-002"),
+    other("text-search-ada-doc-001"),
+    other("text-moderation-stable"),
+    other("text-moderation-latest"),
+    other("omni-moderation-latest"),
+    other("whisper-1"),
+    other("dall-e-2"),
+    other("dall-e-3"),
+    other("gpt-image-1"),
+];
+
+impl ModelId {
+    /// The capabilities of the model, [None] if this crate doesn't know it. Fine-tuned models
+    /// (`ft:gpt-4o-mini-2024-07-18:org::id`) have the ones of their base model.
+    pub fn capabilities(&self) -> Option<ModelCapabilities> {
+        let id = self.base_id();
+        let family = longest_family(REGISTRY, id, |family| family.id)?;
+        let known = known::get(id);
+
+        Some(ModelCapabilities {
+            supports_chat: family.supports_chat,
+            supports_vision: family.supports_vision,
+            supports_tools: family.supports_tools,
+            context_window: known.and_then(|known| known.context_window),
+            max_output_tokens: known.and_then(|known| known.max_output_tokens),
+        })
+    }
+
+    /// Most tokens the model handles in a request, prompt and completion together, [None] if it
+    /// is unknown.
+    pub fn context_length(&self) -> Option<u64> {
+        self.capabilities()?.context_window
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capabilities(id: &str) -> Option<ModelCapabilities> {
+        ModelId::from(id.to_string()).capabilities()
+    }
+
+    #[test]
+    fn families_cover_their_snapshots() {
+        let gpt_4o = capabilities("gpt-4o-2024-08-06").unwrap();
+
+        assert!(gpt_4o.supports(Capability::Chat));
+        assert!(gpt_4o.supports(Capability::Vision));
+        assert!(gpt_4o.supports(Capability::Tools));
+        assert_eq!(gpt_4o.context_window, Some(128_000));
+
+        // The longest family wins
+        let search = capabilities("gpt-4o-mini-search-preview-2025-03-11").unwrap();
+
+        assert!(search.supports_chat && !search.supports_vision && !search.supports_tools);
+    }
+
+    #[test]
+    fn fine_tunes_have_their_base_capabilities() {
+        assert_eq!(
+            capabilities("ft:gpt-4o-mini-2024-07-18:org::id"),
+            capabilities("gpt-4o-mini")
+        );
+    }
+
+    #[test]
+    fn other_and_unknown_models() {
+        let embedding = capabilities("text-embedding-3-small").unwrap();
+
+        assert!(!embedding.supports_chat);
+        assert_eq!(embedding.context_window, Some(8_191));
+        assert!(capabilities("gpt-4-0314").is_some_and(|gpt_4| !gpt_4.supports_tools));
+        assert_eq!(capabilities("unknown-model"), None);
+    }
+}
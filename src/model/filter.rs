@@ -0,0 +1,85 @@
+use super::{capabilities::Capability, Model};
+
+/// Which models [`crate::client::Client::list_models_filtered`] keeps, and in which order.
+#[derive(Debug, Clone, Default)]
+pub struct ModelFilter {
+    owner: Option<String>,
+    id_prefix: Option<String>,
+    capability: Option<Capability>,
+    order: Option<ModelOrder>,
+}
+
+/// Order of the filtered models, the one of the API when unset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelOrder {
+    /// By creation date, the most recent first.
+    NewestFirst,
+    /// By creation date, the oldest first.
+    OldestFirst,
+    /// By id, alphabetically.
+    Id,
+}
+
+impl ModelFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Models owned by `owner`: `openai`, `system`, an organization for fine-tuned models, ...
+    pub fn owned_by(mut self, owner: impl AsRef<str>) -> Self {
+        self.owner = Some(owner.as_ref().to_string());
+        self
+    }
+
+    /// Models whose id starts with `prefix`, like `gpt-4o` or `ft:`.
+    pub fn id_prefix(mut self, prefix: impl AsRef<str>) -> Self {
+        self.id_prefix = Some(prefix.as_ref().to_string());
+        self
+    }
+
+    /// Models known to have `capability`, see [`Model::capabilities`]. The unknown ones are left
+    /// out.
+    pub fn capability(mut self, capability: Capability) -> Self {
+        self.capability = Some(capability);
+        self
+    }
+
+    pub fn order(mut self, order: ModelOrder) -> Self {
+        self.order = Some(order);
+        self
+    }
+
+    pub fn matches(&self, model: &Model) -> bool {
+        self.owner
+            .as_ref()
+            .is_none_or(|owner| model.owned_by() == owner)
+            && self
+                .id_prefix
+                .as_ref()
+                .is_none_or(|prefix| model.id().as_str().starts_with(prefix.as_str()))
+            && self.capability.is_none_or(|capability| {
+                model
+                    .capabilities()
+                    .is_some_and(|capabilities| capabilities.supports(capability))
+            })
+    }
+
+    /// The models of `models` matching the filter, in its order.
+    pub fn apply<'client>(&self, models: Vec<Model<'client>>) -> Vec<Model<'client>> {
+        let mut models = models
+            .into_iter()
+            .filter(|model| self.matches(model))
+            .collect::<Vec<_>>();
+
+        match self.order {
+            Some(ModelOrder::NewestFirst) => {
+                models.sort_by(|a, b| b.created().cmp(a.created()));
+            }
+            Some(ModelOrder::OldestFirst) => models.sort_by_key(|model| *model.created()),
+            Some(ModelOrder::Id) => models.sort_by(|a, b| a.id().as_str().cmp(b.id().as_str())),
+            None => {}
+        }
+
+        models
+    }
+}
@@ -0,0 +1,237 @@
+use serde::Serialize;
+
+use super::{longest_family, ModelId};
+
+/// Metadata of a well-known model, see [`MODELS`].
+///
+/// The dates are ISO 8601 strings, `YYYY-MM` for the training cutoffs and `YYYY-MM-DD` for the
+/// deprecations, which compare in chronological order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct KnownModel {
+    /// Id of the model, covering its dated snapshots unless they have an entry of their own.
+    pub id: &'static str,
+    /// Most tokens in a request, prompt and completion together.
+    pub context_window: Option<u64>,
+    /// Most tokens the model generates in a completion.
+    pub max_output_tokens: Option<u64>,
+    /// Month of the most recent data the model was trained on.
+    pub training_cutoff: Option<&'static str>,
+    /// Day the deprecation of the model was announced.
+    pub deprecated_on: Option<&'static str>,
+    /// Day after which the API doesn't serve the model anymore.
+    pub shutdown_on: Option<&'static str>,
+}
+
+impl KnownModel {
+    const fn new(
+        id: &'static str,
+        context_window: Option<u64>,
+        max_output_tokens: Option<u64>,
+        training_cutoff: Option<&'static str>,
+    ) -> Self {
+        Self {
+            id,
+            context_window,
+            max_output_tokens,
+            training_cutoff,
+            deprecated_on: None,
+            shutdown_on: None,
+        }
+    }
+
+    const fn deprecated(mut self, deprecated_on: &'static str, shutdown_on: &'static str) -> Self {
+        self.deprecated_on = Some(deprecated_on);
+        self.shutdown_on = Some(shutdown_on);
+        self
+    }
+
+    /// Whether the deprecation of the model was announced.
+    pub fn is_deprecated(&self) -> bool {
+        self.deprecated_on.is_some()
+    }
+
+    /// Whether the API doesn't serve the model anymore on `date` (`YYYY-MM-DD`).
+    pub fn is_shut_down_on(&self, date: &str) -> bool {
+        self.shutdown_on
+            .is_some_and(|shutdown_on| shutdown_on < date)
+    }
+}
+
+/// The current UTC date, as `YYYY-MM-DD`.
+pub(crate) fn today() -> String {
+    let days = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() / 86_400);
+
+    civil_date(days)
+}
+
+/// The date `days` after 1970-01-01, as `YYYY-MM-DD`.
+fn civil_date(days: u64) -> String {
+    // In eras of 400 years starting on March 1st
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+const fn model(id: &'static str, context: u64, output: u64, cutoff: &'static str) -> KnownModel {
+    KnownModel::new(id, Some(context), Some(output), Some(cutoff))
+}
+
+const fn legacy(id: &'static str, context: u64, cutoff: &'static str) -> KnownModel {
+    KnownModel::new(id, Some(context), Some(context), Some(cutoff))
+        .deprecated("2023-07-06", "2024-01-04")
+}
+
+/// The well-known models, as published in the model and deprecation pages of the API
+/// documentation.
+pub const MODELS: &[KnownModel] = &[
+    model("gpt-4o", 128_000, 16_384, "2023-10"),
+    model("gpt-4o-2024-05-13", 128_000, 4_096, "2023-10"),
+    model("gpt-4o-mini", 128_000, 16_384, "2023-10"),
+    model("gpt-4o-audio-preview", 128_000, 16_384, "2023-10"),
+    model("gpt-4o-mini-audio-preview", 128_000, 16_384, "2023-10"),
+    model("gpt-4o-search-preview", 128_000, 16_384, "2023-10"),
+    model("gpt-4o-mini-search-preview", 128_000, 16_384, "2023-10"),
+    model("gpt-4.1", 1_047_576, 32_768, "2024-06"),
+    model("gpt-4.1-mini", 1_047_576, 32_768, "2024-06"),
+    model("gpt-4.1-nano", 1_047_576, 32_768, "2024-06"),
+    model("gpt-4.5-preview", 128_000, 16_384, "2023-10").deprecated("2025-04-14", "2025-07-14"),
+    model("gpt-4-turbo", 128_000, 4_096, "2023-12"),
+    model("gpt-4-turbo-preview", 128_000, 4_096, "2023-12"),
+    model("gpt-4-0125-preview", 128_000, 4_096, "2023-12"),
+    model("gpt-4-1106-preview", 128_000, 4_096, "2023-04"),
+    model("gpt-4-vision-preview", 128_000, 4_096, "2023-04").deprecated("2024-06-06", "2024-12-06"),
+    model("gpt-4", 8_192, 8_192, "2021-09"),
+    model("gpt-4-0314", 8_192, 8_192, "2021-09").deprecated("2023-06-13", "2024-06-13"),
+    model("gpt-4-32k", 32_768, 32_768, "2021-09").deprecated("2024-06-06", "2025-06-06"),
+    model("gpt-4-32k-0314", 32_768, 32_768, "2021-09").deprecated("2023-06-13", "2025-06-06"),
+    model("gpt-3.5-turbo", 16_385, 4_096, "2021-09"),
+    model("gpt-3.5-turbo-0301", 4_096, 4_096, "2021-09").deprecated("2023-06-13", "2024-09-13"),
+    model("gpt-3.5-turbo-0613", 4_096, 4_096, "2021-09").deprecated("2023-11-06", "2024-09-13"),
+    model("gpt-3.5-turbo-16k", 16_385, 4_096, "2021-09"),
+    model("gpt-3.5-turbo-16k-0613", 16_385, 4_096, "2021-09")
+        .deprecated("2023-11-06", "2024-09-13"),
+    model("gpt-3.5-turbo-instruct", 4_096, 4_096, "2021-09"),
+    model("o1", 200_000, 100_000, "2023-10"),
+    model("o1-mini", 128_000, 65_536, "2023-10"),
+    model("o1-preview", 128_000, 32_768, "2023-10").deprecated("2025-04-28", "2025-07-28"),
+    model("o3", 200_000, 100_000, "2024-06"),
+    model("o3-mini", 200_000, 100_000, "2023-10"),
+    model("o4-mini", 200_000, 100_000, "2024-06"),
+    legacy("text-davinci-003", 4_097, "2021-06"),
+    legacy("text-davinci-002", 4_097, "2021-06"),
+    legacy("text-curie-001", 2_049, "2019-10"),
+    legacy("text-babbage-001", 2_049, "2019-10"),
+    legacy("text-ada-001", 2_049, "2019-10"),
+    legacy("davinci", 2_049, "2019-10"),
+    legacy("curie", 2_049, "2019-10"),
+    legacy("babbage", 2_049, "2019-10"),
+    legacy("ada", 2_049, "2019-10"),
+    model("davinci-002", 16_384, 16_384, "2021-09"),
+    model("babbage-002", 16_384, 16_384, "2021-09"),
+    KnownModel::new("text-davinci-edit-001", None, None, None)
+        .deprecated("2023-07-06", "2024-01-04"),
+    KnownModel::new("code-davinci-edit-001", None, None, None)
+        .deprecated("2023-07-06", "2024-01-04"),
+    KnownModel::new("text-embedding-3-small", Some(8_191), None, Some("2021-09")),
+    KnownModel::new("text-embedding-3-large", Some(8_191), None, Some("2021-09")),
+    KnownModel::new("text-embedding-ada-002", Some(8_191), None, Some("2021-09")),
+    KnownModel::new("text-search-ada-doc-001", Some(2_046), None, None)
+        .deprecated("2023-07-06", "2024-01-04"),
+    KnownModel::new("text-moderation-stable", Some(32_768), None, None),
+    KnownModel::new("text-moderation-latest", Some(32_768), None, None),
+    KnownModel::new("omni-moderation-latest", None, None, None),
+    KnownModel::new("whisper-1", None, None, None),
+    KnownModel::new("dall-e-2", None, None, None),
+    KnownModel::new("dall-e-3", None, None, None),
+    KnownModel::new("gpt-image-1", None, None, None),
+];
+
+/// The entry of `id`, or of the closest model it is a snapshot of.
+pub fn get(id: &str) -> Option<&'static KnownModel> {
+    longest_family(MODELS, id, |model| model.id)
+}
+
+impl ModelId {
+    /// The metadata of the model, [None] if it isn't well-known. Fine-tuned models have the ones
+    /// of their base model.
+    pub fn known(&self) -> Option<&'static KnownModel> {
+        get(self.base_id())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_of_day_counts() {
+        let dates = [
+            (0, "1970-01-01"),
+            (10_956, "1999-12-31"),
+            (11_016, "2000-02-29"),
+            (11_017, "2000-03-01"),
+            (19_722, "2023-12-31"),
+            (19_723, "2024-01-01"),
+            (19_782, "2024-02-29"),
+            (47_540, "2100-02-28"),
+            (47_541, "2100-03-01"),
+        ];
+
+        for (days, date) in dates {
+            assert_eq!(civil_date(days), date, "{days} days after the epoch");
+        }
+    }
+
+    #[test]
+    fn today_is_a_date() {
+        let today = today();
+
+        assert_eq!(today.len(), 10);
+        assert!(today.as_str() > "2024-01-01");
+        assert!(today
+            .split('-')
+            .all(|part| part.chars().all(|c| c.is_ascii_digit())));
+    }
+
+    #[test]
+    fn snapshots_and_fine_tunes_have_the_family_entry() {
+        assert_eq!(get("gpt-4o-2024-08-06").unwrap().id, "gpt-4o");
+        assert_eq!(
+            get("gpt-4o-2024-05-13").unwrap().max_output_tokens,
+            Some(4_096)
+        );
+        assert_eq!(
+            ModelId::from("ft:gpt-4o-mini-2024-07-18:org::id".to_string())
+                .known()
+                .unwrap()
+                .id,
+            "gpt-4o-mini"
+        );
+        assert!(get("unknown-model").is_none());
+    }
+
+    #[test]
+    fn shutdowns() {
+        let davinci = get("text-davinci-003").unwrap();
+
+        assert!(davinci.is_deprecated());
+        assert!(!davinci.is_shut_down_on("2024-01-04"));
+        assert!(davinci.is_shut_down_on("2024-01-05"));
+        assert!(!get("gpt-4o").unwrap().is_deprecated());
+    }
+}
@@ -0,0 +1,282 @@
+use std::{collections::VecDeque, marker::PhantomData};
+
+use futures_util::{stream, Stream};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{client::Client, error, model::ModelInfo, APIKeysAccess};
+
+/// An item of a cursor paginated list, identified by the cursor used to fetch the next page.
+pub trait Paginated: DeserializeOwned {
+    fn cursor(&self) -> &str;
+}
+
+impl Paginated for ModelInfo {
+    fn cursor(&self) -> &str {
+        self.id().as_str()
+    }
+}
+
+/// One page of a cursor paginated list endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub data: Vec<T>,
+    #[serde(default)]
+    pub first_id: Option<String>,
+    #[serde(default)]
+    pub last_id: Option<String>,
+    #[serde(default)]
+    pub has_more: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum Order {
+    #[serde(rename = "asc")]
+    Asc,
+    #[serde(rename = "desc")]
+    Desc,
+}
+
+impl Order {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Asc => "asc",
+            Self::Desc => "desc",
+        }
+    }
+}
+
+/// Query parameters of a list endpoint.
+#[derive(Debug, Clone, Default, getset::Getters)]
+pub struct PageParams {
+    /// Cursor to start after, the id of the last item of the previous page.
+    #[get = "pub"]
+    after: Option<String>,
+    /// Number of items per page, between 1 and 100 for most endpoints.
+    #[get = "pub"]
+    limit: Option<u32>,
+    /// Sort order by the `created_at` timestamp.
+    #[get = "pub"]
+    order: Option<Order>,
+}
+
+impl PageParams {
+    pub fn with_after(mut self, after: impl AsRef<str>) -> Self {
+        self.after = Some(after.as_ref().to_string());
+        self
+    }
+
+    pub fn with_limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn with_order(mut self, order: Order) -> Self {
+        self.order = Some(order);
+        self
+    }
+
+    fn query(&self) -> Vec<(&'static str, String)> {
+        let mut query = Vec::new();
+
+        if let Some(after) = &self.after {
+            query.push(("after", after.clone()));
+        }
+
+        if let Some(limit) = self.limit {
+            query.push(("limit", limit.to_string()));
+        }
+
+        if let Some(order) = self.order {
+            query.push(("order", order.as_str().to_string()));
+        }
+
+        query
+    }
+}
+
+/// Walks a cursor paginated list endpoint page by page.
+pub struct Paginator<'client, T> {
+    client: &'client Client,
+    url: String,
+    params: PageParams,
+    done: bool,
+
+    _item: PhantomData<T>,
+}
+
+impl<'client, T> Paginator<'client, T>
+where
+    T: Paginated,
+{
+    pub fn new(client: &'client Client, url: impl AsRef<str>, params: PageParams) -> Self {
+        Self {
+            client,
+            url: url.as_ref().to_string(),
+            params,
+            done: false,
+
+            _item: PhantomData,
+        }
+    }
+
+    /// Fetches the next page, [None] once the last one has been returned. A page repeating the
+    /// cursor of the previous one is the last too, even with `has_more`, instead of being fetched
+    /// again forever.
+    pub async fn next_page(&mut self) -> error::Result<Option<Page<T>>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let url = self.client.resolve_url(&self.url)?;
+        let request = self
+            .client
+            .async_client()
+            .get(url)
+            .headers(self.client.common_headers())
+            .query(&self.params.query());
+        let body = self.client.send(request).await?.bytes().await?;
+        let page = self.client.parse::<Page<T>>(&body)?;
+
+        let cursor = page
+            .last_id
+            .clone()
+            .or_else(|| page.data.last().map(|item| item.cursor().to_string()));
+
+        match cursor {
+            Some(cursor) if page.has_more && self.params.after.as_ref() != Some(&cursor) => {
+                self.params.after = Some(cursor)
+            }
+            _ => self.done = true,
+        }
+
+        Ok(Some(page))
+    }
+
+    /// Stream of every item of every page, fetching the pages lazily.
+    pub fn into_stream(self) -> impl Stream<Item = error::Result<T>> + 'client
+    where
+        T: 'client,
+    {
+        stream::try_unfold(
+            (self, VecDeque::new()),
+            |(mut paginator, mut items)| async move {
+                loop {
+                    if let Some(item) = items.pop_front() {
+                        return Ok(Some((item, (paginator, items))));
+                    }
+
+                    match paginator.next_page().await? {
+                        Some(page) => items.extend(page.data),
+                        None => return Ok(None),
+                    }
+                }
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::TryStreamExt;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        client::BASE_URL,
+        model::ModelId,
+        test_util::{
+            self, fixtures, json_response,
+            wiremock::{
+                matchers::{query_param, query_param_is_missing},
+                MockServer,
+            },
+        },
+    };
+
+    fn page(model_ids: impl IntoIterator<Item = ModelId>, has_more: bool) -> serde_json::Value {
+        let mut page = fixtures::model_list(model_ids);
+
+        page["has_more"] = json!(has_more);
+
+        page
+    }
+
+    async fn mock_pages(server: &MockServer) {
+        test_util::models()
+            .and(query_param_is_missing("after"))
+            .respond_with(json_response(page(
+                [ModelId::Gpt4o, ModelId::Gpt4oMini],
+                true,
+            )))
+            .expect(1)
+            .mount(server)
+            .await;
+        test_util::models()
+            .and(query_param("after", "gpt-4o-mini"))
+            .respond_with(json_response(page([ModelId::Gpt4], false)))
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    fn ids(models: &[ModelInfo]) -> Vec<&str> {
+        models.iter().map(Paginated::cursor).collect()
+    }
+
+    #[tokio::test]
+    async fn walks_the_pages_after_the_last_item_of_the_previous_one() {
+        let server = MockServer::start().await;
+
+        mock_pages(&server).await;
+
+        let client = test_util::client(&server);
+        let mut paginator =
+            client.paginate::<ModelInfo>(format!("{BASE_URL}/models"), PageParams::default());
+
+        let first = paginator.next_page().await.unwrap().unwrap();
+        let last = paginator.next_page().await.unwrap().unwrap();
+
+        assert_eq!(ids(&first.data), ["gpt-4o", "gpt-4o-mini"]);
+        assert_eq!(ids(&last.data), ["gpt-4"]);
+        assert!(paginator.next_page().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn streams_the_items_of_every_page() {
+        let server = MockServer::start().await;
+
+        mock_pages(&server).await;
+
+        let client = test_util::client(&server);
+        let models = client
+            .paginate::<ModelInfo>(format!("{BASE_URL}/models"), PageParams::default())
+            .into_stream()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        assert_eq!(ids(&models), ["gpt-4o", "gpt-4o-mini", "gpt-4"]);
+    }
+
+    #[tokio::test]
+    async fn stops_when_the_cursor_doesnt_advance() {
+        let server = MockServer::start().await;
+        let mut stuck = page([ModelId::Gpt4o], true);
+
+        stuck["last_id"] = json!("gpt-4o");
+
+        test_util::models()
+            .respond_with(json_response(stuck))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let client = test_util::client(&server);
+        let mut paginator =
+            client.paginate::<ModelInfo>(format!("{BASE_URL}/models"), PageParams::default());
+
+        assert!(paginator.next_page().await.unwrap().is_some());
+        assert!(paginator.next_page().await.unwrap().is_some());
+        assert!(paginator.next_page().await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,177 @@
+use std::{collections::BTreeMap, sync::OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{model::longest_family, request::Usage};
+
+/// Price of a model, in USD per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub prompt: f64,
+    /// 0 for the models that don't generate anything, like embeddings.
+    #[serde(default)]
+    pub completion: f64,
+}
+
+impl ModelPrice {
+    pub const fn new(prompt: f64, completion: f64) -> Self {
+        Self { prompt, completion }
+    }
+
+    /// Cost of `usage` in USD.
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        (usage.prompt_tokens as f64 * self.prompt
+            + usage.completion_tokens as f64 * self.completion)
+            / 1_000_000.0
+    }
+}
+
+/// Prices of the models, to estimate what the responses cost from their token usage.
+///
+/// The [`Default`] table is bundled with the crate and reflects the public prices at the time of
+/// the release, prices change: update it with [`Self::set`], or load a whole table from JSON
+/// (`{"gpt-4o": {"prompt": 2.5, "completion": 10.0}, ...}`) with serde.
+///
+/// Models are looked up by their id, falling back to the longest known prefix so the dated
+/// snapshots (`gpt-4o-2024-08-06`, `omni-moderation-latest`, ...) get the price of their
+/// family.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PricingTable {
+    prices: BTreeMap<String, ModelPrice>,
+}
+
+/// USD per million (prompt, completion) tokens.
+const BUNDLED_PRICES: &[(&str, f64, f64)] = &[
+    ("gpt-4o", 2.5, 10.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4o-audio-preview", 2.5, 10.0),
+    ("gpt-4o-search-preview", 2.5, 10.0),
+    ("gpt-4o-mini-search-preview", 0.15, 0.6),
+    ("gpt-4-turbo", 10.0, 30.0),
+    ("gpt-4", 30.0, 60.0),
+    ("gpt-4-32k", 60.0, 120.0),
+    ("gpt-3.5-turbo", 0.5, 1.5),
+    ("gpt-3.5-turbo-instruct", 1.5, 2.0),
+    ("o1", 15.0, 60.0),
+    ("o1-mini", 1.1, 4.4),
+    ("o3", 2.0, 8.0),
+    ("o3-mini", 1.1, 4.4),
+    ("o4-mini", 1.1, 4.4),
+    ("text-davinci-003", 20.0, 20.0),
+    ("text-embedding-3-small", 0.02, 0.0),
+    ("text-embedding-3-large", 0.13, 0.0),
+    ("text-embedding-ada-002", 0.1, 0.0),
+    ("omni-moderation", 0.0, 0.0),
+    ("text-moderation", 0.0, 0.0),
+];
+
+impl PricingTable {
+    /// A table without any price.
+    pub fn empty() -> Self {
+        Self {
+            prices: BTreeMap::new(),
+        }
+    }
+
+    /// The table bundled with the crate, shared.
+    pub fn bundled() -> &'static Self {
+        static BUNDLED: OnceLock<PricingTable> = OnceLock::new();
+
+        BUNDLED.get_or_init(Self::default)
+    }
+
+    /// Sets the price of `model`, and of its snapshots without a price of their own.
+    pub fn set(mut self, model: impl AsRef<str>, price: ModelPrice) -> Self {
+        self.prices.insert(model.as_ref().to_string(), price);
+        self
+    }
+
+    pub fn remove(mut self, model: impl AsRef<str>) -> Self {
+        self.prices.remove(model.as_ref());
+        self
+    }
+
+    /// Price of `model`, [None] if neither it nor a prefix of it is known.
+    pub fn price(&self, model: &str) -> Option<ModelPrice> {
+        longest_family(&self.prices, model, |(family, _)| family.as_str()).map(|(_, price)| *price)
+    }
+
+    /// Cost of `usage` by `model` in USD, [None] if the model has no price.
+    pub fn cost(&self, model: &str, usage: &Usage) -> Option<f64> {
+        self.price(model).map(|price| price.cost(usage))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ModelPrice)> {
+        self.prices
+            .iter()
+            .map(|(model, price)| (model.as_str(), price))
+    }
+}
+
+impl Default for PricingTable {
+    fn default() -> Self {
+        BUNDLED_PRICES
+            .iter()
+            .fold(Self::empty(), |table, (model, prompt, completion)| {
+                table.set(model, ModelPrice::new(*prompt, *completion))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        client::Client, request::text_completion::TextCompletionResponse, test_util::fixtures,
+    };
+
+    #[test]
+    fn snapshots_get_the_price_of_their_family() {
+        let pricing = PricingTable::default();
+
+        assert_eq!(pricing.price("gpt-4o"), Some(ModelPrice::new(2.5, 10.0)));
+        assert_eq!(pricing.price("gpt-4o-2024-08-06"), pricing.price("gpt-4o"));
+        assert_eq!(
+            pricing.price("gpt-4o-mini-2024-07-18"),
+            pricing.price("gpt-4o-mini")
+        );
+        assert_eq!(
+            pricing.price("omni-moderation-latest"),
+            Some(ModelPrice::new(0.0, 0.0))
+        );
+        assert_eq!(pricing.price("gpt-4oo"), None);
+        assert_eq!(pricing.price("ft:gpt-4o"), None);
+    }
+
+    #[test]
+    fn costs_are_per_million_tokens() {
+        let usage = Usage {
+            prompt_tokens: 1_000,
+            completion_tokens: 500,
+            total_tokens: 1_500,
+        };
+
+        assert_eq!(ModelPrice::new(2.0, 8.0).cost(&usage), 0.006);
+        assert_eq!(PricingTable::empty().cost("gpt-4o", &usage), None);
+    }
+
+    #[test]
+    fn responses_are_priced_with_the_table_of_the_client() {
+        let pricing = serde_json::from_value::<PricingTable>(serde_json::json!({
+            "text-davinci-003": { "prompt": 1_000_000.0 },
+        }))
+        .unwrap();
+        let client = Client::new("").pricing(pricing);
+        let response =
+            serde_json::from_value::<TextCompletionResponse>(fixtures::text_completion("Hello"))
+                .unwrap();
+
+        // 5 prompt tokens, the completion ones are free
+        assert_eq!(response.estimated_cost(client.pricing_table()), Some(5.0));
+        assert_eq!(
+            response.estimated_cost(PricingTable::bundled()),
+            Some(12.0 * 20.0 / 1_000_000.0)
+        );
+    }
+}
@@ -0,0 +1,234 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use reqwest::header::HeaderMap;
+
+use crate::response::{self, RateLimit};
+
+/// Delays outgoing requests once the `x-ratelimit-*` headers of the previous responses say the
+/// limits of an API key are exhausted, until they reset, instead of letting the API answer with
+/// `429 Too Many Requests`.
+///
+/// The limits are tracked per API key, so clients sharing a key should share a limiter as well,
+/// see [`crate::client::Client::rate_limiter`]. Cloning a limiter shares its state.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter {
+    keys: Arc<Mutex<HashMap<String, KeyLimits>>>,
+}
+
+#[derive(Debug, Default)]
+struct KeyLimits {
+    remaining_requests: Option<u64>,
+    remaining_tokens: Option<u64>,
+    requests_reset_at: Option<Instant>,
+    tokens_reset_at: Option<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Time to wait before a request with `api_key` can be sent, [None] if it can be sent now,
+    /// in which case it is counted against the remaining requests.
+    pub fn reserve(&self, api_key: &str) -> Option<Duration> {
+        let now = Instant::now();
+        let mut keys = self.keys.lock().unwrap_or_else(|e| e.into_inner());
+        let limits = keys.entry(api_key.to_string()).or_default();
+
+        if limits
+            .requests_reset_at
+            .is_some_and(|reset_at| reset_at <= now)
+        {
+            limits.remaining_requests = None;
+            limits.requests_reset_at = None;
+        }
+
+        if limits
+            .tokens_reset_at
+            .is_some_and(|reset_at| reset_at <= now)
+        {
+            limits.remaining_tokens = None;
+            limits.tokens_reset_at = None;
+        }
+
+        let requests_delay = (limits.remaining_requests == Some(0))
+            .then(|| limits.requests_reset_at.map(|reset_at| reset_at - now))
+            .flatten();
+        let tokens_delay = (limits.remaining_tokens == Some(0))
+            .then(|| limits.tokens_reset_at.map(|reset_at| reset_at - now))
+            .flatten();
+
+        match requests_delay.max(tokens_delay) {
+            Some(delay) => Some(delay),
+            None => {
+                if let Some(remaining) = &mut limits.remaining_requests {
+                    *remaining = remaining.saturating_sub(1);
+                }
+
+                None
+            }
+        }
+    }
+
+    /// Waits until a request with `api_key` can be sent.
+    pub async fn wait(&self, api_key: &str) {
+        while let Some(delay) = self.reserve(api_key) {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// (Blocking) Waits until a request with `api_key` can be sent.
+    #[cfg(feature = "blocking")]
+    pub fn wait_blocking(&self, api_key: &str) {
+        while let Some(delay) = self.reserve(api_key) {
+            std::thread::sleep(delay);
+        }
+    }
+
+    /// Records the limits sent with a response. A `retry-after` header, sent with the `429`
+    /// responses, holds back every request until it elapsed.
+    pub fn update(&self, api_key: &str, headers: &HeaderMap) {
+        let now = Instant::now();
+        let rate_limit = RateLimit::from_headers(headers);
+        let retry_after = response::parse_retry_after(headers);
+
+        let mut keys = self.keys.lock().unwrap_or_else(|e| e.into_inner());
+        let limits = keys.entry(api_key.to_string()).or_default();
+
+        if let Some(remaining) = rate_limit.remaining_requests() {
+            limits.remaining_requests = Some(*remaining);
+            limits.requests_reset_at = rate_limit.reset_requests().map(|reset| now + reset);
+        }
+
+        if let Some(remaining) = rate_limit.remaining_tokens() {
+            limits.remaining_tokens = Some(*remaining);
+            limits.tokens_reset_at = rate_limit.reset_tokens().map(|reset| now + reset);
+        }
+
+        if let Some(retry_after) = retry_after {
+            limits.remaining_requests = Some(0);
+            limits.requests_reset_at = Some(now + retry_after);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::{HeaderName, HeaderValue};
+
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|&(name, value)| {
+                (
+                    HeaderName::from_static(name),
+                    HeaderValue::from_str(value).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn limiter(pairs: &[(&'static str, &str)]) -> RateLimiter {
+        let limiter = RateLimiter::new();
+
+        limiter.update("key", &headers(pairs));
+
+        limiter
+    }
+
+    #[test]
+    fn exhausted_limits_wait_for_their_reset() {
+        let requests = limiter(&[
+            ("x-ratelimit-remaining-requests", "0"),
+            ("x-ratelimit-reset-requests", "6m0s"),
+        ])
+        .reserve("key")
+        .unwrap();
+        let tokens = limiter(&[
+            ("x-ratelimit-remaining-tokens", "0"),
+            ("x-ratelimit-reset-tokens", "1m30.5s"),
+        ])
+        .reserve("key")
+        .unwrap();
+
+        assert!(requests > Duration::from_secs(359) && requests <= Duration::from_secs(360));
+        assert!(tokens > Duration::from_secs(90) && tokens <= Duration::from_millis(90_500));
+    }
+
+    #[test]
+    fn the_longest_reset_is_waited_for() {
+        let delay = limiter(&[
+            ("x-ratelimit-remaining-requests", "0"),
+            ("x-ratelimit-reset-requests", "20ms"),
+            ("x-ratelimit-remaining-tokens", "0"),
+            ("x-ratelimit-reset-tokens", "2s"),
+        ])
+        .reserve("key")
+        .unwrap();
+
+        assert!(delay > Duration::from_secs(1));
+    }
+
+    #[test]
+    fn remaining_requests_are_counted_down() {
+        let limiter = limiter(&[
+            ("x-ratelimit-remaining-requests", "2"),
+            ("x-ratelimit-reset-requests", "1s"),
+        ]);
+
+        assert_eq!(limiter.reserve("key"), None);
+        assert_eq!(limiter.reserve("key"), None);
+        assert!(limiter.reserve("key").is_some());
+        assert_eq!(limiter.reserve("other key"), None);
+    }
+
+    #[test]
+    fn retry_after_holds_back_every_request() {
+        let delay = limiter(&[
+            ("x-ratelimit-remaining-requests", "100"),
+            ("retry-after", " 0.5 "),
+        ])
+        .reserve("key")
+        .unwrap();
+
+        assert!(delay > Duration::ZERO && delay <= Duration::from_millis(500));
+    }
+
+    #[test]
+    fn elapsed_resets_clear_the_limits() {
+        let limiter = limiter(&[
+            ("x-ratelimit-remaining-requests", "0"),
+            ("x-ratelimit-reset-requests", "10ms"),
+        ]);
+
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert_eq!(limiter.reserve("key"), None);
+    }
+
+    #[test]
+    fn unparsable_headers_are_ignored() {
+        let limiter = limiter(&[
+            ("x-ratelimit-remaining-requests", "none"),
+            ("x-ratelimit-remaining-tokens", "0"),
+            ("x-ratelimit-reset-tokens", "6 minutes"),
+            ("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT"),
+        ]);
+
+        assert_eq!(limiter.reserve("key"), None);
+
+        for retry_after in ["-1", "NaN", "inf", "1e300"] {
+            let limiter = RateLimiter::new();
+
+            limiter.update("key", &headers(&[("retry-after", retry_after)]));
+
+            assert_eq!(limiter.reserve("key"), None);
+        }
+    }
+}
@@ -1,57 +1,110 @@
-use std::{collections::BTreeMap, num::NonZeroU64};
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+    num::NonZeroU64,
+    ops::{Add, AddAssign},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use async_trait::async_trait;
-use serde::Deserialize;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use serde::{Deserialize, Serialize};
 
 use macros::rq;
 
 use crate::{
+    cache::ResponseCache,
+    dedup::Role,
     error,
-    model::Model,
+    model::{Model, ModelId},
     request::{
-        chat_completion::{ChatCompletionResponse, ChatMessage},
+        chat_completion::{
+            AudioOutput, ChatCompletionResponse, ChatMessage, FunctionCallMode, Modality,
+            ReasoningEffort, ResponseFormat, ServiceTier, WebSearchOptions,
+        },
         edit::EditResponse,
-        text_completion::TextCompletionResponse,
+        embedding::{EmbeddingInput, EmbeddingResponse},
+        moderation::{ModerationInput, ModerationResponse},
+        parameters::{FrequencyPenalty, PresencePenalty, Temperature, TopP},
+        text_completion::{Prompt, TextCompletionResponse},
     },
+    response::{ResponseMeta, WithMeta},
+    timeout,
+    tool::{FunctionDefinition, Tool},
     APIKeysAccess,
 };
 
 pub mod chat_completion;
 pub mod edit;
+pub mod embedding;
+pub mod moderation;
+pub mod parameters;
 pub mod text_completion;
 
 #[rq(
     TextCompletion(
         doc("Given a prompt, the model will return one or more predicted completions, and can also return the probabilities of alternative tokens at each position."),
         url("https://api.openai.com/v1/completions"),
+        validate(text_completion::validate),
+        output_tokens(text_completion::output_tokens),
         compatible_models(
-            "text-davinci-003",
-            "text-davinci-002",
-            "text-curie-001",
-            "text-babbage-001",
-            "text-ada-001",
-            "davinci",
-            "curie",
-            "babbage",
-            "ada",
+            TextDavinci003,
+            TextDavinci002,
+            TextCurie001,
+            TextBabbage001,
+            TextAda001,
+            Davinci,
+            Curie,
+            Babbage,
+            Ada,
         )
     ),
     ChatCompletion(
         doc("Given a chat conversation, the model will return a chat completion response."),
         url("https://api.openai.com/v1/chat/completions"),
+        validate(chat_completion::validate),
+        transform_json(chat_completion::transform_json),
+        moderation_input(chat_completion::moderation_input),
+        output_tokens(chat_completion::output_tokens),
         compatible_models(
-            "gpt-4",
-            "gpt-4-0314",
-            "gpt-4-32k",
-            "gpt-4-32k-0314",
-            "gpt-3.5-turbo",
-            "gpt-3.5-turbo-0301",
+            Gpt4o,
+            Gpt4oMini,
+            Gpt4oAudioPreview,
+            Gpt4oSearchPreview,
+            Gpt4oMiniSearchPreview,
+            Gpt4Turbo,
+            O1,
+            O1Mini,
+            O3,
+            O3Mini,
+            O4Mini,
+            Gpt4,
+            Gpt4_0314,
+            Gpt4_32k,
+            Gpt4_32k_0314,
+            Gpt35Turbo,
+            Gpt35Turbo0301,
         )
     ),
     Edit(
         doc("Creates a new edit for the provided input, instruction, and parameters."),
         url("https://api.openai.com/v1/edits"),
-        compatible_models("text-davinci-edit-001", "code-davinci-edit-001")
+        compatible_models(TextDavinciEdit001, CodeDavinciEdit001)
+    ),
+    Moderation(
+        doc("Classifies if text is potentially harmful across several categories."),
+        url("https://api.openai.com/v1/moderations"),
+        transform_json(moderation::transform_json),
+        output_tokens(timeout::no_output_tokens),
+        compatible_models(TextModerationStable, TextModerationLatest, OmniModerationLatest)
+    ),
+    Embedding(
+        doc("Creates an embedding vector representing the input text."),
+        url("https://api.openai.com/v1/embeddings"),
+        transform_json(embedding::transform_json),
+        output_tokens(timeout::no_output_tokens),
+        compatible_models(TextEmbedding3Small, TextEmbedding3Large, TextEmbeddingAda002)
     )
 )]
 pub struct RequestBody {
@@ -70,7 +123,7 @@ pub struct RequestBody {
     /// so if a `prompt` is not specified the model will generate as if from the beginning of a new
     /// document.
     #[rq(on(TextCompletion))]
-    prompt: Option<Vec<String>>,
+    prompt: Option<Prompt>,
     /// Optional. Defaults to null.
     ///
     /// The `suffix` that comes after a completion of inserted text.
@@ -84,16 +137,33 @@ pub struct RequestBody {
     ///
     /// Most models have a context length of 2048 tokens
     /// (except for the newest models, which support 4096).
+    ///
+    /// Reasoning models reject it in favor of `max_completion_tokens`, chat requests to them
+    /// send it as `max_completion_tokens` instead.
     #[rq(on(TextCompletion, ChatCompletion))]
     max_tokens: Option<u64>,
+    /// Optional. Defaults to null.
+    ///
+    /// An upper bound for the number of tokens that can be generated for a completion, including
+    /// visible output tokens and reasoning tokens.
+    #[rq(on(ChatCompletion))]
+    max_completion_tokens: Option<u64>,
+    /// Optional. Defaults to medium.
+    ///
+    /// Reasoning models only. Constrains effort on reasoning, reducing it can result in faster
+    /// responses and fewer tokens used on reasoning in a response.
+    #[rq(on(ChatCompletion))]
+    reasoning_effort: Option<ReasoningEffort>,
     /// Optional. Defaults to 1.
     ///
     /// What sampling `temperature` to use, between 0 and 2. Higher values like 0.8 will make the
     /// output more random, while lower values like 0.2 will make it more focused and deterministic.
     ///
     /// It's generally recommended to alter this or top_p but not both.
+    ///
+    /// Not supported by reasoning models, chat requests to them leave it out.
     #[rq(on(TextCompletion, ChatCompletion, Edit))]
-    temperature: Option<f64>,
+    temperature: Option<Temperature>,
     /// Optional. Defaults to 1.
     ///
     /// An alternative to sampling with temperature, called nucleus sampling, where the model
@@ -101,8 +171,10 @@ pub struct RequestBody {
     /// tokens comprising the top 10% probability mass are considered.
     ///
     /// It's generally recommended to alter this or temperature but not both.
+    ///
+    /// Not supported by reasoning models, chat requests to them leave it out.
     #[rq(on(TextCompletion, ChatCompletion, Edit))]
-    top_p: Option<f64>,
+    top_p: Option<TopP>,
     /// Optional. Defaults to "".
     ///
     /// The input text to use as a starting point for the edit.
@@ -113,6 +185,23 @@ pub struct RequestBody {
     /// The instruction that tells the model how to edit the prompt.
     #[rq(on(Edit(req)))]
     instruction: String,
+    /// Required.
+    ///
+    /// The text(s) to classify. Sent as `input`, the name being taken by the edits parameter.
+    #[rq(on(Moderation(req)))]
+    moderated_input: ModerationInput,
+    /// Required.
+    ///
+    /// The text(s) to embed, as strings or token arrays. Each input must not exceed the max input
+    /// tokens of the model (8192 tokens), and a request can't embed more than 2048 inputs. Sent
+    /// as `input`, the name being taken by the edits parameter.
+    #[rq(on(Embedding(req)))]
+    embedding_input: EmbeddingInput,
+    /// Optional. Only supported by `text-embedding-3` and later models.
+    ///
+    /// The number of dimensions the resulting embeddings should have.
+    #[rq(on(Embedding))]
+    dimensions: Option<u32>,
     /// Optional. Defaults to 1.
     ///
     /// How many completions to generate for each prompt.
@@ -144,6 +233,18 @@ pub struct RequestBody {
     logprobs: Option<u8>,
     /// Optional. Defaults to false.
     ///
+    /// Whether to return the log probabilities of the output tokens in the `logprobs` of each
+    /// choice. Sent as `logprobs`, the name being taken by the text completions parameter.
+    #[rq(on(ChatCompletion))]
+    include_logprobs: Option<bool>,
+    /// Optional. Defaults to null.
+    ///
+    /// Number between 0 and 20, the number of most likely tokens to return at each token position
+    /// along with their log probability. Implies `include_logprobs`.
+    #[rq(on(ChatCompletion))]
+    top_logprobs: Option<u8>,
+    /// Optional. Defaults to false.
+    ///
     /// Echo back the prompt in addition to the completion.
     #[rq(on(TextCompletion))]
     echo: Option<bool>,
@@ -152,7 +253,7 @@ pub struct RequestBody {
     /// Up to 4 sequences where the API will stop generating further tokens.
     /// The returned text will not contain the `stop` sequence.
     #[rq(on(TextCompletion, ChatCompletion))]
-    stop: Option<Vec<String>>,
+    stop: Option<Stop>,
     /// Optional. Defaults to 0.0.
     ///
     /// Number between -2.0 and 2.0. Positive values penalize new tokens based on whether they
@@ -160,7 +261,7 @@ pub struct RequestBody {
     ///
     /// [See more information about frequency and presence penalties.](https://platform.openai.com/docs/api-reference/parameter-details)
     #[rq(on(TextCompletion, ChatCompletion))]
-    presence_penalty: Option<f64>,
+    presence_penalty: Option<PresencePenalty>,
     /// Optional. Defaults to 0.0.
     ///
     /// Number between -2.0 and 2.0. Positive values penalize new tokens based on their existing
@@ -169,7 +270,7 @@ pub struct RequestBody {
     ///
     /// [See more information about frequency and presence penalties.](https://platform.openai.com/docs/api-reference/parameter-details)
     #[rq(on(TextCompletion, ChatCompletion))]
-    frequency_penalty: Option<f64>,
+    frequency_penalty: Option<FrequencyPenalty>,
     /// Optional. Defaults to 1.
     ///
     /// Generates `best_of` completions server-side and returns the "best"
@@ -197,15 +298,228 @@ pub struct RequestBody {
     ///
     /// As an example, you can pass {"50256": -100} to prevent the <|endoftext|> token from being
     /// generated.
+    ///
+    /// Use [`logit_bias`] to build the map from wider integers, clamping them into range.
     #[rq(on(TextCompletion, ChatCompletion))]
-    logit_bias: Option<BTreeMap<String, i64>>,
+    logit_bias: Option<BTreeMap<u32, i8>>,
     /// Optional
     ///
     /// A unique identifier representing your end-user, which can help OpenAI to monitor and
     /// detect abuse.
     /// [Learn more](https://platform.openai.com/docs/guides/safety-best-practices/end-user-ids).
-    #[rq(on(TextCompletion, ChatCompletion))]
+    #[rq(on(TextCompletion, ChatCompletion, Embedding))]
     user: Option<String>,
+    /// Optional. Defaults to null.
+    ///
+    /// If specified, the system will make a best effort to sample deterministically, such that
+    /// repeated requests with the same `seed` and parameters should return the same result.
+    /// Determinism is not guaranteed, and you should refer to the `system_fingerprint` response
+    /// parameter to monitor changes in the backend.
+    #[rq(on(ChatCompletion))]
+    seed: Option<i64>,
+    /// Optional. Defaults to text.
+    ///
+    /// An object specifying the format that the model must output. Setting it to
+    /// [`ResponseFormat::JsonSchema`] enables Structured Outputs which ensures the model will
+    /// match your supplied JSON schema, [`ResponseFormat::JsonObject`] enables JSON mode.
+    #[rq(on(ChatCompletion))]
+    response_format: Option<ResponseFormat>,
+    /// Optional. Defaults to auto.
+    ///
+    /// Specifies the processing tier to use for serving the request. The tier actually used is
+    /// returned in the response's `service_tier`.
+    #[rq(on(ChatCompletion))]
+    service_tier: Option<ServiceTier>,
+    /// Optional. Defaults to false.
+    ///
+    /// Whether or not to store the output of this chat completion request for use in model
+    /// distillation or evals products, and retrieval via the stored completions endpoints.
+    #[rq(on(ChatCompletion))]
+    store: Option<bool>,
+    /// Optional. Defaults to null.
+    ///
+    /// Up to 16 key-value pairs attached to a stored completion, useful for filtering them later.
+    /// Keys are up to 64 characters long and values up to 512.
+    #[rq(on(ChatCompletion))]
+    metadata: Option<BTreeMap<String, String>>,
+    /// Optional. Defaults to ["text"].
+    ///
+    /// Output types the model should generate. Audio capable models (`gpt-4o-audio-preview`)
+    /// can generate both text and audio, the latter requiring the `audio` parameter.
+    #[rq(on(ChatCompletion))]
+    modalities: Option<Vec<Modality>>,
+    /// Optional. Required when the audio modality is requested.
+    ///
+    /// Voice and format of the generated audio.
+    #[rq(on(ChatCompletion))]
+    audio: Option<AudioOutput>,
+    /// Optional. Only for the search models (`gpt-4o-search-preview`, ...).
+    ///
+    /// Configures the built-in web search. The sources the answer is based on are returned as
+    /// the `annotations` of the message.
+    #[rq(on(ChatCompletion))]
+    web_search_options: Option<WebSearchOptions>,
+    /// Optional. Defaults to null.
+    ///
+    /// A list of tools the model may call. Currently, only functions are supported as a tool.
+    /// Use [`crate::tool::OpenAiTool::tool`] to build an entry from a Rust type.
+    #[rq(on(ChatCompletion))]
+    tools: Option<Vec<Tool>>,
+    /// Optional. Deprecated in favor of `tools`.
+    ///
+    /// A list of functions the model may generate JSON inputs for.
+    #[rq(on(ChatCompletion))]
+    functions: Option<Vec<FunctionDefinition>>,
+    /// Optional. Deprecated in favor of `tool_choice`. Defaults to none when no functions are
+    /// present, auto otherwise.
+    ///
+    /// Controls which (if any) function is called by the model.
+    #[rq(on(ChatCompletion))]
+    function_call: Option<FunctionCallMode>,
+}
+
+/// Smallest bias accepted by the API for a token in `logit_bias`.
+pub const LOGIT_BIAS_MIN: i8 = -100;
+/// Largest bias accepted by the API for a token in `logit_bias`.
+pub const LOGIT_BIAS_MAX: i8 = 100;
+
+/// Clamps a bias into the [`LOGIT_BIAS_MIN`]..=[`LOGIT_BIAS_MAX`] range accepted by the API.
+pub fn clamp_logit_bias(bias: impl Into<i64>) -> i8 {
+    bias.into()
+        .clamp(LOGIT_BIAS_MIN as i64, LOGIT_BIAS_MAX as i64) as i8
+}
+
+/// Adds an issue when a bias of `logit_bias` is out of range.
+pub(crate) fn check_logit_bias(
+    logit_bias: Option<&BTreeMap<u32, i8>>,
+    issues: &mut Vec<error::ValidationIssue>,
+) {
+    let out_of_range = logit_bias
+        .into_iter()
+        .flat_map(BTreeMap::values)
+        .any(|bias| !(LOGIT_BIAS_MIN..=LOGIT_BIAS_MAX).contains(bias));
+
+    if out_of_range {
+        issues.push(error::ValidationIssue::OutOfRange {
+            parameter: "logit_bias",
+            min: LOGIT_BIAS_MIN.into(),
+            max: LOGIT_BIAS_MAX.into(),
+        });
+    }
+}
+
+/// Builds a `logit_bias` map from token ids and biases, clamping every bias into range.
+pub fn logit_bias<B: Into<i64>>(biases: impl IntoIterator<Item = (u32, B)>) -> BTreeMap<u32, i8> {
+    biases
+        .into_iter()
+        .map(|(token, bias)| (token, clamp_logit_bias(bias)))
+        .collect()
+}
+
+/// Requests with a `logit_bias`, biased by words rather than token ids through the tokenizer of
+/// their model, see [`crate::tokens::word_tokens`].
+#[cfg(feature = "tokenizer")]
+pub trait BiasWords: Sized {
+    fn logit_bias_mut(&mut self) -> &mut Option<BTreeMap<u32, i8>>;
+    fn tokenizer_model(&self) -> &ModelId;
+
+    /// Keeps the model from writing `words`, giving their first token the [`LOGIT_BIAS_MIN`]
+    /// bias in `logit_bias`.
+    fn with_banned_words(self, words: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        bias_words(self, words, LOGIT_BIAS_MIN)
+    }
+
+    /// Makes the model more likely to write `words`, giving their first token `bias` (clamped
+    /// into range) in `logit_bias`. Biases from 1 to 5 already make a difference, high ones make
+    /// the model repeat the words.
+    fn with_boosted_words(
+        self,
+        words: impl IntoIterator<Item = impl AsRef<str>>,
+        bias: impl Into<i64>,
+    ) -> Self {
+        bias_words(self, words, clamp_logit_bias(bias))
+    }
+}
+
+/// Sets the bias of the first token of each of `words` in the `logit_bias` of `request`.
+#[cfg(feature = "tokenizer")]
+fn bias_words<R: BiasWords>(
+    mut request: R,
+    words: impl IntoIterator<Item = impl AsRef<str>>,
+    bias: i8,
+) -> R {
+    let tokens = words
+        .into_iter()
+        .flat_map(|word| crate::tokens::word_tokens(word.as_ref(), request.tokenizer_model()))
+        .collect::<Vec<_>>();
+    let logit_bias = request.logit_bias_mut().get_or_insert_with(BTreeMap::new);
+
+    for token in tokens {
+        logit_bias.insert(token, bias);
+    }
+
+    request
+}
+
+/// Up to 4 sequences where the API will stop generating further tokens.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Stop {
+    Single(String),
+    /// Built with [`Stop::multiple`] to check the number of sequences right away. Built directly
+    /// (or deserialized), more than [`Stop::MAX_SEQUENCES`] are reported by [`Request::validate`]
+    /// before the request is sent.
+    Multiple(Vec<String>),
+}
+
+impl Stop {
+    pub const MAX_SEQUENCES: usize = 4;
+
+    pub fn multiple(
+        sequences: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Result<Self, error::ValidationError> {
+        let sequences = sequences
+            .into_iter()
+            .map(|sequence| sequence.as_ref().to_string())
+            .collect::<Vec<_>>();
+
+        if sequences.len() > Self::MAX_SEQUENCES {
+            return Err(error::ValidationIssue::TooManyStopSequences(sequences.len()).into());
+        }
+
+        Ok(Self::Multiple(sequences))
+    }
+}
+
+/// Adds an issue when more than [`Stop::MAX_SEQUENCES`] sequences are set.
+pub(crate) fn check_stop(stop: Option<&Stop>, issues: &mut Vec<error::ValidationIssue>) {
+    if let Some(Stop::Multiple(sequences)) = stop {
+        if sequences.len() > Stop::MAX_SEQUENCES {
+            issues.push(error::ValidationIssue::TooManyStopSequences(
+                sequences.len(),
+            ));
+        }
+    }
+}
+
+impl From<String> for Stop {
+    fn from(value: String) -> Self {
+        Self::Single(value)
+    }
+}
+
+impl From<&str> for Stop {
+    fn from(value: &str) -> Self {
+        Self::Single(value.to_string())
+    }
+}
+
+impl TryFrom<Vec<String>> for Stop {
+    type Error = error::ValidationError;
+
+    fn try_from(value: Vec<String>) -> Result<Self, Self::Error> {
+        Self::multiple(value)
+    }
 }
 
 #[async_trait]
@@ -215,59 +529,684 @@ where
     'client: 'model,
 {
     const URL: &'static str;
-    const COMPATIBLE_MODELS: &'static [&'static str];
+    const COMPATIBLE_MODELS: &'static [ModelId];
+    /// Value of the `OpenAI-Beta` header for endpoints that are still in beta.
+    const BETA: Option<&'static str> = None;
 
-    fn model(&self) -> &'model Model<'client>;
+    fn model(&self) -> &Model<'client>;
     fn model_error() -> error::ModelError;
 
     fn to_json(&self) -> serde_json::Result<serde_json::Value>;
 
+    /// The body sent to the API, serialized. Requests without a transformation of their JSON
+    /// serialize it without building a [`serde_json::Value`] first.
+    fn to_body(&self) -> serde_json::Result<Vec<u8>> {
+        serde_json::to_vec(&self.to_json()?)
+    }
+
+    /// The body sent to the API with the keys of every object sorted, so two equivalent requests
+    /// always serialize the same way.
+    fn to_canonical_json(&self) -> serde_json::Result<serde_json::Value> {
+        Ok(canonicalize(self.to_json()?))
+    }
+
+    /// Tag the usage of the response is accounted under, see
+    /// [`crate::client::Client::usage`].
+    fn usage_tag(&self) -> Option<&str> {
+        None
+    }
+
+    /// Most tokens the response can generate, [None] if uncapped. Used to derive the timeout of
+    /// the request, see [`crate::timeout::TimeoutPolicy`].
+    fn output_tokens(&self) -> Option<u64> {
+        None
+    }
+
+    /// Timeout set on this request, overriding the one of the client.
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Checks the ranges of the fields and the constraints between them that the API would
+    /// otherwise reject with a 400, reporting every issue at once.
+    ///
+    /// Called by the `execute` methods before sending the request.
+    fn validate(&self) -> Result<(), Vec<error::ValidationIssue>> {
+        Ok(())
+    }
+
+    /// Content checked with the moderations endpoint before sending the request, when the client
+    /// is set up to do so with [`crate::client::Client::moderate_inputs`].
+    fn moderation_input(&self) -> Option<ModerationInput> {
+        None
+    }
+
+    fn headers(&self) -> HeaderMap {
+        let mut header_map = self.model().common_headers();
+
+        if let Some(beta) = Self::BETA {
+            header_map.insert("OpenAI-Beta", HeaderValue::from_static(beta));
+        }
+
+        header_map
+    }
+
+    /// A `curl` command sending this request, with the same URL, headers and body as
+    /// [`Request::execute`], for bug reports and debugging. The API key is replaced with
+    /// `$OPENAI_API_KEY`, expanded by the shell.
+    fn to_curl(&self) -> error::Result<String> {
+        let url = self.model().client().resolve_url(Self::URL)?;
+        let body = String::from_utf8_lossy(&self.to_body()?).into_owned();
+        let mut headers = self.headers();
+
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+        let headers = headers.iter().map(|(name, value)| {
+            if name == AUTHORIZATION {
+                format!("-H \"{name}: Bearer $OPENAI_API_KEY\"")
+            } else {
+                format!(
+                    "-H {}",
+                    shell_quote(&format!(
+                        "{name}: {}",
+                        String::from_utf8_lossy(value.as_bytes())
+                    ))
+                )
+            }
+        });
+
+        Ok(std::iter::once(format!("curl {}", shell_quote(&url)))
+            .chain(headers)
+            .chain(std::iter::once(format!("-d {}", shell_quote(&body))))
+            .collect::<Vec<_>>()
+            .join(" \\\n  "))
+    }
+
     #[cfg(feature = "blocking")]
     fn execute_blocking(&self) -> error::Result<Response>
     where
         Self: Sized,
     {
-        if !Self::COMPATIBLE_MODELS.contains(&self.model().id().as_str()) {
+        self.execute_blocking_into()
+    }
+
+    /// (Blocking) Same as [`Request::execute_blocking`], also returning the request id, latency
+    /// and rate limits sent with the response.
+    #[cfg(feature = "blocking")]
+    fn execute_blocking_with_meta(&self) -> error::Result<WithMeta<Response>>
+    where
+        Self: Sized,
+    {
+        self.execute_blocking_into_with_meta()
+    }
+
+    /// (Blocking) Returns the response as untyped JSON, for fields this crate doesn't model yet.
+    #[cfg(feature = "blocking")]
+    fn execute_blocking_raw(&self) -> error::Result<serde_json::Value>
+    where
+        Self: Sized,
+    {
+        self.execute_blocking_into()
+    }
+
+    /// (Blocking) Deserializes the response into a user supplied type instead of `Response`.
+    #[cfg(feature = "blocking")]
+    fn execute_blocking_into<T>(&self) -> error::Result<T>
+    where
+        Self: Sized,
+        T: serde::de::DeserializeOwned,
+    {
+        Ok(self.execute_blocking_into_with_meta()?.response)
+    }
+
+    #[cfg(feature = "blocking")]
+    fn execute_blocking_into_with_meta<T>(&self) -> error::Result<WithMeta<T>>
+    where
+        Self: Sized,
+        T: serde::de::DeserializeOwned,
+    {
+        let client = self.model().client();
+
+        if !client.compatibility().is_compatible(
+            Self::URL,
+            Self::COMPATIBLE_MODELS,
+            self.model().id(),
+        ) {
             return Err(Self::model_error().into());
         }
 
-        let json = self.to_json()?;
-        let res = self
+        client.warn_if_deprecated(self.model().id());
+
+        self.validate().map_err(error::ValidationError::from)?;
+
+        let url = client.resolve_url(Self::URL)?;
+        let key = match client.cache().is_some() || client.deduplicator().is_some() {
+            true => Some(ResponseCache::key(&url, &self.to_json()?)),
+            false => None,
+        };
+        let cache = client.cache().zip(key.as_deref());
+
+        if let Some(cached) = cache.and_then(|(cache, key)| cache.get(key)) {
+            return Ok(WithMeta {
+                response: client.parse(&cached.body)?,
+                meta: cached.meta.into_cached(),
+            });
+        }
+
+        let leader = match client.deduplicator().zip(key.as_deref()) {
+            Some((deduplicator, key)) => match deduplicator.join(key) {
+                Role::Leader(leader) => Some(leader),
+                Role::Follower(call) => match call.wait_blocking() {
+                    Some((body, meta)) => {
+                        return Ok(WithMeta {
+                            response: client.parse(&body)?,
+                            meta,
+                        })
+                    }
+                    None => None,
+                },
+            },
+            None => None,
+        };
+
+        if let Some(input) = self.moderation_input() {
+            moderation::check_blocking(self.model(), input)?;
+        }
+
+        let request_body = self.to_body()?;
+
+        client.check_budget(&request_body, self.model().id(), self.output_tokens())?;
+
+        let timeout = self
+            .timeout()
+            .or_else(|| client.timeout(self.model().id(), self.output_tokens()));
+
+        let start = Instant::now();
+        let mut request = self
             .model()
-            .blocking_client()
-            .post(Self::URL)
-            .headers(self.model().common_headers())
-            .json(&json)
-            .send()?;
+            .blocking_client()?
+            .post(url)
+            .headers(self.headers())
+            .header(CONTENT_TYPE, "application/json")
+            .body(request_body);
+
+        if let Some(timeout) = timeout {
+            request = request.timeout(timeout);
+        }
+
+        let res = client.send_blocking(request)?;
+
+        let headers = res.headers().clone();
+        let body = Arc::<[u8]>::from(res.bytes()?.as_ref());
+        let meta = ResponseMeta::from_headers(&headers, start.elapsed());
+
+        client.record_response(&body, self.usage_tag());
+
+        if let Some(leader) = leader {
+            leader.complete((body.clone(), meta.clone()));
+        }
+
+        let response = client.parse(&body)?;
 
-        Ok(res.json()?)
+        if let Some((cache, key)) = cache {
+            cache.insert(key.to_string(), body, meta.clone());
+        }
+
+        Ok(WithMeta { response, meta })
     }
 
     async fn execute(&self) -> error::Result<Response>
     where
         Self: Sized + Sync,
     {
-        if !Self::COMPATIBLE_MODELS.contains(&self.model().id().as_str()) {
+        self.execute_into().await
+    }
+
+    /// Same as [`Request::execute`], also returning the request id, latency and rate limits sent
+    /// with the response.
+    async fn execute_with_meta(&self) -> error::Result<WithMeta<Response>>
+    where
+        Self: Sized + Sync,
+    {
+        self.execute_into_with_meta().await
+    }
+
+    /// Returns the response as untyped JSON, for fields this crate doesn't model yet.
+    async fn execute_raw(&self) -> error::Result<serde_json::Value>
+    where
+        Self: Sized + Sync,
+    {
+        self.execute_into().await
+    }
+
+    /// Deserializes the response into a user supplied type instead of `Response`.
+    async fn execute_into<T>(&self) -> error::Result<T>
+    where
+        Self: Sized + Sync,
+        T: serde::de::DeserializeOwned,
+    {
+        Ok(self.execute_into_with_meta().await?.response)
+    }
+
+    async fn execute_into_with_meta<T>(&self) -> error::Result<WithMeta<T>>
+    where
+        Self: Sized + Sync,
+        T: serde::de::DeserializeOwned,
+    {
+        let client = self.model().client();
+
+        if !client.compatibility().is_compatible(
+            Self::URL,
+            Self::COMPATIBLE_MODELS,
+            self.model().id(),
+        ) {
             return Err(Self::model_error().into());
         }
 
-        let json = self.to_json()?;
-        let res = self
+        client.warn_if_deprecated(self.model().id());
+
+        self.validate().map_err(error::ValidationError::from)?;
+
+        let url = client.resolve_url(Self::URL)?;
+        let key = match client.cache().is_some() || client.deduplicator().is_some() {
+            true => Some(ResponseCache::key(&url, &self.to_json()?)),
+            false => None,
+        };
+        let cache = client.cache().zip(key.as_deref());
+
+        if let Some(cached) = cache.and_then(|(cache, key)| cache.get(key)) {
+            return Ok(WithMeta {
+                response: client.parse(&cached.body)?,
+                meta: cached.meta.into_cached(),
+            });
+        }
+
+        let leader = match client.deduplicator().zip(key.as_deref()) {
+            Some((deduplicator, key)) => match deduplicator.join(key) {
+                Role::Leader(leader) => Some(leader),
+                Role::Follower(call) => match call.wait().await {
+                    Some((body, meta)) => {
+                        return Ok(WithMeta {
+                            response: client.parse(&body)?,
+                            meta,
+                        })
+                    }
+                    None => None,
+                },
+            },
+            None => None,
+        };
+
+        if let Some(input) = self.moderation_input() {
+            moderation::check(self.model(), input).await?;
+        }
+
+        let request_body = self.to_body()?;
+
+        client.check_budget(&request_body, self.model().id(), self.output_tokens())?;
+
+        let timeout = self
+            .timeout()
+            .or_else(|| client.timeout(self.model().id(), self.output_tokens()));
+
+        let start = Instant::now();
+        let mut request = self
             .model()
             .async_client()
-            .post(Self::URL)
-            .headers(self.model().common_headers())
-            .json(&json)
-            .send()
-            .await?;
+            .post(url)
+            .headers(self.headers())
+            .header(CONTENT_TYPE, "application/json")
+            .body(request_body);
+
+        if let Some(timeout) = timeout {
+            request = request.timeout(timeout);
+        }
+
+        let res = client.send(request).await?;
+
+        let headers = res.headers().clone();
+        let body = Arc::<[u8]>::from(res.bytes().await?.as_ref());
+        let meta = ResponseMeta::from_headers(&headers, start.elapsed());
+
+        client.record_response(&body, self.usage_tag());
+
+        if let Some(leader) = leader {
+            leader.complete((body.clone(), meta.clone()));
+        }
+
+        let response = client.parse(&body)?;
+
+        if let Some((cache, key)) = cache {
+            cache.insert(key.to_string(), body, meta.clone());
+        }
+
+        Ok(WithMeta { response, meta })
+    }
+}
+
+/// `value` in single quotes for a POSIX shell.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Sorts the keys of every object in `value`, whatever the order `serde_json` keeps them in.
+///
+/// `serde_json` already sorts them by default, but its `preserve_order` feature switches its maps
+/// to insertion order. Features are unified across the dependency graph, so any other crate of the
+/// application enabling it would change the JSON of the requests, and with it the cache keys and
+/// the snapshots, without this.
+pub fn canonicalize(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(object) => {
+            let mut entries = object.into_iter().collect::<Vec<_>>();
+
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
 
-        Ok(res.json().await?)
+            serde_json::Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, canonicalize(value)))
+                    .collect(),
+            )
+        }
+        serde_json::Value::Array(values) => {
+            serde_json::Value::Array(values.into_iter().map(canonicalize).collect())
+        }
+        value => value,
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Usage {
+    /// Missing (0) for the endpoints that don't generate anything, like embeddings.
+    #[serde(default)]
     pub completion_tokens: u64,
     pub prompt_tokens: u64,
     pub total_tokens: u64,
 }
+
+/// `12 prompt + 9 completion tokens`, only the prompt tokens when nothing was generated.
+impl Display for Usage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.completion_tokens {
+            0 => write!(f, "{} prompt tokens", self.prompt_tokens),
+            completion_tokens => write!(
+                f,
+                "{} prompt + {completion_tokens} completion tokens",
+                self.prompt_tokens
+            ),
+        }
+    }
+}
+
+impl Add for Usage {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            completion_tokens: self.completion_tokens + rhs.completion_tokens,
+            prompt_tokens: self.prompt_tokens + rhs.prompt_tokens,
+            total_tokens: self.total_tokens + rhs.total_tokens,
+        }
+    }
+}
+
+impl AddAssign for Usage {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+/// The `object` field of API responses, telling which kind of object was returned.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum ObjectType {
+    TextCompletion,
+    ChatCompletion,
+    ChatCompletionChunk,
+    Edit,
+    Model,
+    ModelPermission,
+    List,
+    Embedding,
+    /// Any object type not known to this crate yet.
+    Other(String),
+}
+
+impl ObjectType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::TextCompletion => "text_completion",
+            Self::ChatCompletion => "chat.completion",
+            Self::ChatCompletionChunk => "chat.completion.chunk",
+            Self::Edit => "edit",
+            Self::Model => "model",
+            Self::ModelPermission => "model_permission",
+            Self::List => "list",
+            Self::Embedding => "embedding",
+            Self::Other(other) => other,
+        }
+    }
+}
+
+impl From<String> for ObjectType {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "text_completion" => Self::TextCompletion,
+            "chat.completion" => Self::ChatCompletion,
+            "chat.completion.chunk" => Self::ChatCompletionChunk,
+            "edit" => Self::Edit,
+            "model" => Self::Model,
+            "model_permission" => Self::ModelPermission,
+            "list" => Self::List,
+            "embedding" => Self::Embedding,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+impl From<ObjectType> for String {
+    fn from(value: ObjectType) -> Self {
+        match value {
+            ObjectType::Other(other) => other,
+            known => known.as_str().to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::Client;
+
+    #[test]
+    fn stop_multiple_checks_the_number_of_sequences() {
+        assert!(matches!(
+            Stop::multiple(["a", "b", "c", "d"]),
+            Ok(Stop::Multiple(sequences)) if sequences.len() == Stop::MAX_SEQUENCES
+        ));
+        assert!(matches!(
+            Stop::try_from(vec!["a".to_string(); 5]),
+            Err(error::ValidationError::Issues(issues))
+                if issues == [error::ValidationIssue::TooManyStopSequences(5)]
+        ));
+    }
+
+    #[test]
+    fn validate_reports_stop_built_directly() {
+        let client = Client::new("");
+        let request = ChatCompletionRequest::for_model(&client, ModelId::Gpt4o, vec![])
+            .with_stop(Stop::Multiple(vec!["stop".to_string(); 5]));
+
+        assert!(matches!(
+            request.validate().unwrap_err().as_slice(),
+            [error::ValidationIssue::TooManyStopSequences(5)]
+        ));
+    }
+
+    #[test]
+    fn validate_reports_every_chat_issue() {
+        let client = Client::new("");
+        let request = ChatCompletionRequest::for_model(&client, ModelId::Gpt4o, vec![])
+            .with_top_logprobs(21)
+            .with_max_tokens(16)
+            .with_max_completion_tokens(16)
+            .with_logit_bias([(1, 101)].into())
+            .with_stop(Stop::Multiple(vec!["stop".to_string(); 5]));
+
+        assert_eq!(
+            request.validate().unwrap_err(),
+            [
+                error::ValidationIssue::OutOfRange {
+                    parameter: "top_logprobs",
+                    min: 0.0,
+                    max: 20.0,
+                },
+                error::ValidationIssue::MutuallyExclusive {
+                    parameter: "max_tokens",
+                    other: "max_completion_tokens",
+                },
+                error::ValidationIssue::TooManyStopSequences(5),
+                error::ValidationIssue::OutOfRange {
+                    parameter: "logit_bias",
+                    min: -100.0,
+                    max: 100.0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_reports_every_text_completion_issue() {
+        let client = Client::new("");
+        let request = TextCompletionRequest::for_model(&client, ModelId::TextDavinci003)
+            .with_n(NonZeroU64::new(2).unwrap())
+            .with_best_of(1)
+            .with_stream(true)
+            .with_logprobs(6);
+
+        assert_eq!(
+            request.validate().unwrap_err(),
+            [
+                error::ValidationIssue::BestOfLessThanN { best_of: 1, n: 2 },
+                error::ValidationIssue::BestOfWithStream,
+                error::ValidationIssue::OutOfRange {
+                    parameter: "logprobs",
+                    min: 0.0,
+                    max: 5.0,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn execute_fails_with_every_issue_before_sending() {
+        let server = wiremock::MockServer::start().await;
+        let client = crate::test_util::client(&server);
+        let result = ChatCompletionRequest::for_model(&client, ModelId::Gpt4o, vec![])
+            .with_max_tokens(16)
+            .with_max_completion_tokens(16)
+            .with_top_logprobs(21)
+            .execute()
+            .await;
+        let Err(error::Error::ValidationError(e)) = result else {
+            panic!("expected a validation error, got {result:?}");
+        };
+
+        assert!(matches!(
+            e.downcast_ref::<error::ValidationError>(),
+            Some(error::ValidationError::Issues(issues)) if issues.len() == 2
+        ));
+        assert_eq!(
+            e.to_string(),
+            "\"top_logprobs\" must be between 0 and 20; \"max_tokens\" and \"max_completion_tokens\" \
+            can't be set together"
+        );
+        assert!(server.received_requests().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn canonicalize_sorts_nested_keys() {
+        let mut inner = serde_json::Map::new();
+        inner.insert("b".to_string(), 1.into());
+        inner.insert("a".to_string(), serde_json::Value::Null);
+
+        let json = serde_json::json!({ "z": [inner], "y": {} });
+
+        assert_eq!(
+            canonicalize(json).to_string(),
+            r#"{"y":{},"z":[{"a":null,"b":1}]}"#
+        );
+    }
+
+    #[cfg(feature = "tokenizer")]
+    #[test]
+    fn bias_words_sets_their_first_tokens() {
+        let client = Client::new("");
+        let request = ChatCompletionRequest::for_model(&client, ModelId::Gpt4o, vec![])
+            .with_logit_bias(logit_bias([(1, 1)]))
+            .with_banned_words(["crab"])
+            .with_boosted_words(["lobster"], 1_000);
+        let logit_bias = request.logit_bias().as_ref().unwrap();
+        let banned = crate::tokens::word_tokens("crab", &ModelId::Gpt4o);
+        let boosted = crate::tokens::word_tokens("lobster", &ModelId::Gpt4o);
+
+        assert_eq!(logit_bias.len(), 1 + banned.len() + boosted.len());
+        assert_eq!(logit_bias[&1], 1);
+        assert!(banned
+            .iter()
+            .all(|token| logit_bias[token] == LOGIT_BIAS_MIN));
+        assert!(boosted
+            .iter()
+            .all(|token| logit_bias[token] == LOGIT_BIAS_MAX));
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct AssistantResponse {
+        id: String,
+    }
+
+    #[rq(
+        crate_path("openai_api_rs"),
+        Assistant(
+            doc("Creates an assistant."),
+            url("https://api.openai.com/v1/assistants"),
+            beta("assistants=v2"),
+            compatible_models(Gpt4o)
+        )
+    )]
+    struct AssistantBody {
+        /// Required.
+        #[rq(on(Assistant(req)))]
+        instructions: String,
+    }
+
+    #[tokio::test]
+    async fn beta_endpoints_are_sent_the_beta_header() {
+        use crate::test_util::{
+            self, json_response,
+            wiremock::{
+                matchers::{header, method, path},
+                Mock, MockServer,
+            },
+        };
+
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/assistants"))
+            .and(header("OpenAI-Beta", "assistants=v2"))
+            .respond_with(json_response(serde_json::json!({ "id": "asst_test" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = test_util::client(&server);
+        let request =
+            AssistantRequest::for_model(&client, ModelId::Gpt4o, "Be helpful.".to_string());
+        let chat = ChatCompletionRequest::for_model(&client, ModelId::Gpt4o, vec![]);
+
+        assert_eq!(request.execute().await.unwrap().id, "asst_test");
+        assert!(!chat.headers().contains_key("OpenAI-Beta"));
+    }
+}
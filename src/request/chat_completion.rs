@@ -1,34 +1,1672 @@
-use crate::request::Usage;
-use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Formatter},
+    path::Path,
+};
+
+use base64::Engine;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    error,
+    model::ModelId,
+    pricing::PricingTable,
+    request::{moderation::ModerationInput, ChatCompletionRequest, ObjectType, Request, Usage},
+    tool::ToolKind,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
     pub role: ChatRole,
-    pub content: String,
+    /// Empty when an assistant message only calls a function.
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub content: MessageContent,
+    /// Name of the author of this message, required for `function` messages (the function name)
+    /// and useful to tell apart several users in the same conversation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Legacy function call made by an assistant message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function_call: Option<FunctionCall>,
+    /// Tool calls made by an assistant message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCall>>,
+    /// Id of the tool call a `tool` message answers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    /// Audio generated for an assistant message when the `audio` modality is requested. Only its
+    /// id is sent back when the message is part of a later request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio: Option<MessageAudio>,
+    /// Explanation of an assistant refusing to answer, in which case `content` is empty.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refusal: Option<String>,
+    /// Citations of the web pages the content is based on, when web search was used. Not sent
+    /// back with the message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Vec<Annotation>>,
+}
+
+impl ChatMessage {
+    pub fn new(role: ChatRole, content: impl Into<MessageContent>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+            name: None,
+            function_call: None,
+            tool_calls: None,
+            tool_call_id: None,
+            audio: None,
+            refusal: None,
+            annotations: None,
+        }
+    }
+
+    pub fn system(content: impl Into<MessageContent>) -> Self {
+        Self::new(ChatRole::System, content)
+    }
+
+    /// Instructions for the model, sent as a `system` message to the models that predate the
+    /// `developer` role.
+    pub fn developer(content: impl Into<MessageContent>) -> Self {
+        Self::new(ChatRole::Developer, content)
+    }
+
+    pub fn user(content: impl Into<MessageContent>) -> Self {
+        Self::new(ChatRole::User, content)
+    }
+
+    pub fn assistant(content: impl Into<MessageContent>) -> Self {
+        Self::new(ChatRole::Assistant, content)
+    }
+
+    /// Result of the tool call with the given id.
+    pub fn tool(tool_call_id: impl AsRef<str>, content: impl Into<MessageContent>) -> Self {
+        Self {
+            tool_call_id: Some(tool_call_id.as_ref().to_string()),
+            ..Self::new(ChatRole::Tool, content)
+        }
+    }
+
+    /// The web pages cited by the content.
+    pub fn url_citations(&self) -> impl Iterator<Item = &UrlCitation> {
+        self.annotations
+            .iter()
+            .flatten()
+            .filter_map(|annotation| match annotation {
+                Annotation::UrlCitation { url_citation } => Some(url_citation),
+                Annotation::Other(_) => None,
+            })
+    }
+
+    /// Whether the model refused to answer instead of generating content.
+    pub fn is_refusal(&self) -> bool {
+        self.refusal.is_some()
+    }
+}
+
+/// Builds a `Vec<ChatMessage>`, each message written `role: content`, the role being one of
+/// `system`, `developer`, `user` or `assistant`.
+///
+/// The content is either a format string followed by its arguments or any expression converting
+/// into a [`MessageContent`]. A string literal without arguments is taken as it is, so braces in
+/// JSON examples are left alone. In a format string they are escaped by doubling them (`{{`).
+///
+/// ```
+/// # use openai_api_rs::chat;
+/// let name = "Ferris";
+/// let messages = chat![
+///     system: r#"Answer with JSON like {"greeting": "..."}."#,
+///     user: "Hello, my name is {}! {{Be nice}}", name,
+/// ];
+///
+/// assert_eq!(messages[0].content.text(), r#"Answer with JSON like {"greeting": "..."}."#);
+/// assert_eq!(messages[1].content.text(), "Hello, my name is Ferris! {Be nice}");
+/// ```
+#[macro_export]
+macro_rules! chat {
+    () => {
+        Vec::<$crate::request::chat_completion::ChatMessage>::new()
+    };
+    ($($tokens:tt)+) => {{
+        let mut messages = Vec::<$crate::request::chat_completion::ChatMessage>::new();
+        $crate::__chat_messages!(messages; []; $($tokens)+);
+        messages
+    }};
+}
+
+/// Splits the tokens of [`chat!`] at each `, role:`, collecting the current message's tokens in
+/// the brackets.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __chat_messages {
+    ($messages:ident; []; ) => {};
+    ($messages:ident; [$role:ident $($content:tt)*]; ) => {
+        $messages.push($crate::__chat_message!($role; $($content)*));
+    };
+    ($messages:ident; []; $role:ident : $($rest:tt)*) => {
+        $crate::__chat_messages!($messages; [$role]; $($rest)*);
+    };
+    ($messages:ident; [$current:ident $($content:tt)*]; , $role:ident : $($rest:tt)*) => {
+        $messages.push($crate::__chat_message!($current; $($content)*));
+        $crate::__chat_messages!($messages; [$role]; $($rest)*);
+    };
+    ($messages:ident; [$current:ident $($content:tt)*]; $token:tt $($rest:tt)*) => {
+        $crate::__chat_messages!($messages; [$current $($content)* $token]; $($rest)*);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __chat_message {
+    ($role:ident; $format:literal $(, $arg:expr)+ $(,)?) => {
+        $crate::request::chat_completion::ChatMessage::$role(format!($format $(, $arg)*))
+    };
+    ($role:ident; $content:expr $(,)?) => {
+        $crate::request::chat_completion::ChatMessage::$role($content)
+    };
+}
+
+/// Content of a message, plain text or a list of parts mixing text, images and audio. Used as is
+/// for both the messages sent and the ones received.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+impl MessageContent {
+    /// The text of the message, the text parts joined by newlines.
+    pub fn text(&self) -> String {
+        match self {
+            Self::Text(text) => text.clone(),
+            Self::Parts(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    ContentPart::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
+    /// The content as a list of parts, plain text being a single text part.
+    pub fn into_parts(self) -> Vec<ContentPart> {
+        match self {
+            Self::Text(text) => vec![ContentPart::Text { text }],
+            Self::Parts(parts) => parts,
+        }
+    }
+
+    /// Whether there is no text and no parts, as in assistant messages only calling tools.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Text(text) => text.is_empty(),
+            Self::Parts(parts) => parts.is_empty(),
+        }
+    }
+}
+
+impl Default for MessageContent {
+    fn default() -> Self {
+        Self::Text(String::new())
+    }
+}
+
+impl From<String> for MessageContent {
+    fn from(value: String) -> Self {
+        Self::Text(value)
+    }
+}
+
+impl From<&str> for MessageContent {
+    fn from(value: &str) -> Self {
+        Self::Text(value.to_string())
+    }
+}
+
+impl From<ContentPart> for MessageContent {
+    fn from(value: ContentPart) -> Self {
+        Self::Parts(vec![value])
+    }
+}
+
+impl From<Vec<ContentPart>> for MessageContent {
+    fn from(value: Vec<ContentPart>) -> Self {
+        Self::Parts(value)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text {
+        text: String,
+    },
+    ImageUrl {
+        image_url: ImageUrl,
+    },
+    InputAudio {
+        input_audio: InputAudio,
+    },
+    /// Refusal of an assistant message sent back in a conversation.
+    Refusal {
+        refusal: String,
+    },
+}
+
+impl ContentPart {
+    pub fn text(text: impl AsRef<str>) -> Self {
+        Self::Text {
+            text: text.as_ref().to_string(),
+        }
+    }
+
+    /// An image, either a URL or a base64 encoded `data:` URL.
+    pub fn image_url(url: impl AsRef<str>, detail: Option<ImageDetail>) -> Self {
+        Self::ImageUrl {
+            image_url: ImageUrl {
+                url: url.as_ref().to_string(),
+                detail,
+            },
+        }
+    }
+
+    /// An image read from a local file, sent inline as a base64 encoded `data:` URL.
+    pub fn image_file(path: impl AsRef<Path>, detail: Option<ImageDetail>) -> error::Result<Self> {
+        let bytes = std::fs::read(path)?;
+
+        Self::image_bytes(&bytes, detail)
+    }
+
+    /// An image from its encoded bytes (PNG, JPEG, GIF or WEBP), sent inline as a base64 encoded
+    /// `data:` URL.
+    pub fn image_bytes(bytes: &[u8], detail: Option<ImageDetail>) -> error::Result<Self> {
+        let mime_type =
+            image_mime_type(bytes).ok_or(error::ValidationError::UnsupportedImageType)?;
+        let data = base64::engine::general_purpose::STANDARD.encode(bytes);
+
+        Ok(Self::image_url(
+            format!("data:{mime_type};base64,{data}"),
+            detail,
+        ))
+    }
+
+    /// Audio for audio capable models (`gpt-4o-audio-preview`), from its encoded bytes.
+    pub fn input_audio(bytes: &[u8], format: AudioFormat) -> Self {
+        Self::InputAudio {
+            input_audio: InputAudio {
+                data: base64::engine::general_purpose::STANDARD.encode(bytes),
+                format,
+            },
+        }
+    }
+
+    /// Audio read from a local file, its format inferred from the file extension.
+    pub fn input_audio_file(path: impl AsRef<Path>) -> error::Result<Self> {
+        let path = path.as_ref();
+        let format = match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("wav") => AudioFormat::Wav,
+            Some(ext) if ext.eq_ignore_ascii_case("mp3") => AudioFormat::Mp3,
+            _ => return Err(error::ValidationError::UnsupportedAudioType.into()),
+        };
+        let bytes = std::fs::read(path)?;
+
+        Ok(Self::input_audio(&bytes, format))
+    }
+}
+
+/// MIME type of the images supported by vision models, sniffed from their first bytes.
+fn image_mime_type(bytes: &[u8]) -> Option<&'static str> {
+    match bytes {
+        [0x89, b'P', b'N', b'G', ..] => Some("image/png"),
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [b'G', b'I', b'F', b'8', ..] => Some("image/gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageUrl {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<ImageDetail>,
+}
+
+/// How much detail the model looks at the image with, low detail being faster and cheaper.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageDetail {
+    Auto,
+    Low,
+    High,
+}
+
+/// Base64 encoded audio sent in a message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputAudio {
+    pub data: String,
+    pub format: AudioFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioFormat {
+    Wav,
+    Mp3,
+}
+
+/// Configuration of the built-in web search of the search models.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WebSearchOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search_context_size: Option<SearchContextSize>,
+    /// Approximate location of the user, to refine the search results.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_location: Option<UserLocation>,
+}
+
+/// How much context is retrieved from the web, more being slower and more expensive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchContextSize {
+    Low,
+    Medium,
+    High,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum UserLocation {
+    Approximate { approximate: ApproximateLocation },
+}
+
+impl UserLocation {
+    pub fn approximate(location: ApproximateLocation) -> Self {
+        Self::Approximate {
+            approximate: location,
+        }
+    }
+}
+
+/// Every field is optional, the more are set the better the results are localized.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApproximateLocation {
+    /// Two-letter ISO country code, like `US`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+    /// IANA timezone, like `America/Chicago`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
+/// Annotation of an assistant message's content.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Annotation {
+    UrlCitation {
+        url_citation: UrlCitation,
+    },
+    /// Any annotation type not known to this crate yet, as sent by the API.
+    #[serde(untagged)]
+    Other(serde_json::Value),
+}
+
+/// A web page cited by the part of the content between `start_index` and `end_index`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UrlCitation {
+    pub start_index: usize,
+    pub end_index: usize,
+    pub url: String,
+    pub title: String,
+}
+
+/// Output types the model generates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Modality {
+    Text,
+    /// Requires the `audio` parameter to be set.
+    Audio,
+}
+
+/// Parameters of the audio output, required when the `audio` modality is requested.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioOutput {
+    pub voice: Voice,
+    pub format: AudioOutputFormat,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum Voice {
+    Alloy,
+    Ash,
+    Ballad,
+    Coral,
+    Echo,
+    Sage,
+    Shimmer,
+    Verse,
+    /// Any voice not known to this crate yet.
+    Other(String),
+}
+
+impl Voice {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Alloy => "alloy",
+            Self::Ash => "ash",
+            Self::Ballad => "ballad",
+            Self::Coral => "coral",
+            Self::Echo => "echo",
+            Self::Sage => "sage",
+            Self::Shimmer => "shimmer",
+            Self::Verse => "verse",
+            Self::Other(other) => other,
+        }
+    }
+}
+
+impl From<String> for Voice {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "alloy" => Self::Alloy,
+            "ash" => Self::Ash,
+            "ballad" => Self::Ballad,
+            "coral" => Self::Coral,
+            "echo" => Self::Echo,
+            "sage" => Self::Sage,
+            "shimmer" => Self::Shimmer,
+            "verse" => Self::Verse,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+impl From<Voice> for String {
+    fn from(value: Voice) -> Self {
+        match value {
+            Voice::Other(other) => other,
+            known => known.as_str().to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioOutputFormat {
+    Wav,
+    Mp3,
+    Flac,
+    Opus,
+    Pcm16,
+}
+
+/// Audio response of the model.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageAudio {
+    /// Refers to this audio in follow-up requests, instead of sending it back.
+    pub id: String,
+    /// Base64 encoded audio, in the requested format.
+    #[serde(default)]
+    pub data: String,
+    /// Unix timestamp after which the audio can't be referred to by its id anymore.
+    #[serde(default)]
+    pub expires_at: u64,
+    /// Transcript of the generated audio.
+    #[serde(default)]
+    pub transcript: String,
+}
+
+impl MessageAudio {
+    /// Decodes the base64 `data` into the audio bytes.
+    pub fn decode(&self) -> error::Result<Vec<u8>> {
+        base64::engine::general_purpose::STANDARD
+            .decode(&self.data)
+            .map_err(|e| error::ParseError::InvalidBase64(e).into())
+    }
+}
+
+/// A call to one of the request's `tools`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: ToolKind,
+    pub function: FunctionCall,
+}
+
+/// A call to one of the request's legacy `functions`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionCall {
+    pub name: String,
+    /// JSON encoded arguments, which the model doesn't always generate validly.
+    pub arguments: String,
+}
+
+impl FunctionCall {
+    /// Parses the arguments, for example into an [`crate::tool::OpenAiTool`] type.
+    pub fn parse_arguments<T: DeserializeOwned>(&self) -> error::Result<T> {
+        Ok(serde_json::from_str(&self.arguments)?)
+    }
+}
+
+/// Controls which (if any) of the legacy `functions` the model calls.
+#[derive(Debug, Clone)]
+pub enum FunctionCallMode {
+    /// The model doesn't call a function and generates a message instead.
+    None,
+    /// The model picks between generating a message and calling a function.
+    Auto,
+    /// The model has to call the function with this name.
+    Function(String),
+}
+
+impl Serialize for FunctionCallMode {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::None => serializer.serialize_str("none"),
+            Self::Auto => serializer.serialize_str("auto"),
+            Self::Function(name) => serde_json::json!({ "name": name }).serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for FunctionCallMode {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Mode(String),
+            Function { name: String },
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Mode(mode) => match mode.as_str() {
+                "none" => Ok(Self::None),
+                "auto" => Ok(Self::Auto),
+                _ => Err(serde::de::Error::unknown_variant(&mode, &["none", "auto"])),
+            },
+            Repr::Function { name } => Ok(Self::Function(name)),
+        }
+    }
+}
+
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
 pub enum ChatRole {
-    #[serde(rename = "user")]
     User,
-    #[serde(rename = "system")]
     System,
-    #[serde(rename = "assistant")]
     Assistant,
+    /// Result of a tool call, answering an assistant message's `tool_calls`.
+    Tool,
+    /// Result of a legacy function call.
+    Function,
+    /// Replaces `system` for the newer (reasoning) models, see [`ChatRole::instructions_for`].
+    Developer,
+    /// Any role not known to this crate yet.
+    Other(String),
 }
 
-#[derive(Debug, Deserialize)]
+impl ChatRole {
+    /// Role of the instructions messages for the given model: `developer` for the reasoning
+    /// models, `system` for the others. Requests convert between the two automatically.
+    pub fn instructions_for(model_id: &ModelId) -> Self {
+        if model_id.is_reasoning() {
+            Self::Developer
+        } else {
+            Self::System
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::User => "user",
+            Self::System => "system",
+            Self::Assistant => "assistant",
+            Self::Tool => "tool",
+            Self::Function => "function",
+            Self::Developer => "developer",
+            Self::Other(other) => other,
+        }
+    }
+}
+
+impl From<String> for ChatRole {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "user" => Self::User,
+            "system" => Self::System,
+            "assistant" => Self::Assistant,
+            "tool" => Self::Tool,
+            "function" => Self::Function,
+            "developer" => Self::Developer,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+impl From<ChatRole> for String {
+    fn from(value: ChatRole) -> Self {
+        match value {
+            ChatRole::Other(other) => other,
+            known => known.as_str().to_string(),
+        }
+    }
+}
+
+/// How much effort reasoning models spend on reasoning before answering. Less effort means
+/// faster responses and fewer reasoning tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasoningEffort {
+    Low,
+    Medium,
+    High,
+}
+
+/// Processing tier used to serve the request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum ServiceTier {
+    /// Uses the project's default tier, or scale tier credits when available.
+    Auto,
+    Default,
+    /// Cheaper, slower processing.
+    Flex,
+    /// Any tier not known to this crate yet.
+    Other(String),
+}
+
+impl ServiceTier {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Auto => "auto",
+            Self::Default => "default",
+            Self::Flex => "flex",
+            Self::Other(other) => other,
+        }
+    }
+}
+
+impl From<String> for ServiceTier {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "auto" => Self::Auto,
+            "default" => Self::Default,
+            "flex" => Self::Flex,
+            _ => Self::Other(value),
+        }
+    }
+}
+
+impl From<ServiceTier> for String {
+    fn from(value: ServiceTier) -> Self {
+        match value {
+            ServiceTier::Other(other) => other,
+            known => known.as_str().to_string(),
+        }
+    }
+}
+
+/// Format the model must output.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    Text,
+    /// JSON mode, the model outputs valid JSON. The messages still have to instruct the model to
+    /// produce JSON.
+    JsonObject,
+    /// Structured outputs, the model outputs JSON matching the given schema.
+    JsonSchema {
+        json_schema: JsonSchema,
+    },
+}
+
+impl ResponseFormat {
+    /// Strict structured outputs with the given schema.
+    pub fn json_schema(name: impl AsRef<str>, schema: serde_json::Value) -> Self {
+        Self::JsonSchema {
+            json_schema: JsonSchema {
+                name: name.as_ref().to_string(),
+                description: None,
+                schema,
+                strict: Some(true),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonSchema {
+    /// Name of the response format, a-z, A-Z, 0-9, underscores and dashes, up to 64 characters.
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub schema: serde_json::Value,
+    /// Whether the output must follow the schema exactly. Only a subset of JSON Schema is
+    /// supported in strict mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+/// Serialized as received, the audio data and annotations of its messages included, to be cached,
+/// logged or stored. They are left out when a message is sent back in a request.
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ChatCompletionResponse {
     pub id: String,
-    pub object: String,
+    pub object: ObjectType,
     pub created: u64,
+    pub model: String,
     pub choices: Vec<ChatCompletionChoice>,
     pub usage: Usage,
+    /// Fingerprint of the backend configuration the model ran with. Together with `seed` it can
+    /// be used to detect backend changes that might affect determinism.
+    #[serde(default)]
+    pub system_fingerprint: Option<String>,
+    /// Tier actually used to serve the request.
+    #[serde(default)]
+    pub service_tier: Option<ServiceTier>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ChatCompletionChoice {
     pub index: u64,
     pub message: ChatMessage,
     pub finish_reason: String,
+    /// Present when the request asked for `logprobs`.
+    #[serde(default)]
+    pub logprobs: Option<ChatLogProbs>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatLogProbs {
+    /// Log probabilities of the message content tokens.
+    #[serde(default)]
+    pub content: Option<Vec<TokenLogProb>>,
+    /// Log probabilities of the refusal tokens.
+    #[serde(default)]
+    pub refusal: Option<Vec<TokenLogProb>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenLogProb {
+    pub token: String,
+    pub logprob: f64,
+    /// UTF-8 bytes of the token, useful when a character is split across several tokens.
+    #[serde(default)]
+    pub bytes: Option<Vec<u8>>,
+    /// The `top_logprobs` most likely tokens at this position, empty when not requested.
+    #[serde(default)]
+    pub top_logprobs: Vec<TopLogProb>,
+}
+
+impl TokenLogProb {
+    /// Probability of the token, between 0 and 1.
+    pub fn probability(&self) -> f64 {
+        self.logprob.exp()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopLogProb {
+    pub token: String,
+    pub logprob: f64,
+    #[serde(default)]
+    pub bytes: Option<Vec<u8>>,
+}
+
+impl TopLogProb {
+    /// Probability of the token, between 0 and 1.
+    pub fn probability(&self) -> f64 {
+        self.logprob.exp()
+    }
+}
+
+/// Largest `top_logprobs` accepted by the API.
+pub const TOP_LOGPROBS_MAX: u8 = 20;
+
+/// Rejects out of range `top_logprobs`, `logit_bias` and `stop`, mutually exclusive parameters,
+/// and messages longer than the context of the model.
+pub(crate) fn validate(request: &ChatCompletionRequest) -> Result<(), Vec<error::ValidationIssue>> {
+    let mut issues = Vec::new();
+
+    if request
+        .top_logprobs()
+        .is_some_and(|top_logprobs| top_logprobs > TOP_LOGPROBS_MAX)
+    {
+        issues.push(error::ValidationIssue::OutOfRange {
+            parameter: "top_logprobs",
+            min: 0.0,
+            max: TOP_LOGPROBS_MAX.into(),
+        });
+    }
+
+    if request.max_tokens().is_some() && request.max_completion_tokens().is_some() {
+        issues.push(error::ValidationIssue::MutuallyExclusive {
+            parameter: "max_tokens",
+            other: "max_completion_tokens",
+        });
+    }
+
+    if request.tools().is_some() && request.functions().is_some() {
+        issues.push(error::ValidationIssue::MutuallyExclusive {
+            parameter: "tools",
+            other: "functions",
+        });
+    }
+
+    super::check_stop(request.stop().as_ref(), &mut issues);
+    super::check_logit_bias(request.logit_bias().as_ref(), &mut issues);
+
+    #[cfg(feature = "tokenizer")]
+    {
+        let model = request.model().id();
+        let prompt_tokens = crate::tokens::count_tokens(request.messages(), model) as u64;
+
+        if let Err(issue) =
+            check_context_length(model, prompt_tokens, output_tokens(request).unwrap_or(0))
+        {
+            issues.push(issue);
+        }
+    }
+
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(issues)
+    }
+}
+
+/// Rejects a request whose prompt and completion don't fit in the context of `model`, when it is
+/// known.
+#[cfg(feature = "tokenizer")]
+pub(crate) fn check_context_length(
+    model: &crate::model::ModelId,
+    prompt_tokens: u64,
+    completion_tokens: u64,
+) -> Result<(), error::ValidationIssue> {
+    match model.context_length() {
+        Some(context_length) if prompt_tokens + completion_tokens > context_length => {
+            Err(error::ValidationIssue::ContextLengthExceeded {
+                prompt_tokens,
+                completion_tokens,
+                context_length,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The text of the user messages, checked by the moderation model of the client if it has one.
+pub(crate) fn moderation_input(request: &ChatCompletionRequest) -> Option<ModerationInput> {
+    let texts = request
+        .messages()
+        .iter()
+        .filter(|message| message.role == ChatRole::User)
+        .map(|message| message.content.text())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>();
+
+    (!texts.is_empty()).then(|| texts.into())
+}
+
+/// `max_completion_tokens`, or the legacy `max_tokens`.
+pub(crate) fn output_tokens(request: &ChatCompletionRequest) -> Option<u64> {
+    request.max_completion_tokens().or(*request.max_tokens())
+}
+
+/// A piece of a streamed chat completion.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatCompletionChunk {
+    pub id: String,
+    pub object: ObjectType,
+    pub created: u64,
+    pub model: String,
+    pub choices: Vec<ChatCompletionChunkChoice>,
+}
+
+impl ChatCompletionChunk {
+    /// The content delta of the first choice, empty if there is none.
+    pub fn content(&self) -> &str {
+        self.choices
+            .first()
+            .and_then(|choice| choice.delta.content.as_deref())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatCompletionChunkChoice {
+    pub index: u64,
+    pub delta: ChatDelta,
+    /// Set on the last chunk of the choice.
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+}
+
+/// What a chunk adds to the message, the role only being sent by the first one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChatDelta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<ChatRole>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refusal: Option<String>,
+}
+
+/// Parameters reasoning models reject instead of ignoring.
+const UNSUPPORTED_BY_REASONING: &[&str] = &[
+    "temperature",
+    "top_p",
+    "presence_penalty",
+    "frequency_penalty",
+    "logit_bias",
+];
+
+/// Sends `include_logprobs` under its API name, enabling it when `top_logprobs` is set, and
+/// `system`/`developer` messages with the role the model family expects (see
+/// [`ChatRole::instructions_for`]). Messages of earlier responses are sent back without their
+/// annotations, and with only the id of their audio.
+///
+/// Also adapts the request to the quirks of reasoning models: `max_tokens` is sent as
+/// `max_completion_tokens` and the sampling parameters they reject are dropped.
+pub(crate) fn transform_json(
+    request: &ChatCompletionRequest,
+    json: &mut serde_json::Map<String, serde_json::Value>,
+) {
+    if let Some(include_logprobs) = json.remove("include_logprobs") {
+        json.insert("logprobs".to_string(), include_logprobs);
+    }
+
+    if json.contains_key("top_logprobs") {
+        json.entry("logprobs").or_insert(true.into());
+    }
+
+    let instructions_role = ChatRole::instructions_for(request.model().id());
+
+    if let Some(serde_json::Value::Array(messages)) = json.get_mut("messages") {
+        messages
+            .iter_mut()
+            .filter_map(|message| message.get_mut("role"))
+            .filter(|role| {
+                [ChatRole::System.as_str(), ChatRole::Developer.as_str()]
+                    .contains(&role.as_str().unwrap_or_default())
+            })
+            .for_each(|role| *role = instructions_role.as_str().into());
+
+        for message in messages
+            .iter_mut()
+            .filter_map(serde_json::Value::as_object_mut)
+        {
+            message.remove("annotations");
+
+            if let Some(audio) = message
+                .get_mut("audio")
+                .and_then(serde_json::Value::as_object_mut)
+            {
+                audio.retain(|key, _| key == "id");
+            }
+        }
+    }
+
+    if !request.model().id().is_reasoning() {
+        return;
+    }
+
+    if let Some(max_tokens) = json.remove("max_tokens") {
+        json.entry("max_completion_tokens").or_insert(max_tokens);
+    }
+
+    UNSUPPORTED_BY_REASONING.iter().for_each(|param| {
+        json.remove(*param);
+    });
+}
+
+/// Message sent after an output that failed to parse as JSON, by the `*_with_repair` methods.
+const JSON_REPAIR_PROMPT: &str =
+    "Your previous answer is not valid JSON, the error was: {error}. Answer again with only the \
+    fixed JSON.";
+
+impl<'model, 'client> ChatCompletionRequest<'model, 'client> {
+    /// Parses the first choice as JSON into `T`, for requests in JSON mode or with structured
+    /// outputs. On parse failure the error carries the raw text of the model.
+    pub async fn execute_json<T: DeserializeOwned>(self) -> error::Result<T> {
+        parse_json(self.execute().await?)
+    }
+
+    /// Same as [`Self::execute_json`], but an output that fails to parse is sent back once with a
+    /// message asking the model to fix it. If the second attempt fails too, the error carries
+    /// both attempts.
+    pub async fn execute_json_with_repair<T: DeserializeOwned>(mut self) -> error::Result<T> {
+        let response = self.execute().await?;
+
+        match parse_json_output(response)? {
+            (_, Ok(value)) => Ok(value),
+            (message, Err(first_attempt)) => {
+                self.push_json_repair(message, &first_attempt);
+
+                retry_json(parse_json_output(self.execute().await?)?, first_attempt)
+            }
+        }
+    }
+
+    /// (Blocking) Parses the first choice as JSON into `T`, for requests in JSON mode or with
+    /// structured outputs. On parse failure the error carries the raw text of the model.
+    #[cfg(feature = "blocking")]
+    pub fn execute_json_blocking<T: DeserializeOwned>(self) -> error::Result<T> {
+        parse_json(self.execute_blocking()?)
+    }
+
+    /// (Blocking) Same as [`Self::execute_json_blocking`], but an output that fails to parse is
+    /// sent back once with a message asking the model to fix it. If the second attempt fails too,
+    /// the error carries both attempts.
+    #[cfg(feature = "blocking")]
+    pub fn execute_json_with_repair_blocking<T: DeserializeOwned>(mut self) -> error::Result<T> {
+        let response = self.execute_blocking()?;
+
+        match parse_json_output(response)? {
+            (_, Ok(value)) => Ok(value),
+            (message, Err(first_attempt)) => {
+                self.push_json_repair(message, &first_attempt);
+
+                retry_json(parse_json_output(self.execute_blocking()?)?, first_attempt)
+            }
+        }
+    }
+
+    fn push_json_repair(&mut self, message: ChatMessage, error: &error::StructuredOutputError) {
+        self.messages.push(message);
+        self.messages.push(ChatMessage::new(
+            ChatRole::User,
+            JSON_REPAIR_PROMPT.replace("{error}", &error.source.to_string()),
+        ));
+    }
+}
+
+/// Message sent after an answer cut off by `max_tokens`, by the `*_with_continuation` methods.
+const CONTINUATION_PROMPT: &str =
+    "Continue exactly where you left off, without repeating anything you already wrote.";
+
+impl<'model, 'client> ChatCompletionRequest<'model, 'client> {
+    /// Executes the request, then as long as the answer is cut off by `max_tokens`
+    /// (`finish_reason` is `length`) asks the model to continue it, up to `max_continuations`
+    /// times.
+    ///
+    /// The parts are stitched together in the first choice of the returned response, whose
+    /// `usage` covers every request and `finish_reason` is the one of the last part. Only the
+    /// first choice is continued.
+    pub async fn execute_with_continuation(
+        mut self,
+        max_continuations: usize,
+    ) -> error::Result<ChatCompletionResponse> {
+        let mut response = self.execute().await?;
+        let mut cut_off = response.cut_off_text();
+
+        for _ in 0..max_continuations {
+            let Some(part) = cut_off.take() else {
+                break;
+            };
+
+            self.push_continuation(part);
+
+            let next = self.execute().await?;
+
+            cut_off = next.cut_off_text();
+            response.append_continuation(next);
+        }
+
+        Ok(response)
+    }
+
+    /// (Blocking) Executes the request, then as long as the answer is cut off by `max_tokens`
+    /// asks the model to continue it, up to `max_continuations` times. See
+    /// [`Self::execute_with_continuation`].
+    #[cfg(feature = "blocking")]
+    pub fn execute_with_continuation_blocking(
+        mut self,
+        max_continuations: usize,
+    ) -> error::Result<ChatCompletionResponse> {
+        let mut response = self.execute_blocking()?;
+        let mut cut_off = response.cut_off_text();
+
+        for _ in 0..max_continuations {
+            let Some(part) = cut_off.take() else {
+                break;
+            };
+
+            self.push_continuation(part);
+
+            let next = self.execute_blocking()?;
+
+            cut_off = next.cut_off_text();
+            response.append_continuation(next);
+        }
+
+        Ok(response)
+    }
+
+    /// Adds the latest part of the cut off answer and the continuation prompt. The earlier parts
+    /// are already in the messages.
+    fn push_continuation(&mut self, part: String) {
+        self.messages.push(ChatMessage::assistant(part));
+        self.messages.push(ChatMessage::user(CONTINUATION_PROMPT));
+    }
+}
+
+impl<'model, 'client> ChatCompletionRequest<'model, 'client> {
+    /// Executes the request, then as long as the answer is stopped by the content filter
+    /// (`finish_reason` is `content_filter`) lets `sanitize` change the messages, rephrasing the
+    /// prompt for example, and sends them again, up to `max_retries` times.
+    ///
+    /// `sanitize` gets the messages and the filtered response. If every attempt is filtered, the
+    /// error carries all of them, see [`error::ContentFiltered`].
+    pub async fn execute_with_content_filter_retry(
+        mut self,
+        max_retries: usize,
+        mut sanitize: impl FnMut(&mut Vec<ChatMessage>, &ChatCompletionResponse),
+    ) -> error::Result<ChatCompletionResponse> {
+        let mut attempts = Vec::new();
+
+        loop {
+            let response = self.execute().await?;
+
+            if !response.is_content_filtered() {
+                return Ok(response);
+            }
+
+            if attempts.len() == max_retries {
+                attempts.push(response);
+
+                return Err(error::ContentFiltered { attempts }.into());
+            }
+
+            sanitize(&mut self.messages, &response);
+            attempts.push(response);
+        }
+    }
+
+    /// (Blocking) Same as [`Self::execute_with_content_filter_retry`].
+    #[cfg(feature = "blocking")]
+    pub fn execute_with_content_filter_retry_blocking(
+        mut self,
+        max_retries: usize,
+        mut sanitize: impl FnMut(&mut Vec<ChatMessage>, &ChatCompletionResponse),
+    ) -> error::Result<ChatCompletionResponse> {
+        let mut attempts = Vec::new();
+
+        loop {
+            let response = self.execute_blocking()?;
+
+            if !response.is_content_filtered() {
+                return Ok(response);
+            }
+
+            if attempts.len() == max_retries {
+                attempts.push(response);
+
+                return Err(error::ContentFiltered { attempts }.into());
+            }
+
+            sanitize(&mut self.messages, &response);
+            attempts.push(response);
+        }
+    }
+}
+
+#[cfg(feature = "tokenizer")]
+impl<'model, 'client> super::BiasWords for ChatCompletionRequest<'model, 'client> {
+    fn logit_bias_mut(&mut self) -> &mut Option<std::collections::BTreeMap<u32, i8>> {
+        &mut self.logit_bias
+    }
+
+    fn tokenizer_model(&self) -> &ModelId {
+        self.model.id()
+    }
+}
+
+/// `role: content`, followed by a `role: -> function(arguments)` line per call for the
+/// assistant messages calling tools.
+impl Display for ChatMessage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let role = match &self.name {
+            Some(name) => format!("{} ({name})", self.role.as_str()),
+            None => self.role.as_str().to_string(),
+        };
+        let content = match (&self.refusal, &self.audio) {
+            (Some(refusal), _) => Some(format!("[refused] {refusal}")),
+            (None, Some(audio)) if self.content.is_empty() => {
+                Some(format!("[audio] {}", audio.transcript))
+            }
+            _ => (!self.content.is_empty()).then(|| self.content.text()),
+        };
+        let calls = self
+            .tool_calls
+            .iter()
+            .flatten()
+            .map(|tool_call| &tool_call.function)
+            .chain(&self.function_call)
+            .map(|call| format!("-> {}({})", call.name, call.arguments));
+        let lines = content
+            .into_iter()
+            .chain(calls)
+            .map(|line| format!("{role}: {line}"))
+            .collect::<Vec<_>>();
+
+        if lines.is_empty() {
+            write!(f, "{role}:")
+        } else {
+            write!(f, "{}", lines.join("\n"))
+        }
+    }
+}
+
+/// The message of the first choice, see [`ChatCompletionResponse::into_message`].
+impl TryFrom<ChatCompletionResponse> for ChatMessage {
+    type Error = error::ParseError;
+
+    fn try_from(response: ChatCompletionResponse) -> Result<Self, Self::Error> {
+        response.into_message()
+    }
+}
+
+/// The text of the first message, see [`MessageContent::text`].
+impl TryFrom<ChatCompletionResponse> for String {
+    type Error = error::ParseError;
+
+    fn try_from(response: ChatCompletionResponse) -> Result<Self, Self::Error> {
+        Ok(response.into_message()?.content.text())
+    }
+}
+
+/// The message of each choice, numbered when there are several, followed by the model and the
+/// usage. Choices cut off or filtered tell their `finish_reason`.
+impl Display for ChatCompletionResponse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for choice in &self.choices {
+            if self.choices.len() > 1 {
+                write!(f, "[{}] ", choice.index)?;
+            }
+
+            write!(f, "{}", choice.message)?;
+
+            if !["stop", "tool_calls", "function_call"].contains(&choice.finish_reason.as_str()) {
+                write!(f, " [{}]", choice.finish_reason)?;
+            }
+
+            writeln!(f)?;
+        }
+
+        write!(f, "({}, {})", self.model, self.usage)
+    }
+}
+
+impl ChatCompletionResponse {
+    /// The message of the first choice, the only one unless `n` was set.
+    pub fn first_message(&self) -> Option<&ChatMessage> {
+        self.choices.first().map(|choice| &choice.message)
+    }
+
+    /// The text of the first message, see [`MessageContent::text`].
+    pub fn first_text(&self) -> Option<String> {
+        self.first_message().map(|message| message.content.text())
+    }
+
+    /// The text of the message of each choice.
+    pub fn texts(&self) -> Vec<String> {
+        self.choices
+            .iter()
+            .map(|choice| choice.message.content.text())
+            .collect()
+    }
+
+    /// The message of the first choice, failing if there are no choices.
+    pub fn into_message(self) -> Result<ChatMessage, error::ParseError> {
+        self.choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message)
+            .ok_or_else(|| error::ParseError::FieldNotFound("choices".to_string()))
+    }
+
+    /// Whether the first choice was stopped by the content filter of the API.
+    pub fn is_content_filtered(&self) -> bool {
+        self.choices
+            .first()
+            .is_some_and(|choice| choice.finish_reason == "content_filter")
+    }
+
+    /// Cost of this response in USD with `pricing` (the one of the client is
+    /// [`crate::client::Client::pricing_table`]), [None] if its model has no price.
+    pub fn estimated_cost(&self, pricing: &PricingTable) -> Option<f64> {
+        pricing.cost(&self.model, &self.usage)
+    }
+
+    /// The text of the first choice if it was cut off by `max_tokens`.
+    fn cut_off_text(&self) -> Option<String> {
+        self.choices
+            .first()
+            .filter(|choice| choice.finish_reason == "length")
+            .map(|choice| choice.message.content.text())
+    }
+
+    fn append_continuation(&mut self, continuation: ChatCompletionResponse) {
+        self.usage += continuation.usage;
+
+        if let (Some(choice), Some(next)) = (
+            self.choices.first_mut(),
+            continuation.choices.into_iter().next(),
+        ) {
+            choice.message.content = format!(
+                "{}{}",
+                choice.message.content.text(),
+                next.message.content.text()
+            )
+            .into();
+            choice.finish_reason = next.finish_reason;
+        }
+    }
+}
+
+#[cfg(feature = "schemars")]
+impl<'model, 'client> ChatCompletionRequest<'model, 'client> {
+    /// Asks for a strict JSON Schema output generated from `T` and parses the first choice into
+    /// it. On parse failure the error carries the raw text of the model.
+    pub async fn execute_structured<T>(self) -> error::Result<T>
+    where
+        T: schemars::JsonSchema + DeserializeOwned,
+    {
+        self.with_response_format(structured_response_format::<T>())
+            .execute_json()
+            .await
+    }
+
+    /// Same as [`Self::execute_structured`], retrying once with a repair message when the output
+    /// fails to parse, see [`Self::execute_json_with_repair`].
+    pub async fn execute_structured_with_repair<T>(self) -> error::Result<T>
+    where
+        T: schemars::JsonSchema + DeserializeOwned,
+    {
+        self.with_response_format(structured_response_format::<T>())
+            .execute_json_with_repair()
+            .await
+    }
+
+    /// (Blocking) Asks for a strict JSON Schema output generated from `T` and parses the first
+    /// choice into it. On parse failure the error carries the raw text of the model.
+    #[cfg(feature = "blocking")]
+    pub fn execute_structured_blocking<T>(self) -> error::Result<T>
+    where
+        T: schemars::JsonSchema + DeserializeOwned,
+    {
+        self.with_response_format(structured_response_format::<T>())
+            .execute_json_blocking()
+    }
+
+    /// (Blocking) Same as [`Self::execute_structured_blocking`], retrying once with a repair
+    /// message when the output fails to parse, see [`Self::execute_json_with_repair_blocking`].
+    #[cfg(feature = "blocking")]
+    pub fn execute_structured_with_repair_blocking<T>(self) -> error::Result<T>
+    where
+        T: schemars::JsonSchema + DeserializeOwned,
+    {
+        self.with_response_format(structured_response_format::<T>())
+            .execute_json_with_repair_blocking()
+    }
+}
+
+#[cfg(feature = "schemars")]
+fn structured_response_format<T: schemars::JsonSchema>() -> ResponseFormat {
+    let mut schema =
+        serde_json::to_value(schemars::schema_for!(T)).expect("JSON Schemas always serialize");
+
+    if let Some(schema) = schema.as_object_mut() {
+        schema.remove("$schema");
+        schema.remove("title");
+    }
+
+    strict_schema(&mut schema);
+
+    let name = T::schema_name()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(64)
+        .collect::<String>();
+
+    ResponseFormat::json_schema(name, schema)
+}
+
+/// Strict mode needs every object to list all of its properties as required and to forbid
+/// additional ones. Optional fields stay optional by also accepting null.
+#[cfg(feature = "schemars")]
+fn strict_schema(schema: &mut serde_json::Value) {
+    match schema {
+        serde_json::Value::Object(object) => {
+            if let Some(serde_json::Value::Object(properties)) = object.get("properties") {
+                let required = properties
+                    .keys()
+                    .cloned()
+                    .map(serde_json::Value::String)
+                    .collect();
+
+                object.insert("required".to_string(), serde_json::Value::Array(required));
+                object.insert("additionalProperties".to_string(), false.into());
+            }
+
+            object.values_mut().for_each(strict_schema);
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(strict_schema),
+        _ => {}
+    }
+}
+
+fn parse_json<T: DeserializeOwned>(response: ChatCompletionResponse) -> error::Result<T> {
+    Ok(parse_json_output(response)?.1?)
+}
+
+/// The first choice's message and the result of parsing its content.
+fn parse_json_output<T: DeserializeOwned>(
+    response: ChatCompletionResponse,
+) -> error::Result<(ChatMessage, Result<T, error::StructuredOutputError>)> {
+    let message = response.into_message()?;
+    let raw = message.content.text();
+    let parsed = serde_json::from_str(&raw).map_err(|source| error::StructuredOutputError {
+        raw,
+        source,
+        first_attempt: None,
+    });
+
+    Ok((message, parsed))
+}
+
+fn retry_json<T>(
+    (_, parsed): (ChatMessage, Result<T, error::StructuredOutputError>),
+    first_attempt: error::StructuredOutputError,
+) -> error::Result<T> {
+    parsed.map_err(|error| {
+        error::StructuredOutputError {
+            first_attempt: Some(Box::new(first_attempt)),
+            ..error
+        }
+        .into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::MockServer;
+
+    use super::*;
+    use crate::test_util::{self, chat_completions, fixtures, json_response};
+
+    fn part(content: &str, finish_reason: &str) -> serde_json::Value {
+        let mut body = fixtures::chat_completion(content);
+
+        body["choices"][0]["finish_reason"] = finish_reason.into();
+
+        body
+    }
+
+    #[tokio::test]
+    async fn continuations_send_each_part_once() {
+        let server = MockServer::start().await;
+
+        for (content, finish_reason) in [
+            ("Once", "length"),
+            (" upon", "length"),
+            (" a time", "length"),
+            (".", "stop"),
+        ] {
+            chat_completions()
+                .respond_with(json_response(part(content, finish_reason)))
+                .up_to_n_times(1)
+                .mount(&server)
+                .await;
+        }
+
+        let client = test_util::client(&server);
+        let response = ChatCompletionRequest::for_model(
+            &client,
+            ModelId::Gpt4oMini,
+            vec![ChatMessage::user("Tell me a story")],
+        )
+        .execute_with_continuation(5)
+        .await
+        .unwrap();
+
+        assert_eq!(response.first_text().as_deref(), Some("Once upon a time."));
+        assert_eq!(response.choices[0].finish_reason, "stop");
+
+        let requests = server.received_requests().await.unwrap();
+        let messages = requests
+            .iter()
+            .map(|request| {
+                let body = request.body_json::<serde_json::Value>().unwrap();
+
+                body["messages"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|message| {
+                        format!(
+                            "{}: {}",
+                            message["role"].as_str().unwrap(),
+                            message["content"].as_str().unwrap()
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let prompt = "user: Tell me a story".to_string();
+        let continuation = format!("user: {CONTINUATION_PROMPT}");
+
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0], std::slice::from_ref(&prompt));
+        assert_eq!(
+            messages[3],
+            [
+                prompt,
+                "assistant: Once".to_string(),
+                continuation.clone(),
+                "assistant:  upon".to_string(),
+                continuation.clone(),
+                "assistant:  a time".to_string(),
+                continuation,
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_annotations_are_kept() {
+        let message = serde_json::from_value::<ChatMessage>(serde_json::json!({
+            "role": "assistant",
+            "content": "Rust 1.0 was released in 2015.",
+            "annotations": [
+                {
+                    "type": "url_citation",
+                    "url_citation": {
+                        "start_index": 0,
+                        "end_index": 30,
+                        "url": "https://blog.rust-lang.org/2015/05/15/Rust-1.0.html",
+                        "title": "Announcing Rust 1.0",
+                    },
+                },
+                { "type": "file_citation", "file_id": "file-abc" },
+            ],
+        }))
+        .unwrap();
+
+        assert_eq!(message.url_citations().count(), 1);
+        assert!(matches!(
+            message.annotations.as_deref(),
+            Some([_, Annotation::Other(other)]) if other["file_id"] == "file-abc"
+        ));
+    }
+
+    fn answer() -> serde_json::Value {
+        serde_json::json!({
+            "role": "assistant",
+            "content": "Rust 1.0 was released in 2015.",
+            "audio": {
+                "id": "audio_abc",
+                "data": "UklGRg==",
+                "expires_at": 1_700_000_000,
+                "transcript": "Rust 1.0 was released in 2015.",
+            },
+            "annotations": [{ "type": "file_citation", "file_id": "file-abc" }],
+        })
+    }
+
+    #[test]
+    fn messages_serialize_as_received() {
+        let message = serde_json::from_value::<ChatMessage>(answer()).unwrap();
+
+        assert_eq!(serde_json::to_value(&message).unwrap(), answer());
+    }
+
+    #[test]
+    fn messages_sent_back_only_refer_to_their_audio() {
+        let client = crate::client::Client::new("");
+        let message = serde_json::from_value::<ChatMessage>(answer()).unwrap();
+        let json = ChatCompletionRequest::for_model(&client, ModelId::Gpt4o, vec![message])
+            .to_json()
+            .unwrap();
+
+        assert_eq!(
+            json["messages"][0],
+            serde_json::json!({
+                "role": "assistant",
+                "content": "Rust 1.0 was released in 2015.",
+                "audio": { "id": "audio_abc" },
+            })
+        );
+    }
 }
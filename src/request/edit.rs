@@ -1,17 +1,32 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::request::Usage;
+use crate::request::{ObjectType, Usage};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct EditResponse {
-    pub object: String,
+    pub object: ObjectType,
     pub created: u64,
     pub choices: Vec<EditChoice>,
     pub usage: Usage,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct EditChoice {
     pub text: String,
     pub index: u64,
 }
+
+impl EditResponse {
+    /// The text of the first choice, the only one unless `n` was set.
+    pub fn first_text(&self) -> Option<&str> {
+        self.choices.first().map(|choice| choice.text.as_str())
+    }
+
+    /// The text of each choice.
+    pub fn texts(&self) -> Vec<&str> {
+        self.choices
+            .iter()
+            .map(|choice| choice.text.as_str())
+            .collect()
+    }
+}
@@ -0,0 +1,410 @@
+use std::ops::Deref;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    batch, error,
+    pricing::PricingTable,
+    request::{EmbeddingRequest, ObjectType, Usage},
+    retry::RetryPolicy,
+};
+
+/// Most inputs the API accepts in a single request.
+pub const MAX_INPUTS_PER_REQUEST: usize = 2048;
+
+/// The text(s) to embed, as strings or token arrays.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EmbeddingInput {
+    String(String),
+    Strings(Vec<String>),
+    Tokens(Vec<u32>),
+    TokenArrays(Vec<Vec<u32>>),
+}
+
+impl EmbeddingInput {
+    /// Number of inputs, each getting its own embedding.
+    pub fn len(&self) -> usize {
+        match self {
+            Self::String(_) | Self::Tokens(_) => 1,
+            Self::Strings(strings) => strings.len(),
+            Self::TokenArrays(token_arrays) => token_arrays.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl From<String> for EmbeddingInput {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<&str> for EmbeddingInput {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<Vec<String>> for EmbeddingInput {
+    fn from(value: Vec<String>) -> Self {
+        Self::Strings(value)
+    }
+}
+
+impl From<Vec<&str>> for EmbeddingInput {
+    fn from(value: Vec<&str>) -> Self {
+        Self::Strings(value.into_iter().map(ToString::to_string).collect())
+    }
+}
+
+impl From<Vec<u32>> for EmbeddingInput {
+    fn from(value: Vec<u32>) -> Self {
+        Self::Tokens(value)
+    }
+}
+
+impl From<Vec<Vec<u32>>> for EmbeddingInput {
+    fn from(value: Vec<Vec<u32>>) -> Self {
+        Self::TokenArrays(value)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmbeddingResponse {
+    pub object: ObjectType,
+    pub model: String,
+    /// One embedding per input, in the order of the inputs.
+    pub data: Vec<Embedding>,
+    /// `completion_tokens` is always 0.
+    pub usage: Usage,
+}
+
+impl EmbeddingResponse {
+    /// Cost of this response in USD with `pricing` (the one of the client is
+    /// [`crate::client::Client::pricing_table`]), [None] if its model has no price.
+    pub fn estimated_cost(&self, pricing: &PricingTable) -> Option<f64> {
+        pricing.cost(&self.model, &self.usage)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Embedding {
+    pub object: ObjectType,
+    /// Index of the input this embedding is for.
+    pub index: usize,
+    pub embedding: EmbeddingVector,
+}
+
+/// The vector of an [`Embedding`], with the math comparing embeddings.
+///
+/// The comparisons expect vectors of the same length, the embeddings of a same model with the
+/// same `dimensions`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct EmbeddingVector(pub Vec<f32>);
+
+impl EmbeddingVector {
+    /// Dot product. Vectors of different lengths are truncated to the shorter one, after
+    /// panicking in debug builds.
+    pub fn dot(&self, other: &Self) -> f32 {
+        debug_assert_eq!(self.len(), other.len(), "embeddings of different lengths");
+
+        self.iter().zip(other.iter()).map(|(a, b)| a * b).sum()
+    }
+
+    /// Euclidean length.
+    pub fn norm(&self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    /// Cosine of the angle between the vectors, from -1 (opposite) to 1 (same direction). 0 if
+    /// one of them is all zeros.
+    ///
+    /// The OpenAI embeddings are already normalized, their [`Self::dot`] product is the same.
+    pub fn cosine_similarity(&self, other: &Self) -> f32 {
+        let norms = self.norm() * other.norm();
+
+        if norms == 0.0 {
+            0.0
+        } else {
+            self.dot(other) / norms
+        }
+    }
+
+    /// Scales the vector to a length of 1, left as is if it is all zeros. Needed after
+    /// shortening an embedding by hand instead of with the `dimensions` parameter.
+    pub fn normalize(&mut self) {
+        let norm = self.norm();
+
+        if norm != 0.0 {
+            self.0.iter_mut().for_each(|value| *value /= norm);
+        }
+    }
+
+    /// Same as [`Self::normalize`], by value.
+    pub fn normalized(mut self) -> Self {
+        self.normalize();
+        self
+    }
+
+    #[cfg(feature = "ndarray")]
+    pub fn view(&self) -> ndarray::ArrayView1<'_, f32> {
+        ndarray::ArrayView1::from(self.0.as_slice())
+    }
+}
+
+impl Deref for EmbeddingVector {
+    type Target = [f32];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<Vec<f32>> for EmbeddingVector {
+    fn from(value: Vec<f32>) -> Self {
+        Self(value)
+    }
+}
+
+impl From<EmbeddingVector> for Vec<f32> {
+    fn from(value: EmbeddingVector) -> Self {
+        value.0
+    }
+}
+
+#[cfg(feature = "ndarray")]
+impl From<EmbeddingVector> for ndarray::Array1<f32> {
+    fn from(value: EmbeddingVector) -> Self {
+        value.0.into()
+    }
+}
+
+#[cfg(feature = "ndarray")]
+impl From<ndarray::Array1<f32>> for EmbeddingVector {
+    fn from(value: ndarray::Array1<f32>) -> Self {
+        Self(value.into_iter().collect())
+    }
+}
+
+impl<'model, 'client> EmbeddingRequest<'model, 'client> {
+    /// This request split into requests of at most `batch_size` inputs, in order.
+    pub fn batches(&self, batch_size: usize) -> Vec<Self> {
+        let batch_size = batch_size.max(1);
+
+        match &self.embedding_input {
+            EmbeddingInput::Strings(strings) if strings.len() > batch_size => strings
+                .chunks(batch_size)
+                .map(|chunk| Self {
+                    embedding_input: EmbeddingInput::Strings(chunk.to_vec()),
+                    ..self.clone()
+                })
+                .collect(),
+            EmbeddingInput::TokenArrays(token_arrays) if token_arrays.len() > batch_size => {
+                token_arrays
+                    .chunks(batch_size)
+                    .map(|chunk| Self {
+                        embedding_input: EmbeddingInput::TokenArrays(chunk.to_vec()),
+                        ..self.clone()
+                    })
+                    .collect()
+            }
+            _ => vec![self.clone()],
+        }
+    }
+
+    /// Embeds any number of inputs, splitting them into requests of at most
+    /// [`MAX_INPUTS_PER_REQUEST`] inputs executed with at most `concurrency` in flight (and
+    /// retried with the default [`RetryPolicy`]). The embeddings come back in the order of the
+    /// inputs, with the usage of every request summed.
+    pub async fn execute_batched(&self, concurrency: usize) -> error::Result<EmbeddingResponse> {
+        let batches = self.batches(MAX_INPUTS_PER_REQUEST);
+        let sizes = batches
+            .iter()
+            .map(|batch| batch.embedding_input.len())
+            .collect::<Vec<_>>();
+
+        let responses =
+            batch::execute_many_with_retry(batches, concurrency, RetryPolicy::default())
+                .await
+                .into_iter()
+                .collect::<error::Result<Vec<_>>>()?;
+
+        Ok(merge(responses, &sizes))
+    }
+
+    /// (Blocking) Same as [`Self::execute_batched`], one request after the other.
+    #[cfg(feature = "blocking")]
+    pub fn execute_batched_blocking(&self) -> error::Result<EmbeddingResponse> {
+        let batches = self.batches(MAX_INPUTS_PER_REQUEST);
+        let sizes = batches
+            .iter()
+            .map(|batch| batch.embedding_input.len())
+            .collect::<Vec<_>>();
+
+        let responses = batches
+            .iter()
+            .map(|batch| crate::retry::execute_blocking_with_retry(batch, &RetryPolicy::default()))
+            .collect::<error::Result<Vec<_>>>()?;
+
+        Ok(merge(responses, &sizes))
+    }
+}
+
+/// Concatenates the responses of consecutive batches of `sizes` inputs, shifting the indexes of
+/// their embeddings to the ones of the inputs.
+fn merge(responses: Vec<EmbeddingResponse>, sizes: &[usize]) -> EmbeddingResponse {
+    let offsets = sizes.iter().scan(0, |offset, size| {
+        let start = *offset;
+
+        *offset += size;
+
+        Some(start)
+    });
+
+    responses.into_iter().zip(offsets).fold(
+        EmbeddingResponse {
+            object: ObjectType::List,
+            model: String::new(),
+            data: Vec::new(),
+            usage: Usage::default(),
+        },
+        |mut merged, (response, offset)| {
+            merged.object = response.object;
+            merged.model = response.model;
+            merged.usage += response.usage;
+            merged
+                .data
+                .extend(response.data.into_iter().map(|embedding| Embedding {
+                    index: embedding.index + offset,
+                    ..embedding
+                }));
+
+            merged
+        },
+    )
+}
+
+/// Sends `embedding_input` under its API name.
+pub(crate) fn transform_json(
+    _request: &EmbeddingRequest,
+    json: &mut serde_json::Map<String, serde_json::Value>,
+) {
+    if let Some(input) = json.remove("embedding_input") {
+        json.insert("input".to_string(), input);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        model::ModelId,
+        test_util::{
+            self, fixtures, json_response,
+            wiremock::{MockServer, Request, ResponseTemplate},
+        },
+    };
+
+    /// Embeds each input, a number, as a vector holding it.
+    fn embed(request: &Request) -> ResponseTemplate {
+        let body = request.body_json::<serde_json::Value>().unwrap();
+        let vectors = body["input"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|input| vec![input.as_str().unwrap().parse().unwrap()]);
+
+        json_response(fixtures::embedding(vectors))
+    }
+
+    fn vector(values: &[f32]) -> EmbeddingVector {
+        EmbeddingVector(values.to_vec())
+    }
+
+    #[test]
+    fn dot_products_sum_the_products_of_the_values() {
+        assert_eq!(
+            vector(&[1.0, 2.0, 3.0]).dot(&vector(&[4.0, -5.0, 6.0])),
+            12.0
+        );
+        assert_eq!(vector(&[]).dot(&vector(&[])), 0.0);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "embeddings of different lengths")]
+    fn dot_products_of_different_lengths_panic_in_debug_builds() {
+        vector(&[1.0, 2.0]).dot(&vector(&[1.0]));
+    }
+
+    #[test]
+    fn cosine_similarities_compare_directions() {
+        let a = vector(&[3.0, 4.0]);
+
+        assert!((a.cosine_similarity(&vector(&[6.0, 8.0])) - 1.0).abs() < 1e-6);
+        assert!((a.cosine_similarity(&vector(&[-3.0, -4.0])) + 1.0).abs() < 1e-6);
+        assert_eq!(a.cosine_similarity(&vector(&[-4.0, 3.0])), 0.0);
+        assert_eq!(a.cosine_similarity(&vector(&[0.0, 0.0])), 0.0);
+    }
+
+    #[test]
+    fn normalized_vectors_have_a_length_of_1() {
+        let normalized = vector(&[3.0, 4.0]).normalized();
+
+        assert_eq!(normalized, vector(&[0.6, 0.8]));
+        assert!((normalized.norm() - 1.0).abs() < 1e-6);
+        assert_eq!(vector(&[0.0, 0.0]).normalized(), vector(&[0.0, 0.0]));
+    }
+
+    #[test]
+    #[cfg(feature = "ndarray")]
+    fn vectors_convert_to_and_from_arrays() {
+        let array = ndarray::Array1::from(vec![1.0, 2.0, 3.0]);
+        let embedding = EmbeddingVector::from(array.clone());
+
+        assert_eq!(embedding, vector(&[1.0, 2.0, 3.0]));
+        assert_eq!(embedding.view(), array.view());
+        assert_eq!(ndarray::Array1::from(embedding), array);
+    }
+
+    #[tokio::test]
+    async fn batched_requests_are_merged_in_the_order_of_the_inputs() {
+        let server = MockServer::start().await;
+
+        test_util::embeddings()
+            .respond_with(embed)
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let client = test_util::client(&server);
+        let inputs = (0..MAX_INPUTS_PER_REQUEST + 3)
+            .map(|input| input.to_string())
+            .collect::<Vec<_>>();
+        let response =
+            EmbeddingRequest::for_model(&client, ModelId::TextEmbedding3Small, inputs.into())
+                .execute_batched(2)
+                .await
+                .unwrap();
+
+        assert_eq!(response.data.len(), MAX_INPUTS_PER_REQUEST + 3);
+        assert!(response
+            .data
+            .iter()
+            .enumerate()
+            .all(|(i, embedding)| embedding.index == i && *embedding.embedding == [i as f32]));
+        assert_eq!(
+            response.usage.prompt_tokens,
+            MAX_INPUTS_PER_REQUEST as u64 + 3
+        );
+        assert_eq!(
+            response.usage.total_tokens,
+            MAX_INPUTS_PER_REQUEST as u64 + 3
+        );
+    }
+}
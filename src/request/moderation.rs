@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error,
+    model::Model,
+    request::{ModerationRequest, Request},
+};
+
+/// The text(s) to classify.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ModerationInput {
+    String(String),
+    Strings(Vec<String>),
+}
+
+impl From<String> for ModerationInput {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<&str> for ModerationInput {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<Vec<String>> for ModerationInput {
+    fn from(value: Vec<String>) -> Self {
+        Self::Strings(value)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModerationResponse {
+    pub id: String,
+    pub model: String,
+    /// One result per input.
+    pub results: Vec<ModerationResult>,
+}
+
+impl ModerationResponse {
+    /// Whether any of the inputs was flagged.
+    pub fn flagged(&self) -> bool {
+        self.results.iter().any(|result| result.flagged)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModerationResult {
+    /// Whether any of the categories was flagged.
+    pub flagged: bool,
+    /// Whether each category was flagged, by name (`harassment`, `violence/graphic`, ...).
+    pub categories: BTreeMap<String, bool>,
+    /// Confidence of each category, between 0 and 1.
+    pub category_scores: BTreeMap<String, f64>,
+}
+
+impl ModerationResult {
+    /// Names of the flagged categories.
+    pub fn flagged_categories(&self) -> impl Iterator<Item = &str> {
+        self.categories
+            .iter()
+            .filter_map(|(category, flagged)| flagged.then_some(category.as_str()))
+    }
+}
+
+/// Sends `moderated_input` under its API name.
+pub(crate) fn transform_json(
+    _request: &ModerationRequest,
+    json: &mut serde_json::Map<String, serde_json::Value>,
+) {
+    if let Some(input) = json.remove("moderated_input") {
+        json.insert("input".to_string(), input);
+    }
+}
+
+/// Runs `input` through the moderation model of the client, if it has one.
+pub(crate) async fn check(model: &Model<'_>, input: ModerationInput) -> error::Result<()> {
+    let Some(moderation_model) = model.client().moderation_model() else {
+        return Ok(());
+    };
+
+    let response = ModerationRequest::for_model(model.client(), moderation_model.clone(), input)
+        .execute()
+        .await?;
+
+    flagged_error(response)
+}
+
+/// (Blocking) Runs `input` through the moderation model of the client, if it has one.
+#[cfg(feature = "blocking")]
+pub(crate) fn check_blocking(model: &Model<'_>, input: ModerationInput) -> error::Result<()> {
+    let Some(moderation_model) = model.client().moderation_model() else {
+        return Ok(());
+    };
+
+    let response = ModerationRequest::for_model(model.client(), moderation_model.clone(), input)
+        .execute_blocking()?;
+
+    flagged_error(response)
+}
+
+fn flagged_error(response: ModerationResponse) -> error::Result<()> {
+    if !response.flagged() {
+        return Ok(());
+    }
+
+    let mut categories = response
+        .results
+        .iter()
+        .flat_map(ModerationResult::flagged_categories)
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+
+    categories.sort();
+    categories.dedup();
+
+    Err(error::ContentFlagged {
+        categories,
+        results: response.results,
+    }
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{client::Client, model::ModelId};
+
+    #[test]
+    fn moderated_input_is_sent_as_input() {
+        let client = Client::new("");
+        let request =
+            ModerationRequest::for_model(&client, ModelId::OmniModerationLatest, "Hello".into());
+
+        assert!(matches!(
+            request.moderated_input(),
+            ModerationInput::String(input) if input == "Hello"
+        ));
+        assert!(request.moderation_input().is_none());
+        assert_eq!(
+            request.to_json().unwrap(),
+            serde_json::json!({ "model": "omni-moderation-latest", "input": "Hello" })
+        );
+    }
+}
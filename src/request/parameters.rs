@@ -0,0 +1,79 @@
+use nutype::nutype;
+
+use crate::error;
+
+macro_rules! ranged_parameter {
+    ($($(#[$meta:meta])* $name:ident [$error:ident] ($field:literal, $min:literal..=$max:literal)),* $(,)*) => {
+        $(
+            $(#[$meta])*
+            #[nutype(validate(min = $min, max = $max, with = |value| value.is_finite()))]
+            #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Into, TryFrom, Serialize, Deserialize)]
+            pub struct $name(f64);
+
+            impl $name {
+                pub const MIN: f64 = $min;
+                pub const MAX: f64 = $max;
+            }
+
+            impl From<$error> for error::ValidationError {
+                fn from(_: $error) -> Self {
+                    error::ValidationIssue::OutOfRange {
+                        parameter: $field,
+                        min: $name::MIN,
+                        max: $name::MAX,
+                    }
+                    .into()
+                }
+            }
+
+            impl From<$error> for error::Error {
+                fn from(e: $error) -> Self {
+                    error::ValidationError::from(e).into()
+                }
+            }
+        )*
+    };
+}
+
+ranged_parameter!(
+    /// Sampling temperature, between 0 and 2.
+    Temperature[TemperatureError]("temperature", 0.0..=2.0),
+    /// Nucleus sampling probability mass, between 0 and 1.
+    TopP[TopPError]("top_p", 0.0..=1.0),
+    /// Penalty for tokens that already appeared in the text, between -2 and 2.
+    PresencePenalty[PresencePenaltyError]("presence_penalty", -2.0..=2.0),
+    /// Penalty for tokens proportional to their frequency in the text, between -2 and 2.
+    FrequencyPenalty[FrequencyPenaltyError]("frequency_penalty", -2.0..=2.0),
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_values_out_of_range() {
+        assert!(Temperature::new(Temperature::MIN).is_ok());
+        assert!(Temperature::new(Temperature::MAX).is_ok());
+        assert!(Temperature::new(2.5).is_err());
+        assert!(PresencePenalty::new(-2.5).is_err());
+    }
+
+    #[test]
+    fn rejects_non_finite_values() {
+        for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(Temperature::new(value).is_err());
+            assert!(TopP::new(value).is_err());
+            assert!(PresencePenalty::new(value).is_err());
+            assert!(FrequencyPenalty::new(value).is_err());
+        }
+
+        assert!(matches!(
+            error::ValidationError::from(Temperature::new(f64::NAN).unwrap_err()),
+            error::ValidationError::Issues(issues) if issues == [error::ValidationIssue::OutOfRange {
+                parameter: "temperature",
+                min: 0.0,
+                max: 2.0,
+            }]
+        ));
+    }
+}
@@ -1,21 +1,235 @@
-use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+    num::NonZeroU64,
+};
 
-use crate::request::Usage;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize)]
+use crate::{
+    error,
+    pricing::PricingTable,
+    request::{ObjectType, TextCompletionRequest, Usage},
+};
+
+/// The prompt(s) to generate completions for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Prompt {
+    String(String),
+    Strings(Vec<String>),
+    Tokens(Vec<u32>),
+    TokenArrays(Vec<Vec<u32>>),
+}
+
+impl From<String> for Prompt {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<&str> for Prompt {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<Vec<String>> for Prompt {
+    fn from(value: Vec<String>) -> Self {
+        Self::Strings(value)
+    }
+}
+
+impl From<Vec<&str>> for Prompt {
+    fn from(value: Vec<&str>) -> Self {
+        Self::Strings(value.into_iter().map(ToString::to_string).collect())
+    }
+}
+
+impl From<Vec<u32>> for Prompt {
+    fn from(value: Vec<u32>) -> Self {
+        Self::Tokens(value)
+    }
+}
+
+impl From<Vec<Vec<u32>>> for Prompt {
+    fn from(value: Vec<Vec<u32>>) -> Self {
+        Self::TokenArrays(value)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct TextCompletionResponse {
     pub choices: Vec<TextCompletionChoice>,
     pub created: u64,
     pub id: String,
     pub model: String,
-    pub object: String,
+    pub object: ObjectType,
     pub usage: Usage,
 }
 
-#[derive(Debug, Deserialize)]
+#[cfg(feature = "tokenizer")]
+impl<'model, 'client> super::BiasWords for TextCompletionRequest<'model, 'client> {
+    fn logit_bias_mut(&mut self) -> &mut Option<BTreeMap<u32, i8>> {
+        &mut self.logit_bias
+    }
+
+    fn tokenizer_model(&self) -> &crate::model::ModelId {
+        self.model.id()
+    }
+}
+
+/// The text of each choice, numbered when there are several, followed by the model and the
+/// usage. Choices cut off tell their `finish_reason`.
+impl Display for TextCompletionResponse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for choice in &self.choices {
+            if self.choices.len() > 1 {
+                write!(f, "[{}] ", choice.index)?;
+            }
+
+            write!(f, "{}", choice.text)?;
+
+            if choice.finish_reason != "stop" {
+                write!(f, " [{}]", choice.finish_reason)?;
+            }
+
+            writeln!(f)?;
+        }
+
+        write!(f, "({}, {})", self.model, self.usage)
+    }
+}
+
+impl TextCompletionResponse {
+    /// The text of the first choice, the only one unless `n` was set.
+    pub fn first_text(&self) -> Option<&str> {
+        self.choices.first().map(|choice| choice.text.as_str())
+    }
+
+    /// The text of each choice.
+    pub fn texts(&self) -> Vec<&str> {
+        self.choices
+            .iter()
+            .map(|choice| choice.text.as_str())
+            .collect()
+    }
+
+    /// The text of the first choice, failing if there are no choices.
+    pub fn into_text(self) -> Result<String, error::ParseError> {
+        self.choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.text)
+            .ok_or_else(|| error::ParseError::FieldNotFound("choices".to_string()))
+    }
+
+    /// Cost of this response in USD with `pricing` (the one of the client is
+    /// [`crate::client::Client::pricing_table`]), [None] if its model has no price.
+    pub fn estimated_cost(&self, pricing: &PricingTable) -> Option<f64> {
+        pricing.cost(&self.model, &self.usage)
+    }
+}
+
+/// The text of the first choice, see [`TextCompletionResponse::into_text`].
+impl TryFrom<TextCompletionResponse> for String {
+    type Error = error::ParseError;
+
+    fn try_from(response: TextCompletionResponse) -> Result<Self, Self::Error> {
+        response.into_text()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct TextCompletionChoice {
     pub finish_reason: String,
     pub index: u64,
-    pub logprobs: Option<u8>,
+    pub logprobs: Option<LogProbs>,
     pub text: String,
 }
+
+/// Log probabilities of the generated tokens, returned when `logprobs` is set on the request.
+///
+/// All the vectors are parallel, one entry per token.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogProbs {
+    pub tokens: Vec<String>,
+    /// `null` for the first token of an echoed prompt.
+    pub token_logprobs: Vec<Option<f64>>,
+    /// The `logprobs` most likely tokens and their log probabilities at each position.
+    pub top_logprobs: Option<Vec<Option<BTreeMap<String, f64>>>>,
+    /// Character offset of each token in the completion text.
+    pub text_offset: Vec<u64>,
+}
+
+/// `max_tokens`, 16 when unset like the API does.
+pub(crate) fn output_tokens(request: &TextCompletionRequest) -> Option<u64> {
+    Some(request.max_tokens().unwrap_or(16))
+}
+
+/// Largest `logprobs` accepted by the API.
+pub const LOGPROBS_MAX: u8 = 5;
+
+/// Rejects `best_of` smaller than `n` or combined with `stream`, out of range `logprobs`,
+/// `logit_bias` and `stop`, and prompts longer than the context of the model.
+pub(crate) fn validate(request: &TextCompletionRequest) -> Result<(), Vec<error::ValidationIssue>> {
+    let mut issues = Vec::new();
+
+    if let Some(best_of) = *request.best_of() {
+        let n = request.n().map(NonZeroU64::get).unwrap_or(1);
+
+        if best_of < n {
+            issues.push(error::ValidationIssue::BestOfLessThanN { best_of, n });
+        }
+
+        if request.stream().unwrap_or(false) {
+            issues.push(error::ValidationIssue::BestOfWithStream);
+        }
+    }
+
+    if request
+        .logprobs()
+        .is_some_and(|logprobs| logprobs > LOGPROBS_MAX)
+    {
+        issues.push(error::ValidationIssue::OutOfRange {
+            parameter: "logprobs",
+            min: 0.0,
+            max: LOGPROBS_MAX.into(),
+        });
+    }
+
+    super::check_stop(request.stop().as_ref(), &mut issues);
+    super::check_logit_bias(request.logit_bias().as_ref(), &mut issues);
+
+    #[cfg(feature = "tokenizer")]
+    if let Some(prompt) = request.prompt() {
+        use crate::{request::Request, tokens::count_tokens};
+
+        let model = request.model().id();
+        // Each prompt is completed on its own, the longest one has to fit
+        let prompt_tokens = match prompt {
+            Prompt::String(prompt) => count_tokens(prompt, model),
+            Prompt::Strings(prompts) => prompts
+                .iter()
+                .map(|prompt| count_tokens(prompt, model))
+                .max()
+                .unwrap_or(0),
+            Prompt::Tokens(tokens) => tokens.len(),
+            Prompt::TokenArrays(prompts) => prompts.iter().map(Vec::len).max().unwrap_or(0),
+        };
+
+        if let Err(issue) = super::chat_completion::check_context_length(
+            model,
+            prompt_tokens as u64,
+            output_tokens(request).unwrap_or(0),
+        ) {
+            issues.push(issue);
+        }
+    }
+
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(issues)
+    }
+}
@@ -0,0 +1,467 @@
+use std::{ops::Deref, path::Path, time::Duration};
+
+use reqwest::header::HeaderMap;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    error,
+    model::ModelInfo,
+    pagination::{Page, Paginated},
+    request::{
+        chat_completion::{ChatCompletionChunk, ChatCompletionResponse},
+        edit::EditResponse,
+        embedding::EmbeddingResponse,
+        moderation::ModerationResponse,
+        text_completion::TextCompletionResponse,
+    },
+};
+
+/// A response along with the metadata the API sent in its headers.
+#[derive(Debug)]
+pub struct WithMeta<Response> {
+    pub response: Response,
+    pub meta: ResponseMeta,
+}
+
+impl<Response> WithMeta<Response> {
+    pub fn into_response(self) -> Response {
+        self.response
+    }
+}
+
+impl<Response> Deref for WithMeta<Response> {
+    type Target = Response;
+
+    fn deref(&self) -> &Self::Target {
+        &self.response
+    }
+}
+
+/// Request id, processing time and rate limits of a response.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, getset::Getters)]
+pub struct ResponseMeta {
+    /// `x-request-id`, useful when contacting OpenAI support.
+    #[get = "pub"]
+    request_id: Option<String>,
+    /// `openai-processing-ms`, time the API spent on the request.
+    #[get = "pub"]
+    processing_time: Option<Duration>,
+    /// Time from sending the request until the whole response was received.
+    #[get = "pub"]
+    latency: Duration,
+    #[get = "pub"]
+    rate_limit: RateLimit,
+    /// Whether the response came from the [`crate::cache::ResponseCache`] of the client, in
+    /// which case the rest is the metadata of the original response.
+    #[get = "pub"]
+    cached: bool,
+}
+
+impl ResponseMeta {
+    pub fn from_headers(headers: &HeaderMap, latency: Duration) -> Self {
+        Self {
+            request_id: header_str(headers, "x-request-id").map(ToString::to_string),
+            processing_time: header_parse(headers, "openai-processing-ms")
+                .map(Duration::from_millis),
+            latency,
+            rate_limit: RateLimit::from_headers(headers),
+            cached: false,
+        }
+    }
+
+    pub(crate) fn into_cached(self) -> Self {
+        Self {
+            cached: true,
+            ..self
+        }
+    }
+}
+
+/// The `x-ratelimit-*` headers.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, getset::Getters)]
+pub struct RateLimit {
+    #[get = "pub"]
+    limit_requests: Option<u64>,
+    #[get = "pub"]
+    limit_tokens: Option<u64>,
+    #[get = "pub"]
+    remaining_requests: Option<u64>,
+    #[get = "pub"]
+    remaining_tokens: Option<u64>,
+    /// Time until the request limit resets to its initial state.
+    #[get = "pub"]
+    reset_requests: Option<Duration>,
+    /// Time until the token limit resets to its initial state.
+    #[get = "pub"]
+    reset_tokens: Option<Duration>,
+}
+
+impl RateLimit {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        Self {
+            limit_requests: header_parse(headers, "x-ratelimit-limit-requests"),
+            limit_tokens: header_parse(headers, "x-ratelimit-limit-tokens"),
+            remaining_requests: header_parse(headers, "x-ratelimit-remaining-requests"),
+            remaining_tokens: header_parse(headers, "x-ratelimit-remaining-tokens"),
+            reset_requests: header_str(headers, "x-ratelimit-reset-requests")
+                .and_then(parse_duration),
+            reset_tokens: header_str(headers, "x-ratelimit-reset-tokens").and_then(parse_duration),
+        }
+    }
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn header_parse<T: std::str::FromStr>(headers: &HeaderMap, name: &str) -> Option<T> {
+    header_str(headers, name).and_then(|value| value.trim().parse().ok())
+}
+
+/// The `retry-after` header as a number of seconds, [None] if it is missing, an HTTP date or out of
+/// the range of a [`Duration`].
+pub(crate) fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    header_parse::<f64>(headers, "retry-after")
+        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+}
+
+/// Parses the durations used by the rate limit headers, like `1s`, `6m0s` or `20ms`.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let mut total = Duration::ZERO;
+    let mut rest = value.trim();
+
+    if rest.is_empty() {
+        return None;
+    }
+
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let number = rest[..number_len].parse::<f64>().ok()?;
+        rest = &rest[number_len..];
+
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        let seconds = match &rest[..unit_len] {
+            "h" => number * 3600.0,
+            "m" => number * 60.0,
+            "s" => number,
+            "ms" => number / 1000.0,
+            _ => return None,
+        };
+        rest = &rest[unit_len..];
+
+        total = total.checked_add(Duration::try_from_secs_f64(seconds).ok()?)?;
+    }
+
+    Some(total)
+}
+
+/// How [`FromJsonFile::from_json_file_with`] reads a recorded payload.
+#[derive(Debug, Clone, Default)]
+pub struct JsonFileOptions {
+    /// [JSON pointer](https://datatracker.ietf.org/doc/html/rfc6901) to the response inside the
+    /// file, for recordings wrapping it (`/response/body`, ...).
+    pub pointer: Option<String>,
+    /// Drops the `null` fields of every object, nested ones included, before deserializing. The
+    /// fields with a default then take it instead of failing on `null`, the other ones still fail
+    /// as missing. `null` items of arrays are kept.
+    pub strip_nulls: bool,
+}
+
+/// Loads a response recorded as JSON, to replay production payloads through application logic in
+/// tests.
+pub trait FromJsonFile: DeserializeOwned {
+    fn from_json_file(path: impl AsRef<Path>) -> error::Result<Self> {
+        Self::from_json_file_with(path, &JsonFileOptions::default())
+    }
+
+    fn from_json_file_with(
+        path: impl AsRef<Path>,
+        options: &JsonFileOptions,
+    ) -> error::Result<Self> {
+        let mut json = serde_json::from_slice::<serde_json::Value>(&std::fs::read(path)?)?;
+
+        if let Some(pointer) = &options.pointer {
+            json = json
+                .pointer_mut(pointer)
+                .map(serde_json::Value::take)
+                .ok_or_else(|| error::ParseError::FieldNotFound(pointer.clone()))?;
+        }
+
+        if options.strip_nulls {
+            strip_nulls(&mut json);
+        }
+
+        Ok(serde_json::from_value(json)?)
+    }
+}
+
+fn strip_nulls(json: &mut serde_json::Value) {
+    match json {
+        serde_json::Value::Object(object) => {
+            object.retain(|_, value| !value.is_null());
+            object.values_mut().for_each(strip_nulls);
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+impl FromJsonFile for TextCompletionResponse {}
+impl FromJsonFile for ChatCompletionResponse {}
+impl FromJsonFile for ChatCompletionChunk {}
+impl FromJsonFile for EditResponse {}
+impl FromJsonFile for EmbeddingResponse {}
+impl FromJsonFile for ModerationResponse {}
+impl FromJsonFile for ModelInfo {}
+impl<T: Paginated> FromJsonFile for Page<T> {}
+
+/// How strictly the responses are parsed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParsingMode {
+    /// The responses must follow the schema of the OpenAI API.
+    #[default]
+    Strict,
+    /// Tolerates the small deviations of the providers with an OpenAI compatible API (Groq,
+    /// Together, the Gemini compatibility endpoints, ...): missing or `null` ids, timestamps,
+    /// usages, indexes, finish reasons and roles get a default value, and missing or `null`
+    /// model permissions an empty list. Unknown object types, roles and service tiers are
+    /// always accepted, as their `Other` variant.
+    Relaxed,
+}
+
+impl ParsingMode {
+    /// Parses a response body, see [`crate::client::Client::parsing_mode`].
+    pub fn parse<T: DeserializeOwned>(&self, body: &[u8]) -> serde_json::Result<T> {
+        match self {
+            Self::Strict => serde_json::from_slice(body),
+            Self::Relaxed => {
+                let mut json = serde_json::from_slice::<serde_json::Value>(body)?;
+
+                relax_response(&mut json);
+
+                serde_json::from_value(json)
+            }
+        }
+    }
+}
+
+fn relax_response(json: &mut serde_json::Value) {
+    let Some(response) = json.as_object_mut() else {
+        return;
+    };
+
+    let is_model = response.get("object").and_then(|object| object.as_str()) == Some("model");
+
+    if is_model || response.contains_key("owned_by") {
+        relax_model(response);
+        return;
+    }
+
+    or_default(response, "id", "");
+    or_default(response, "created", 0);
+
+    let first_choice = response
+        .get("choices")
+        .and_then(|choices| choices.as_array()?.first());
+    let object = match first_choice {
+        Some(choice) if choice.get("message").is_some() => Some("chat.completion"),
+        Some(_) => Some("text_completion"),
+        None => None,
+    };
+    let has_embeddings = response
+        .get("data")
+        .and_then(|data| data.as_array())
+        .is_some_and(|data| data.iter().any(|item| item.get("embedding").is_some()));
+
+    if let Some(object) = object.or(has_embeddings.then_some("list")) {
+        or_default(response, "object", object);
+        or_default(response, "model", "");
+        relax_usage(response);
+    }
+
+    if let Some(choices) = response
+        .get_mut("choices")
+        .and_then(|choices| choices.as_array_mut())
+    {
+        for (index, choice) in choices.iter_mut().enumerate() {
+            let Some(choice) = choice.as_object_mut() else {
+                continue;
+            };
+
+            or_default(choice, "index", index);
+            or_default(choice, "finish_reason", "");
+
+            if let Some(message) = choice.get_mut("message").and_then(|m| m.as_object_mut()) {
+                or_default(message, "role", "assistant");
+
+                let tool_calls = message
+                    .get_mut("tool_calls")
+                    .and_then(|tool_calls| tool_calls.as_array_mut());
+
+                for tool_call in tool_calls.into_iter().flatten() {
+                    if let Some(tool_call) = tool_call.as_object_mut() {
+                        or_default(tool_call, "id", "");
+                        or_default(tool_call, "type", "function");
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(data) = response
+        .get_mut("data")
+        .and_then(|data| data.as_array_mut())
+    {
+        for (index, item) in data.iter_mut().enumerate() {
+            let Some(item) = item.as_object_mut() else {
+                continue;
+            };
+
+            if item.contains_key("embedding") {
+                or_default(item, "object", "embedding");
+                or_default(item, "index", index);
+            } else {
+                relax_model(item);
+            }
+        }
+    }
+}
+
+fn relax_model(model: &mut serde_json::Map<String, serde_json::Value>) {
+    or_default(model, "created", 0);
+    or_default(model, "owned_by", "");
+
+    if !model.get("permission").is_some_and(|p| p.is_array()) {
+        model.insert(
+            "permission".to_string(),
+            serde_json::Value::Array(Vec::new()),
+        );
+    }
+
+    let permissions = model
+        .get_mut("permission")
+        .and_then(|permissions| permissions.as_array_mut());
+
+    for permission in permissions.into_iter().flatten() {
+        let Some(permission) = permission.as_object_mut() else {
+            continue;
+        };
+
+        for flag in [
+            "allow_create_engine",
+            "allow_fine_tuning",
+            "allow_logprobs",
+            "allow_sampling",
+            "allow_search_indices",
+            "allow_view",
+            "is_blocking",
+        ] {
+            or_default(permission, flag, false);
+        }
+
+        or_default(permission, "created", 0);
+        or_default(permission, "id", "");
+        or_default(permission, "organization", "*");
+    }
+}
+
+/// Fills a missing usage with zeros and its missing counts, the total being the sum of the
+/// others.
+fn relax_usage(response: &mut serde_json::Map<String, serde_json::Value>) {
+    if !response.get("usage").is_some_and(|usage| usage.is_object()) {
+        response.insert("usage".to_string(), serde_json::json!({}));
+    }
+
+    let Some(usage) = response.get_mut("usage").and_then(|u| u.as_object_mut()) else {
+        return;
+    };
+
+    or_default(usage, "prompt_tokens", 0);
+    or_default(usage, "completion_tokens", 0);
+
+    let count = |usage: &serde_json::Map<_, serde_json::Value>, key| {
+        usage.get(key).and_then(|count| count.as_u64()).unwrap_or(0)
+    };
+    let total = count(usage, "prompt_tokens") + count(usage, "completion_tokens");
+
+    or_default(usage, "total_tokens", total);
+}
+
+/// Sets `key` to `default` if it is missing or `null`.
+fn or_default(
+    object: &mut serde_json::Map<String, serde_json::Value>,
+    key: &str,
+    default: impl Into<serde_json::Value>,
+) {
+    if object.get(key).is_none_or(|value| value.is_null()) {
+        object.insert(key.to_string(), default.into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::fixtures;
+
+    #[test]
+    fn parses_rate_limit_durations() {
+        assert_eq!(parse_duration("1s"), Some(Duration::from_secs(1)));
+        assert_eq!(parse_duration("6m0s"), Some(Duration::from_secs(360)));
+        assert_eq!(parse_duration("20ms"), Some(Duration::from_millis(20)));
+        assert_eq!(
+            parse_duration(" 1h2m3.5s "),
+            Some(Duration::from_millis(3_723_500))
+        );
+        assert_eq!(parse_duration("0.5s"), Some(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        for value in ["", "12", "s", "1d", "1.2.3s", "6 m", "-1s"] {
+            assert_eq!(parse_duration(value), None, "{value:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_durations_out_of_range() {
+        assert_eq!(parse_duration("99999999999999999999h"), None);
+        assert_eq!(
+            parse_duration("18446744073709551615s18446744073709551615s"),
+            None
+        );
+    }
+
+    #[test]
+    fn recorded_nulls_can_fall_back_to_defaults() {
+        let mut body = fixtures::text_completion("Hello");
+        body["usage"]["completion_tokens"] = serde_json::Value::Null;
+
+        let path = std::env::temp_dir().join(format!("recorded-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            serde_json::json!({ "response": { "body": body } }).to_string(),
+        )
+        .unwrap();
+
+        let mut options = JsonFileOptions {
+            pointer: Some("/response/body".to_string()),
+            strip_nulls: false,
+        };
+
+        assert!(TextCompletionResponse::from_json_file_with(&path, &options).is_err());
+
+        options.strip_nulls = true;
+
+        let response = TextCompletionResponse::from_json_file_with(&path, &options).unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(response.choices[0].text, "Hello");
+        assert_eq!(response.usage.completion_tokens, 0);
+    }
+}
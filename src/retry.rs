@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+
+use crate::{error, request::Request};
+
+/// How often and how long apart failed requests are sent again. Only the errors that may go
+/// away are retried, see [`error::Error::is_retryable`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts in total, the first one included.
+    pub max_attempts: usize,
+    /// Delay before the second attempt, doubled before each following one.
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A single attempt.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Delay after the failed `attempt` (starting at 1), the `retry-after` of the API if it sent
+    /// one.
+    pub fn delay(&self, attempt: usize, error: &error::Error) -> Duration {
+        if let error::Error::ApiError(error::ApiError {
+            retry_after: Some(retry_after),
+            ..
+        }) = error
+        {
+            return (*retry_after).min(self.max_delay);
+        }
+
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1).min(31) as u32);
+
+        self.initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay)
+    }
+
+    /// Whether `error`, from the failed `attempt`, should be retried.
+    pub fn should_retry(&self, attempt: usize, error: &error::Error) -> bool {
+        attempt < self.max_attempts && error.is_retryable()
+    }
+}
+
+/// Executes `request`, retrying it according to `policy`.
+pub async fn execute_with_retry<'model, 'client, R, Response>(
+    request: &R,
+    policy: &RetryPolicy,
+) -> error::Result<Response>
+where
+    R: Request<'model, 'client, Response> + Sync,
+    Response: DeserializeOwned,
+    'client: 'model,
+{
+    let mut attempt = 1;
+
+    loop {
+        let delay = match request.execute().await {
+            Err(e) if policy.should_retry(attempt, &e) => policy.delay(attempt, &e),
+            result => return result,
+        };
+
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// (Blocking) Executes `request`, retrying it according to `policy`.
+#[cfg(feature = "blocking")]
+pub fn execute_blocking_with_retry<'model, 'client, R, Response>(
+    request: &R,
+    policy: &RetryPolicy,
+) -> error::Result<Response>
+where
+    R: Request<'model, 'client, Response>,
+    Response: DeserializeOwned,
+    'client: 'model,
+{
+    let mut attempt = 1;
+
+    loop {
+        match request.execute_blocking() {
+            Err(e) if policy.should_retry(attempt, &e) => {
+                std::thread::sleep(policy.delay(attempt, &e))
+            }
+            result => return result,
+        }
+
+        attempt += 1;
+    }
+}
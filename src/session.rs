@@ -0,0 +1,440 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::Client,
+    error,
+    model::ModelId,
+    request::{chat_completion::ChatMessage, ChatCompletionRequest},
+};
+#[cfg(feature = "tokenizer")]
+use crate::{
+    request::chat_completion::ChatRole,
+    summarize::{summarize_long_text, SummarizeOptions},
+};
+
+/// A conversation and the parameters it is run with, to move prompts between this crate and
+/// other tooling.
+///
+/// It (de)serializes as the body of a chat completion request, the JSON the OpenAI playground
+/// exports and imports a preset as: `model`, `messages` and the parameters (`temperature`,
+/// `max_tokens`, ...) next to them. The parameters are kept as JSON, those this crate doesn't
+/// model included.
+///
+/// With the `tokenizer` feature, `ChatSession::truncate` keeps long conversations within a token
+/// budget, see `Truncation`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatSession {
+    pub model: ModelId,
+    #[serde(default)]
+    pub messages: Vec<ChatMessage>,
+    #[serde(flatten)]
+    pub parameters: serde_json::Map<String, serde_json::Value>,
+}
+
+/// How [`ChatSession::truncate`] shortens a conversation over its token budget. The last message
+/// is always kept.
+#[cfg(feature = "tokenizer")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Truncation {
+    /// Drops the oldest messages.
+    DropOldest,
+    /// Drops the oldest messages, except the system (and developer) ones.
+    KeepSystem,
+    /// Replaces the messages [`Truncation::KeepSystem`] would drop with a system message
+    /// summarizing them, written by `model`, then drops the oldest ones if that is not enough.
+    SummarizeThenDrop { model: ModelId },
+}
+
+#[cfg(feature = "tokenizer")]
+const SUMMARY_PROMPT: &str = "Summarize the following beginning of a conversation between a user \
+    and an assistant. Keep the facts, decisions and open questions the rest of the conversation \
+    may refer to.";
+
+impl ChatSession {
+    pub fn new(model: impl Into<ModelId>) -> Self {
+        Self {
+            model: model.into(),
+            messages: Vec::new(),
+            parameters: serde_json::Map::new(),
+        }
+    }
+
+    pub fn with_messages(mut self, messages: impl IntoIterator<Item = ChatMessage>) -> Self {
+        self.messages.extend(messages);
+        self
+    }
+
+    pub fn with_parameter(
+        mut self,
+        key: impl AsRef<str>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.parameters
+            .insert(key.as_ref().to_string(), value.into());
+        self
+    }
+
+    pub fn push(&mut self, message: ChatMessage) {
+        self.messages.push(message);
+    }
+
+    /// The session of `request`, from its typed fields and extra ones, as set on it rather than
+    /// as adapted to its model when sent.
+    pub fn from_request(request: &ChatCompletionRequest) -> error::Result<Self> {
+        let mut fields = request.fields_to_json()?;
+
+        fields.extend(request.extra().clone());
+
+        Ok(serde_json::from_value(fields.into())?)
+    }
+
+    /// A request continuing this session with `client`. The parameters set its typed fields, those
+    /// it doesn't model being sent as they are (see `with_extra`).
+    pub fn to_request<'model, 'client>(
+        &self,
+        client: &'client Client,
+    ) -> error::Result<ChatCompletionRequest<'model, 'client>> {
+        Ok(
+            ChatCompletionRequest::for_model(client, self.model.clone(), self.messages.clone())
+                .with_fields_json(self.parameters.clone())?,
+        )
+    }
+
+    pub fn from_json(json: &str) -> error::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// As pretty JSON.
+    pub fn to_json(&self) -> error::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> error::Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> error::Result<()> {
+        Ok(std::fs::write(path, self.to_json()?)?)
+    }
+}
+
+#[cfg(feature = "tokenizer")]
+impl ChatSession {
+    /// Tokens of the messages for the model of the session, see [`crate::tokens::count_tokens`].
+    pub fn count_tokens(&self) -> usize {
+        crate::tokens::count_tokens(&self.messages, &self.model)
+    }
+
+    /// Shortens the conversation with `truncation` until its messages fit in `max_tokens`. It
+    /// can stay over it once only the messages the strategy keeps are left.
+    pub async fn truncate(
+        &mut self,
+        client: &Client,
+        max_tokens: usize,
+        truncation: &Truncation,
+    ) -> error::Result<()> {
+        if let Truncation::SummarizeThenDrop { model } = truncation {
+            if let Some(transcript) = self.transcript_to_summarize(max_tokens) {
+                let options = SummarizeOptions::new().map_prompt(SUMMARY_PROMPT);
+                let summary =
+                    summarize_long_text(client, model.clone(), &transcript, &options, |_| {})
+                        .await?;
+
+                self.replace_with_summary(max_tokens, summary);
+            }
+        }
+
+        self.drop_to_fit(max_tokens, truncation != &Truncation::DropOldest);
+
+        Ok(())
+    }
+
+    /// (Blocking) Same as [`Self::truncate`].
+    #[cfg(feature = "blocking")]
+    pub fn truncate_blocking(
+        &mut self,
+        client: &Client,
+        max_tokens: usize,
+        truncation: &Truncation,
+    ) -> error::Result<()> {
+        if let Truncation::SummarizeThenDrop { model } = truncation {
+            if let Some(transcript) = self.transcript_to_summarize(max_tokens) {
+                let options = SummarizeOptions::new().map_prompt(SUMMARY_PROMPT);
+                let summary = crate::summarize::summarize_long_text_blocking(
+                    client,
+                    model.clone(),
+                    &transcript,
+                    &options,
+                    |_| {},
+                )?;
+
+                self.replace_with_summary(max_tokens, summary);
+            }
+        }
+
+        self.drop_to_fit(max_tokens, truncation != &Truncation::DropOldest);
+
+        Ok(())
+    }
+
+    /// Drops the oldest messages, but the last one and the system ones if `keep_system`, until
+    /// the conversation fits in `max_tokens`. Returns the dropped messages.
+    fn drop_to_fit(&mut self, max_tokens: usize, keep_system: bool) -> Vec<ChatMessage> {
+        let mut dropped = Vec::new();
+
+        while self.count_tokens() > max_tokens {
+            let droppable = self.messages[..self.messages.len().saturating_sub(1)]
+                .iter()
+                .position(|message| !(keep_system && is_instructions(message)));
+
+            match droppable {
+                Some(index) => dropped.push(self.messages.remove(index)),
+                None => break,
+            }
+        }
+
+        dropped
+    }
+
+    /// The messages [`Truncation::KeepSystem`] would drop to fit in `max_tokens`, as a transcript
+    /// to summarize, if any.
+    fn transcript_to_summarize(&self, max_tokens: usize) -> Option<String> {
+        let dropped = self.clone().drop_to_fit(max_tokens, true);
+
+        (!dropped.is_empty()).then(|| {
+            dropped
+                .iter()
+                .map(|message| format!("{}: {}", message.role.as_str(), message.content.text()))
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        })
+    }
+
+    /// Replaces the messages summarized as `summary` with it, after the system messages.
+    fn replace_with_summary(&mut self, max_tokens: usize, summary: String) {
+        self.drop_to_fit(max_tokens, true);
+
+        let position = self
+            .messages
+            .iter()
+            .take_while(|message| is_instructions(message))
+            .count();
+
+        self.messages.insert(
+            position,
+            ChatMessage::system(format!("Summary of the conversation so far: {summary}")),
+        );
+    }
+}
+
+#[cfg(feature = "tokenizer")]
+fn is_instructions(message: &ChatMessage) -> bool {
+    matches!(message.role, ChatRole::System | ChatRole::Developer)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::request::{chat_completion::ChatRole, Request};
+    #[cfg(feature = "tokenizer")]
+    use crate::test_util::{self, wiremock::MockServer};
+
+    #[cfg(feature = "tokenizer")]
+    fn conversation() -> ChatSession {
+        let turns = (1..=3).flat_map(|turn| {
+            [
+                ChatMessage::user(format!("Tell me fact number {turn} about crabs.")),
+                ChatMessage::assistant(format!("Crab fact {turn}: they walk sideways, mostly.")),
+            ]
+        });
+
+        ChatSession::new(ModelId::Gpt4o)
+            .with_messages([ChatMessage::system("You are a marine biologist.")])
+            .with_messages(turns)
+    }
+
+    /// Tokens of a session with only `messages`.
+    #[cfg(feature = "tokenizer")]
+    fn tokens_of(messages: &[ChatMessage]) -> usize {
+        ChatSession::new(ModelId::Gpt4o)
+            .with_messages(messages.to_vec())
+            .count_tokens()
+    }
+
+    #[cfg(feature = "tokenizer")]
+    fn texts(session: &ChatSession) -> Vec<String> {
+        session
+            .messages
+            .iter()
+            .map(|message| message.content.text())
+            .collect()
+    }
+
+    #[cfg(feature = "tokenizer")]
+    #[tokio::test]
+    async fn drops_the_oldest_messages() {
+        let mut session = conversation();
+        let tail = session.messages[4..].to_vec();
+        let client = Client::new("sk-test");
+
+        session
+            .truncate(&client, tokens_of(&tail), &Truncation::DropOldest)
+            .await
+            .unwrap();
+
+        assert_eq!(session.messages.len(), tail.len());
+        assert_eq!(
+            texts(&session),
+            texts(&ChatSession::new(ModelId::Gpt4o).with_messages(tail))
+        );
+
+        // The last message stays, even over the budget
+        session
+            .truncate(&client, 0, &Truncation::DropOldest)
+            .await
+            .unwrap();
+
+        assert_eq!(session.messages.len(), 1);
+        assert_eq!(session.messages[0].role, ChatRole::Assistant);
+    }
+
+    #[cfg(feature = "tokenizer")]
+    #[tokio::test]
+    async fn keeps_the_system_messages() {
+        let mut session = conversation();
+        let kept = [&session.messages[..1], &session.messages[5..]].concat();
+        let client = Client::new("sk-test");
+
+        session
+            .truncate(&client, tokens_of(&kept), &Truncation::KeepSystem)
+            .await
+            .unwrap();
+
+        assert_eq!(session.messages.len(), kept.len());
+        assert_eq!(session.messages[0].role, ChatRole::System);
+        assert!(session.count_tokens() <= tokens_of(&kept));
+
+        session
+            .truncate(&client, 0, &Truncation::KeepSystem)
+            .await
+            .unwrap();
+
+        assert_eq!(session.messages.len(), 2);
+        assert_eq!(session.messages[0].role, ChatRole::System);
+    }
+
+    #[cfg(feature = "tokenizer")]
+    #[tokio::test]
+    async fn summarizes_the_dropped_messages() {
+        let server = MockServer::start().await;
+
+        test_util::mock_chat_completion(&server, "Two crab facts were shared.").await;
+
+        let mut session = conversation();
+        let summary =
+            ChatMessage::system("Summary of the conversation so far: Two crab facts were shared.");
+        let budget = tokens_of(
+            &[
+                session.messages[..1].to_vec(),
+                vec![summary],
+                session.messages[5..].to_vec(),
+            ]
+            .concat(),
+        );
+        let truncation = Truncation::SummarizeThenDrop {
+            model: ModelId::Gpt4oMini,
+        };
+
+        session
+            .truncate(&test_util::client(&server), budget, &truncation)
+            .await
+            .unwrap();
+
+        assert!(session.count_tokens() <= budget);
+        assert_eq!(session.messages[0].role, ChatRole::System);
+        assert_eq!(
+            session.messages[1].content.text(),
+            "Summary of the conversation so far: Two crab facts were shared."
+        );
+        assert_eq!(
+            session.messages.last().unwrap().content.text(),
+            texts(&conversation())[6]
+        );
+
+        let requests = server.received_requests().await.unwrap();
+        let body = String::from_utf8_lossy(&requests[0].body);
+
+        assert_eq!(requests.len(), 1);
+        assert!(body.contains("gpt-4o-mini"));
+        assert!(body.contains("user: Tell me fact number 1 about crabs."));
+        assert!(!body.contains("You are a marine biologist."));
+    }
+
+    #[test]
+    fn round_trips_the_typed_fields() {
+        let client = Client::new("sk-test");
+        let request = ChatCompletionRequest::for_model(
+            &client,
+            ModelId::O1,
+            vec![
+                ChatMessage::system("Answer in French."),
+                ChatMessage::user("Hello"),
+            ],
+        )
+        .with_max_tokens(100)
+        .with_extra("x_gateway_route", "eu");
+
+        let session = ChatSession::from_request(&request).unwrap();
+
+        // As set on the request, not as adapted to the reasoning model
+        assert_eq!(session.messages[0].role, ChatRole::System);
+        assert_eq!(session.parameters["max_tokens"], json!(100));
+        assert!(!session.parameters.contains_key("max_completion_tokens"));
+        assert_eq!(session.parameters["x_gateway_route"], json!("eu"));
+
+        let restored = session.to_request(&client).unwrap();
+
+        assert_eq!(*restored.max_tokens(), Some(100));
+        assert!(!restored.extra().contains_key("max_tokens"));
+        assert_eq!(restored.extra()["x_gateway_route"], json!("eu"));
+        assert_eq!(restored.to_json().unwrap(), request.to_json().unwrap());
+    }
+
+    #[test]
+    fn saves_and_loads_the_sessions() {
+        let path = std::env::temp_dir().join(format!("chat-session-{}.json", std::process::id()));
+        let session = ChatSession::new(ModelId::Gpt4o)
+            .with_messages([ChatMessage::user("Hello")])
+            .with_parameter("temperature", 0.5)
+            .with_parameter("seed", 42);
+
+        session.save(&path).unwrap();
+
+        let loaded = ChatSession::load(&path).unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.model, ModelId::Gpt4o);
+        assert_eq!(loaded.messages[0].content.text(), "Hello");
+        assert_eq!(loaded.parameters, session.parameters);
+
+        let client = Client::new("sk-test");
+        let request = loaded.to_request(&client).unwrap();
+
+        assert_eq!(request.temperature().map(f64::from), Some(0.5));
+        assert_eq!(*request.seed(), Some(42));
+        assert!(request.extra().is_empty());
+    }
+
+    #[test]
+    fn rejects_mistyped_parameters() {
+        let client = Client::new("sk-test");
+        let session = ChatSession::new(ModelId::Gpt4o).with_parameter("max_tokens", "many");
+
+        assert!(session.to_request(&client).is_err());
+    }
+}
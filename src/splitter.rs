@@ -0,0 +1,169 @@
+use crate::{model::ModelId, tokens};
+
+/// Splits long documents into chunks of a number of tokens, to embed or summarize them piece by
+/// piece.
+///
+/// Consecutive chunks can overlap, so that what is cut at the end of one chunk is still whole in
+/// the next. A chunk ends at whitespace when there is some in its second half, not to cut words.
+#[derive(Debug, Clone)]
+pub struct TextSplitter {
+    model: ModelId,
+    chunk_tokens: usize,
+    overlap_tokens: usize,
+}
+
+impl TextSplitter {
+    /// Chunks of at most `chunk_tokens` tokens of `model`, without overlap.
+    pub fn new(model: impl Into<ModelId>, chunk_tokens: usize) -> Self {
+        Self {
+            model: model.into(),
+            chunk_tokens: chunk_tokens.max(1),
+            overlap_tokens: 0,
+        }
+    }
+
+    /// Tokens at the end of a chunk repeated at the start of the next one, less than half a chunk.
+    pub fn overlap(mut self, overlap_tokens: usize) -> Self {
+        self.overlap_tokens = overlap_tokens.min(self.chunk_tokens.saturating_sub(1) / 2);
+        self
+    }
+
+    pub fn chunk_tokens(&self) -> usize {
+        self.chunk_tokens
+    }
+
+    pub fn overlap_tokens(&self) -> usize {
+        self.overlap_tokens
+    }
+
+    /// The chunks of `text`, in order.
+    pub fn split<'text>(&self, text: &'text str) -> Vec<&'text str> {
+        let offsets = tokens::token_offsets(text, &self.model);
+        let token_count = offsets.len() - 1;
+        let mut chunks = Vec::new();
+        let mut start = 0;
+
+        loop {
+            let mut end = (start + self.chunk_tokens).min(token_count);
+
+            if end < token_count {
+                let half = start + self.chunk_tokens / 2 + 1;
+
+                if let Some(boundary) = (half..=end)
+                    .rev()
+                    .find(|&token| text[offsets[token]..].starts_with(char::is_whitespace))
+                {
+                    end = boundary;
+                }
+            }
+
+            let chunk = &text[offsets[start]..offsets[end]];
+
+            if !chunk.trim().is_empty() {
+                chunks.push(chunk);
+            }
+
+            if end >= token_count {
+                return chunks;
+            }
+
+            start = end.saturating_sub(self.overlap_tokens).max(start + 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "Crabs walk sideways because of how their legs bend. They can walk \
+        forwards too, slowly. Hermit crabs move into bigger shells as they grow, and some queue \
+        by size to swap shells together.";
+
+    fn tokens(text: &str) -> usize {
+        tokens::count_tokens(text, &ModelId::Gpt4o)
+    }
+
+    #[test]
+    fn short_and_empty_texts() {
+        let splitter = TextSplitter::new(ModelId::Gpt4o, 1_000).overlap(100);
+
+        assert_eq!(splitter.split(TEXT), [TEXT]);
+        assert!(splitter.split("").is_empty());
+        assert!(splitter.split(" \n\t ").is_empty());
+    }
+
+    #[test]
+    fn chunks_fit_and_cover_the_text() {
+        for chunk_tokens in [1, 2, 5, 8, 13] {
+            let chunks = TextSplitter::new(ModelId::Gpt4o, chunk_tokens).split(TEXT);
+
+            assert!(chunks.iter().all(|chunk| tokens(chunk) <= chunk_tokens));
+            assert_eq!(chunks.concat(), TEXT, "{chunk_tokens} tokens per chunk");
+        }
+    }
+
+    #[test]
+    fn chunks_end_at_whitespace() {
+        let chunks = TextSplitter::new(ModelId::Gpt4o, 8).split(TEXT);
+
+        for pair in chunks.windows(2) {
+            assert!(
+                pair[1].starts_with(char::is_whitespace),
+                "{:?} cuts a word before {:?}",
+                pair[0],
+                pair[1]
+            );
+        }
+    }
+
+    #[test]
+    fn chunks_overlap() {
+        let splitter = TextSplitter::new(ModelId::Gpt4o, 12).overlap(4);
+        let chunks = splitter.split(TEXT);
+
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| tokens(chunk) <= 12));
+        assert!(chunks.last().unwrap().ends_with("together."));
+
+        for pair in chunks.windows(2) {
+            let start = TEXT.find(pair[0]).unwrap();
+            let next_start = TEXT.find(pair[1]).unwrap();
+
+            assert!(next_start > start);
+            assert!(next_start < start + pair[0].len(), "{pair:?} don't overlap");
+        }
+    }
+
+    #[test]
+    fn overlap_stays_under_half_a_chunk() {
+        assert_eq!(
+            TextSplitter::new(ModelId::Gpt4o, 10)
+                .overlap(50)
+                .overlap_tokens(),
+            4
+        );
+        assert_eq!(
+            TextSplitter::new(ModelId::Gpt4o, 1)
+                .overlap(50)
+                .overlap_tokens(),
+            0
+        );
+        assert_eq!(TextSplitter::new(ModelId::Gpt4o, 0).chunk_tokens(), 1);
+    }
+
+    #[test]
+    fn multibyte_characters_stay_whole() {
+        let text = "🦀🦀🦀 蟹は横に歩く 🦀🦀🦀 ".repeat(5);
+
+        for chunk_tokens in [1, 3, 7] {
+            let chunks = TextSplitter::new(ModelId::Gpt4o, chunk_tokens)
+                .overlap(1)
+                .split(&text);
+
+            // Slicing on a boundary that splits a character would have panicked
+            assert!(!chunks.is_empty());
+            assert!(chunks.last().unwrap().trim_end().ends_with('🦀'));
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::VecDeque;
+
+use futures_util::{stream, Stream, StreamExt};
+
+use crate::error;
+
+/// Where [`TextChunker`] splits the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChunkBoundary {
+    /// After `.`, `!`, `?` or `…` (and any closing quotes or brackets) followed by whitespace.
+    #[default]
+    Sentence,
+    /// At blank lines.
+    Paragraph,
+}
+
+/// Buffers text deltas and hands them back as whole sentences or paragraphs, which is what
+/// text-to-speech and typewriter style UIs want instead of token fragments.
+#[derive(Debug, Clone, Default)]
+pub struct TextChunker {
+    boundary: ChunkBoundary,
+    buffer: String,
+}
+
+impl TextChunker {
+    pub fn new(boundary: ChunkBoundary) -> Self {
+        Self {
+            boundary,
+            buffer: String::new(),
+        }
+    }
+
+    /// Adds a delta, returning the chunks it completed (trimmed, never empty).
+    pub fn push(&mut self, delta: impl AsRef<str>) -> Vec<String> {
+        self.buffer.push_str(delta.as_ref());
+
+        let mut chunks = Vec::new();
+
+        while let Some((end, next)) = self.find_boundary() {
+            let chunk = self.buffer[..end].trim().to_string();
+
+            self.buffer.drain(..next);
+
+            if !chunk.is_empty() {
+                chunks.push(chunk);
+            }
+        }
+
+        chunks
+    }
+
+    /// The text left once the deltas are over, [None] if there is none.
+    pub fn finish(&mut self) -> Option<String> {
+        let rest = std::mem::take(&mut self.buffer).trim().to_string();
+
+        (!rest.is_empty()).then_some(rest)
+    }
+
+    /// End of the first chunk in the buffer and start of the text after it. A boundary is only
+    /// known once the whitespace following it was received.
+    fn find_boundary(&self) -> Option<(usize, usize)> {
+        match self.boundary {
+            ChunkBoundary::Sentence => {
+                let mut chars = self.buffer.char_indices().peekable();
+
+                while let Some((_, c)) = chars.next() {
+                    if !matches!(c, '.' | '!' | '?' | '…') {
+                        continue;
+                    }
+
+                    while let Some(&(_, '"' | '\'' | ')' | ']' | '”' | '’' | '.' | '!' | '?')) =
+                        chars.peek()
+                    {
+                        chars.next();
+                    }
+
+                    match chars.peek() {
+                        Some(&(end, c)) if c.is_whitespace() => {
+                            return Some((end, end + c.len_utf8()))
+                        }
+                        _ => continue,
+                    }
+                }
+
+                None
+            }
+            ChunkBoundary::Paragraph => self
+                .buffer
+                .find("\n\n")
+                .map(|end| (end, end + "\n\n".len())),
+        }
+    }
+}
+
+/// Regroups a stream of text deltas into a stream of whole chunks, see [`TextChunker`]. Errors
+/// are passed through as they come.
+pub fn chunk_text<S, D>(
+    deltas: S,
+    boundary: ChunkBoundary,
+) -> impl Stream<Item = error::Result<String>>
+where
+    S: Stream<Item = error::Result<D>> + Unpin,
+    D: AsRef<str>,
+{
+    stream::unfold(
+        (deltas, TextChunker::new(boundary), VecDeque::new(), false),
+        |(mut deltas, mut chunker, mut chunks, mut done)| async move {
+            loop {
+                if let Some(chunk) = chunks.pop_front() {
+                    return Some((Ok(chunk), (deltas, chunker, chunks, done)));
+                }
+
+                if done {
+                    return None;
+                }
+
+                match deltas.next().await {
+                    Some(Ok(delta)) => chunks.extend(chunker.push(delta)),
+                    Some(Err(e)) => return Some((Err(e), (deltas, chunker, chunks, done))),
+                    None => {
+                        done = true;
+                        chunks.extend(chunker.finish());
+                    }
+                }
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::stream;
+
+    use super::*;
+
+    fn chunks(boundary: ChunkBoundary, deltas: &[&str]) -> Vec<String> {
+        let mut chunker = TextChunker::new(boundary);
+        let mut chunks = deltas
+            .iter()
+            .flat_map(|delta| chunker.push(delta))
+            .collect::<Vec<_>>();
+
+        chunks.extend(chunker.finish());
+
+        chunks
+    }
+
+    #[test]
+    fn splits_sentences_once_the_whitespace_after_them_arrives() {
+        let mut chunker = TextChunker::default();
+
+        assert!(chunker.push("Crabs walk").is_empty());
+        assert!(chunker.push(" sideways.").is_empty());
+        assert_eq!(chunker.push(" They"), ["Crabs walk sideways."]);
+        assert_eq!(chunker.finish().as_deref(), Some("They"));
+    }
+
+    #[test]
+    fn keeps_the_closing_quotes_and_brackets_with_their_sentence() {
+        assert_eq!(
+            chunks(
+                ChunkBoundary::Sentence,
+                &["He said \"hi.\" Then", " (he left!) Wait… what?! ", "End"],
+            ),
+            [
+                "He said \"hi.\"",
+                "Then (he left!)",
+                "Wait…",
+                "what?!",
+                "End"
+            ]
+        );
+    }
+
+    #[test]
+    fn doesnt_split_inside_numbers_or_names() {
+        assert_eq!(
+            chunks(
+                ChunkBoundary::Sentence,
+                &["It costs 3.5 dollars on example.com today."]
+            ),
+            ["It costs 3.5 dollars on example.com today."]
+        );
+    }
+
+    #[test]
+    fn splits_paragraphs_at_blank_lines() {
+        assert_eq!(
+            chunks(
+                ChunkBoundary::Paragraph,
+                &[
+                    "First line.\nSame paragraph.\n",
+                    "\nSecond",
+                    " paragraph.\n\n\n"
+                ],
+            ),
+            ["First line.\nSame paragraph.", "Second paragraph."]
+        );
+    }
+
+    #[test]
+    fn finishing_flushes_the_rest_once() {
+        let mut chunker = TextChunker::new(ChunkBoundary::Sentence);
+
+        assert!(chunker.push("No ending").is_empty());
+        assert_eq!(chunker.finish().as_deref(), Some("No ending"));
+        assert_eq!(chunker.finish(), None);
+
+        chunker.push("  \n");
+
+        assert_eq!(chunker.finish(), None);
+    }
+
+    #[tokio::test]
+    async fn streams_the_chunks_and_passes_the_errors_through() {
+        let deltas = stream::iter([
+            Ok("One. Tw"),
+            Err(error::Error::IoError(std::io::Error::other("Interrupted"))),
+            Ok("o. Three"),
+        ]);
+        let chunks = chunk_text(deltas, ChunkBoundary::Sentence)
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[0].as_deref().unwrap(), "One.");
+        assert!(matches!(chunks[1], Err(error::Error::IoError(_))));
+        assert_eq!(chunks[2].as_deref().unwrap(), "Two.");
+        assert_eq!(chunks[3].as_deref().unwrap(), "Three");
+    }
+}
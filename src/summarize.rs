@@ -0,0 +1,329 @@
+use futures_util::StreamExt;
+
+use crate::{
+    batch,
+    client::Client,
+    error,
+    model::ModelId,
+    request::{
+        chat_completion::{ChatCompletionResponse, ChatMessage},
+        ChatCompletionRequest,
+    },
+    retry::RetryPolicy,
+    splitter::TextSplitter,
+    tokens,
+};
+
+const MAP_PROMPT: &str = "Summarize the following part of a longer text. Keep the key facts, \
+    names and figures, and leave out anything else.";
+const REDUCE_PROMPT: &str = "The following are summaries of consecutive parts of a text. \
+    Combine them into a single coherent summary of the whole text.";
+
+/// How [`summarize_long_text`] splits and summarizes a text.
+#[derive(Debug, Clone)]
+pub struct SummarizeOptions {
+    chunk_tokens: usize,
+    overlap_tokens: usize,
+    concurrency: usize,
+    map_prompt: String,
+    reduce_prompt: String,
+    retry_policy: RetryPolicy,
+}
+
+/// Where [`summarize_long_text`] is at, passed to its progress callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SummaryProgress {
+    /// `done` of the `total` chunks are summarized. The summaries are split and summarized again
+    /// at the next `level` when they are still too long together.
+    Chunk {
+        level: usize,
+        done: usize,
+        total: usize,
+    },
+    /// The summaries of the chunks are being combined into the final one.
+    Reducing { summaries: usize },
+}
+
+impl Default for SummarizeOptions {
+    fn default() -> Self {
+        Self {
+            chunk_tokens: 4_000,
+            overlap_tokens: 200,
+            concurrency: 4,
+            map_prompt: MAP_PROMPT.to_string(),
+            reduce_prompt: REDUCE_PROMPT.to_string(),
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+}
+
+impl SummarizeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Size of the chunks summarized on their own, see [`TextSplitter`].
+    pub fn chunk_tokens(mut self, chunk_tokens: usize) -> Self {
+        self.chunk_tokens = chunk_tokens;
+        self
+    }
+
+    /// Tokens shared by consecutive chunks, see [`TextSplitter::overlap`].
+    pub fn overlap_tokens(mut self, overlap_tokens: usize) -> Self {
+        self.overlap_tokens = overlap_tokens;
+        self
+    }
+
+    /// Most chunks summarized at the same time.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// System message of the requests summarizing a chunk.
+    pub fn map_prompt(mut self, prompt: impl AsRef<str>) -> Self {
+        self.map_prompt = prompt.as_ref().to_string();
+        self
+    }
+
+    /// System message of the request combining the summaries, where to ask for a length, a
+    /// language or a format.
+    pub fn reduce_prompt(mut self, prompt: impl AsRef<str>) -> Self {
+        self.reduce_prompt = prompt.as_ref().to_string();
+        self
+    }
+
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    fn splitter(&self, model: &ModelId) -> TextSplitter {
+        TextSplitter::new(model.clone(), self.chunk_tokens).overlap(self.overlap_tokens)
+    }
+
+    fn request<'model, 'client>(
+        &self,
+        client: &'client Client,
+        model: &ModelId,
+        prompt: &str,
+        text: &str,
+    ) -> ChatCompletionRequest<'model, 'client> {
+        ChatCompletionRequest::for_model(
+            client,
+            model.clone(),
+            vec![ChatMessage::system(prompt), ChatMessage::user(text)],
+        )
+    }
+}
+
+/// Summarizes a text longer than the context of `model`: summarizes its chunks concurrently
+/// (map), then combines their summaries (reduce). Summaries too long to be combined at once are
+/// summarized again first, and cut to a chunk when that doesn't shorten them. `on_progress` is
+/// called as the chunks get summarized.
+///
+/// A text fitting in a chunk is summarized with a single request.
+pub async fn summarize_long_text(
+    client: &Client,
+    model: impl Into<ModelId>,
+    text: &str,
+    options: &SummarizeOptions,
+    mut on_progress: impl FnMut(SummaryProgress),
+) -> error::Result<String> {
+    let model = model.into();
+    let splitter = options.splitter(&model);
+    let mut chunks = splitter
+        .split(text)
+        .into_iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+
+    if chunks.len() <= 1 {
+        let request = options.request(client, &model, &options.map_prompt, text);
+
+        return Ok(first_text(
+            crate::retry::execute_with_retry(&request, &options.retry_policy).await?,
+        ));
+    }
+
+    let mut level = 0;
+
+    loop {
+        let total = chunks.len();
+        let requests = chunks
+            .iter()
+            .map(|chunk| options.request(client, &model, &options.map_prompt, chunk))
+            .collect::<Vec<_>>();
+        let mut summaries = vec![String::new(); total];
+        let mut results =
+            batch::execute_many_stream(requests, options.concurrency, options.retry_policy);
+        let mut done = 0;
+
+        while let Some((index, result)) = results.next().await {
+            summaries[index] = first_text(result?);
+            done += 1;
+            on_progress(SummaryProgress::Chunk { level, done, total });
+        }
+
+        let joined = summaries.join("\n\n");
+        let next_chunks = splitter.split(&joined);
+
+        // Reduced as is when summarizing again wouldn't shorten it, cut to a chunk not to go over
+        // the context of the model
+        if next_chunks.len() <= 1 || next_chunks.len() >= total {
+            on_progress(SummaryProgress::Reducing { summaries: total });
+
+            let joined = tokens::truncate(&joined, options.chunk_tokens, &model);
+            let request = options.request(client, &model, &options.reduce_prompt, joined);
+
+            return Ok(first_text(
+                crate::retry::execute_with_retry(&request, &options.retry_policy).await?,
+            ));
+        }
+
+        chunks = next_chunks.into_iter().map(ToString::to_string).collect();
+        level += 1;
+    }
+}
+
+/// (Blocking) Same as [`summarize_long_text`], one chunk after the other.
+#[cfg(feature = "blocking")]
+pub fn summarize_long_text_blocking(
+    client: &Client,
+    model: impl Into<ModelId>,
+    text: &str,
+    options: &SummarizeOptions,
+    mut on_progress: impl FnMut(SummaryProgress),
+) -> error::Result<String> {
+    use crate::retry::execute_blocking_with_retry;
+
+    let model = model.into();
+    let splitter = options.splitter(&model);
+    let mut chunks = splitter
+        .split(text)
+        .into_iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+
+    if chunks.len() <= 1 {
+        let request = options.request(client, &model, &options.map_prompt, text);
+
+        return Ok(first_text(execute_blocking_with_retry(
+            &request,
+            &options.retry_policy,
+        )?));
+    }
+
+    let mut level = 0;
+
+    loop {
+        let total = chunks.len();
+        let mut summaries = Vec::with_capacity(total);
+
+        for chunk in &chunks {
+            let request = options.request(client, &model, &options.map_prompt, chunk);
+
+            summaries.push(first_text(execute_blocking_with_retry(
+                &request,
+                &options.retry_policy,
+            )?));
+            on_progress(SummaryProgress::Chunk {
+                level,
+                done: summaries.len(),
+                total,
+            });
+        }
+
+        let joined = summaries.join("\n\n");
+        let next_chunks = splitter.split(&joined);
+
+        if next_chunks.len() <= 1 || next_chunks.len() >= total {
+            on_progress(SummaryProgress::Reducing { summaries: total });
+
+            let joined = tokens::truncate(&joined, options.chunk_tokens, &model);
+            let request = options.request(client, &model, &options.reduce_prompt, joined);
+
+            return Ok(first_text(execute_blocking_with_retry(
+                &request,
+                &options.retry_policy,
+            )?));
+        }
+
+        chunks = next_chunks.into_iter().map(ToString::to_string).collect();
+        level += 1;
+    }
+}
+
+fn first_text(response: ChatCompletionResponse) -> String {
+    response.first_text().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{
+        self, fixtures, json_response,
+        wiremock::{matchers::body_string_contains, MockServer},
+    };
+
+    #[tokio::test]
+    async fn summarizes_short_texts_at_once() {
+        let server = MockServer::start().await;
+
+        test_util::mock_chat_completion(&server, "Crabs walk sideways.").await;
+
+        let mut progress = Vec::new();
+        let summary = summarize_long_text(
+            &test_util::client(&server),
+            ModelId::Gpt4oMini,
+            "Crabs walk sideways because of how their legs bend.",
+            &SummarizeOptions::new(),
+            |step| progress.push(step),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(summary, "Crabs walk sideways.");
+        assert!(progress.is_empty());
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn cuts_the_summaries_that_dont_shrink() {
+        let server = MockServer::start().await;
+        let verbose = "Crabs are decapod crustaceans with a short projecting tail. ".repeat(10);
+
+        test_util::chat_completions()
+            .and(body_string_contains("Combine them"))
+            .respond_with(json_response(fixtures::chat_completion("Crabs.")))
+            .mount(&server)
+            .await;
+        test_util::mock_chat_completion(&server, &verbose).await;
+
+        let text = "Crabs walk sideways because of how their legs bend. ".repeat(30);
+        let options = SummarizeOptions::new().chunk_tokens(50).overlap_tokens(0);
+        let mut progress = Vec::new();
+        let summary = summarize_long_text(
+            &test_util::client(&server),
+            ModelId::Gpt4oMini,
+            &text,
+            &options,
+            |step| progress.push(step),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(summary, "Crabs.");
+        assert!(matches!(
+            progress.last(),
+            Some(SummaryProgress::Reducing { .. })
+        ));
+
+        let requests = server.received_requests().await.unwrap();
+        let reduce: serde_json::Value = requests.last().unwrap().body_json().unwrap();
+        let combined = reduce["messages"][1]["content"].as_str().unwrap();
+
+        assert!(tokens::count_tokens(combined, &ModelId::Gpt4oMini) <= 50);
+        assert!(verbose.starts_with(combined));
+    }
+}
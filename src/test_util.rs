@@ -0,0 +1,428 @@
+//! Helpers for testing code built on this crate against a [`wiremock`] server instead of the
+//! real API: a client pointed at the server, mock builders for each endpoint, matchers and
+//! realistic response fixtures.
+
+use std::time::Duration;
+
+use futures_util::{stream, Stream, StreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use wiremock::{
+    matchers::{header, method, path},
+    Match, Mock, MockBuilder, MockServer, ResponseTemplate,
+};
+
+use crate::{
+    client::{Client, BASE_URL},
+    error,
+    model::ModelId,
+    request::{
+        canonicalize,
+        chat_completion::{ChatCompletionChunk, ChatCompletionChunkChoice, ChatDelta, ChatRole},
+        ChatCompletionRequest, EditRequest, EmbeddingRequest, ModerationRequest, ObjectType,
+        Request, TextCompletionRequest,
+    },
+};
+
+pub use wiremock;
+
+pub mod faults;
+pub mod strategies;
+
+/// API key of the [`client`] returned for a mock server.
+pub const TEST_API_KEY: &str = "sk-test";
+
+/// A client sending its requests to the mock server (over plain HTTP).
+pub fn client(server: &MockServer) -> Client {
+    Client::new(TEST_API_KEY)
+        .base_url(server.uri())
+        .allow_http()
+}
+
+/// Path of an endpoint on the mock server, from its full URL.
+fn endpoint(url: &str) -> String {
+    url.strip_prefix(BASE_URL).unwrap_or(url).to_string()
+}
+
+/// `POST /chat/completions`
+pub fn chat_completions() -> MockBuilder {
+    Mock::given(method("POST")).and(path(endpoint(ChatCompletionRequest::URL)))
+}
+
+/// `POST /completions`
+pub fn completions() -> MockBuilder {
+    Mock::given(method("POST")).and(path(endpoint(TextCompletionRequest::URL)))
+}
+
+/// `POST /edits`
+pub fn edits() -> MockBuilder {
+    Mock::given(method("POST")).and(path(endpoint(EditRequest::URL)))
+}
+
+/// `POST /moderations`
+pub fn moderations() -> MockBuilder {
+    Mock::given(method("POST")).and(path(endpoint(ModerationRequest::URL)))
+}
+
+/// `POST /embeddings`
+pub fn embeddings() -> MockBuilder {
+    Mock::given(method("POST")).and(path(endpoint(EmbeddingRequest::URL)))
+}
+
+/// `GET /models`
+pub fn models() -> MockBuilder {
+    Mock::given(method("GET")).and(path("/models"))
+}
+
+/// `GET /models/{model_id}`
+pub fn model(model_id: impl Into<ModelId>) -> MockBuilder {
+    Mock::given(method("GET")).and(path(format!("/models/{}", model_id.into())))
+}
+
+/// Matches requests sent with the API key of the [`client`].
+pub fn authorized() -> impl Match {
+    header("Authorization", format!("Bearer {TEST_API_KEY}").as_str())
+}
+
+/// Matches request bodies whose `model` is the given one.
+pub fn model_is(model_id: impl Into<ModelId>) -> impl Match {
+    ModelIs(model_id.into())
+}
+
+struct ModelIs(ModelId);
+
+impl Match for ModelIs {
+    fn matches(&self, request: &wiremock::Request) -> bool {
+        serde_json::from_slice::<serde_json::Value>(&request.body)
+            .ok()
+            .and_then(|body| body.get("model")?.as_str().map(ModelId::from))
+            .is_some_and(|model_id| model_id == self.0)
+    }
+}
+
+/// The body of a request as canonical pretty JSON, for snapshot assertions (with `insta` for
+/// example) guarding against accidental changes of the wire format.
+pub fn snapshot<'model, 'client, R, Response>(request: &R) -> String
+where
+    R: Request<'model, 'client, Response>,
+    Response: DeserializeOwned,
+    'client: 'model,
+{
+    let json = request
+        .to_canonical_json()
+        .expect("Request bodies always serialize");
+
+    serde_json::to_string_pretty(&json).expect("JSON values always serialize")
+}
+
+/// Any serializable value (responses, messages, ...) as canonical pretty JSON, see [`snapshot`].
+pub fn snapshot_value(value: &impl Serialize) -> String {
+    let json = canonicalize(serde_json::to_value(value).expect("Failed to serialize the value"));
+
+    serde_json::to_string_pretty(&json).expect("JSON values always serialize")
+}
+
+/// A successful response with the given JSON body.
+pub fn json_response(body: serde_json::Value) -> ResponseTemplate {
+    ResponseTemplate::new(200)
+        .insert_header("x-request-id", "req_test")
+        .insert_header("openai-processing-ms", "42")
+        .set_body_json(body)
+}
+
+/// An API error response, as returned for invalid requests, rate limits, ...
+pub fn error_response(status: u16, kind: &str, message: &str) -> ResponseTemplate {
+    ResponseTemplate::new(status).set_body_json(fixtures::error(kind, message))
+}
+
+/// Mounts a chat completion answering `content` on the server.
+pub async fn mock_chat_completion(server: &MockServer, content: &str) {
+    chat_completions()
+        .respond_with(json_response(fixtures::chat_completion(content)))
+        .mount(server)
+        .await;
+}
+
+/// Turns a fixed text into a realistic stream of chat completion chunks, to develop streaming UIs
+/// without spending tokens. The same text and settings always give the same chunks.
+#[derive(Debug, Clone)]
+pub struct FakeChatStream {
+    text: String,
+    chunk_size: usize,
+    delay: Duration,
+}
+
+impl FakeChatStream {
+    pub const DEFAULT_CHUNK_SIZE: usize = 4;
+    pub const DEFAULT_DELAY: Duration = Duration::from_millis(30);
+
+    pub fn new(text: impl AsRef<str>) -> Self {
+        Self {
+            text: text.as_ref().to_string(),
+            chunk_size: Self::DEFAULT_CHUNK_SIZE,
+            delay: Self::DEFAULT_DELAY,
+        }
+    }
+
+    /// Number of characters per chunk, roughly a token each by default.
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Delay before each chunk of [`Self::into_stream`].
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// The chunks: the role first, then the text, then the finish reason.
+    pub fn chunks(&self) -> Vec<ChatCompletionChunk> {
+        let chars = self.text.chars().collect::<Vec<_>>();
+        let role = ChatDelta {
+            role: Some(ChatRole::Assistant),
+            content: Some(String::new()),
+            ..Default::default()
+        };
+        let contents = chars.chunks(self.chunk_size).map(|chunk| ChatDelta {
+            content: Some(chunk.iter().collect()),
+            ..Default::default()
+        });
+
+        std::iter::once((role, None))
+            .chain(contents.map(|delta| (delta, None)))
+            .chain(std::iter::once((
+                ChatDelta::default(),
+                Some("stop".to_string()),
+            )))
+            .map(|(delta, finish_reason)| ChatCompletionChunk {
+                id: "chatcmpl-test".to_string(),
+                object: ObjectType::ChatCompletionChunk,
+                created: 1_700_000_000,
+                model: ModelId::Gpt4oMini.to_string(),
+                choices: vec![ChatCompletionChunkChoice {
+                    index: 0,
+                    delta,
+                    finish_reason,
+                }],
+            })
+            .collect()
+    }
+
+    /// The chunks, each one after the delay.
+    pub fn into_stream(self) -> impl Stream<Item = error::Result<ChatCompletionChunk>> {
+        let delay = self.delay;
+
+        stream::iter(self.chunks()).then(move |chunk| async move {
+            tokio::time::sleep(delay).await;
+
+            Ok(chunk)
+        })
+    }
+
+    /// The chunks as the server-sent events body of a streamed response, ending with `[DONE]`.
+    pub fn sse_body(&self) -> String {
+        self.chunks()
+            .iter()
+            .map(|chunk| {
+                format!(
+                    "data: {}\n\n",
+                    serde_json::to_string(chunk).expect("Chunks always serialize")
+                )
+            })
+            .chain(std::iter::once("data: [DONE]\n\n".to_string()))
+            .collect()
+    }
+
+    /// A streamed response for a mock server. The body is sent at once, wiremock can't delay
+    /// between the events.
+    pub fn response(&self) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_raw(self.sse_body(), "text/event-stream")
+    }
+}
+
+/// Response bodies shaped like the ones of the API.
+pub mod fixtures {
+    use serde_json::json;
+
+    use crate::model::ModelId;
+
+    const CREATED: u64 = 1_700_000_000;
+
+    pub fn chat_completion(content: &str) -> serde_json::Value {
+        json!({
+            "id": "chatcmpl-test",
+            "object": "chat.completion",
+            "created": CREATED,
+            "model": ModelId::Gpt4oMini,
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": content, "refusal": null },
+                "logprobs": null,
+                "finish_reason": "stop",
+            }],
+            "usage": usage(12, 9),
+            "system_fingerprint": "fp_test",
+            "service_tier": "default",
+        })
+    }
+
+    /// An assistant message calling the function `name` with the JSON `arguments`.
+    pub fn chat_completion_tool_call(
+        name: &str,
+        arguments: serde_json::Value,
+    ) -> serde_json::Value {
+        json!({
+            "id": "chatcmpl-test",
+            "object": "chat.completion",
+            "created": CREATED,
+            "model": ModelId::Gpt4oMini,
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_test",
+                        "type": "function",
+                        "function": { "name": name, "arguments": arguments.to_string() },
+                    }],
+                },
+                "finish_reason": "tool_calls",
+            }],
+            "usage": usage(40, 15),
+        })
+    }
+
+    pub fn text_completion(text: &str) -> serde_json::Value {
+        json!({
+            "id": "cmpl-test",
+            "object": "text_completion",
+            "created": CREATED,
+            "model": ModelId::TextDavinci003,
+            "choices": [{
+                "text": text,
+                "index": 0,
+                "logprobs": null,
+                "finish_reason": "stop",
+            }],
+            "usage": usage(5, 7),
+        })
+    }
+
+    pub fn edit(text: &str) -> serde_json::Value {
+        json!({
+            "object": "edit",
+            "created": CREATED,
+            "choices": [{ "text": text, "index": 0 }],
+            "usage": usage(25, 32),
+        })
+    }
+
+    /// A moderation result, with `flagged_categories` flagged and every other category clear.
+    pub fn moderation(flagged_categories: &[&str]) -> serde_json::Value {
+        let categories = [
+            "harassment",
+            "harassment/threatening",
+            "hate",
+            "hate/threatening",
+            "self-harm",
+            "sexual",
+            "violence",
+            "violence/graphic",
+        ];
+
+        json!({
+            "id": "modr-test",
+            "model": ModelId::OmniModerationLatest,
+            "results": [{
+                "flagged": !flagged_categories.is_empty(),
+                "categories": categories
+                    .iter()
+                    .map(|category| (category.to_string(), flagged_categories.contains(category).into()))
+                    .collect::<serde_json::Map<_, _>>(),
+                "category_scores": categories
+                    .iter()
+                    .map(|category| {
+                        let score = if flagged_categories.contains(category) { 0.98 } else { 0.0001 };
+
+                        (category.to_string(), score.into())
+                    })
+                    .collect::<serde_json::Map<_, _>>(),
+            }],
+        })
+    }
+
+    /// The embeddings of as many inputs as `vectors`, counting a token per input.
+    pub fn embedding(vectors: impl IntoIterator<Item = Vec<f32>>) -> serde_json::Value {
+        let data = vectors
+            .into_iter()
+            .enumerate()
+            .map(|(index, vector)| {
+                json!({ "object": "embedding", "index": index, "embedding": vector })
+            })
+            .collect::<Vec<_>>();
+
+        json!({
+            "object": "list",
+            "model": ModelId::TextEmbedding3Small,
+            "usage": usage(data.len() as u64, 0),
+            "data": data,
+        })
+    }
+
+    pub fn model(model_id: impl Into<ModelId>) -> serde_json::Value {
+        json!({
+            "id": model_id.into(),
+            "object": "model",
+            "created": CREATED,
+            "owned_by": "openai",
+        })
+    }
+
+    pub fn model_list(model_ids: impl IntoIterator<Item = ModelId>) -> serde_json::Value {
+        json!({
+            "object": "list",
+            "data": model_ids.into_iter().map(model).collect::<Vec<_>>(),
+        })
+    }
+
+    /// Body of the API errors, `kind` being for example `invalid_request_error`.
+    pub fn error(kind: &str, message: &str) -> serde_json::Value {
+        json!({
+            "error": { "message": message, "type": kind, "param": null, "code": null },
+        })
+    }
+
+    fn usage(prompt_tokens: u64, completion_tokens: u64) -> serde_json::Value {
+        json!({
+            "prompt_tokens": prompt_tokens,
+            "completion_tokens": completion_tokens,
+            "total_tokens": prompt_tokens + completion_tokens,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn fake_streams_are_served_as_event_streams() {
+        let server = MockServer::start().await;
+        let stream = FakeChatStream::new("Once upon a time");
+
+        chat_completions()
+            .respond_with(stream.response())
+            .mount(&server)
+            .await;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/chat/completions", server.uri()))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+        assert_eq!(response.text().await.unwrap(), stream.sse_body());
+    }
+}
@@ -0,0 +1,634 @@
+//! A proxy injecting faults between a client and a server (a [`wiremock::MockServer`] or the real
+//! API), to check that retry and streaming logic built on this crate copes with latency, rate
+//! limits, server errors, truncated streams and connection resets.
+//!
+//! The faults happen on the wire, so they go through the same code paths as real ones, for the
+//! blocking and async clients alike.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+use crate::{client::Client, test_util::TEST_API_KEY};
+
+/// A fault the proxy injects in place of, or on top of, forwarding a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// Waits before forwarding the request.
+    Latency(Duration),
+    /// Answers `429 Too Many Requests` with a `retry-after` header instead of forwarding.
+    RateLimited { retry_after: Duration },
+    /// Answers `500 Internal Server Error` instead of forwarding.
+    ServerError,
+    /// Forwards the request, but closes the connection after `bytes` of the response body.
+    Truncated { bytes: usize },
+    /// Resets the connection without answering.
+    ConnectionReset,
+}
+
+/// How often the [`FaultProxy`] injects each fault on its own. Every probability is 0 by
+/// default, and the faults are drawn from a seeded generator, so a run can be reproduced.
+#[derive(Debug, Clone)]
+pub struct FaultConfig {
+    seed: u64,
+    latency: Option<(Duration, Duration)>,
+    rate_limited: f64,
+    server_error: f64,
+    truncated_streams: f64,
+    connection_resets: f64,
+}
+
+impl Default for FaultConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0x5eed,
+            latency: None,
+            rate_limited: 0.0,
+            server_error: 0.0,
+            truncated_streams: 0.0,
+            connection_resets: 0.0,
+        }
+    }
+}
+
+impl FaultConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Delays every request by a duration between `min` and `max`.
+    pub fn latency(mut self, min: Duration, max: Duration) -> Self {
+        self.latency = Some((min, max.max(min)));
+        self
+    }
+
+    /// Probability of answering a request with a [`Fault::RateLimited`].
+    pub fn rate_limited(mut self, probability: f64) -> Self {
+        self.rate_limited = probability;
+        self
+    }
+
+    /// Probability of answering a request with a [`Fault::ServerError`].
+    pub fn server_errors(mut self, probability: f64) -> Self {
+        self.server_error = probability;
+        self
+    }
+
+    /// Probability of cutting a streamed (`text/event-stream`) response at a random point.
+    pub fn truncated_streams(mut self, probability: f64) -> Self {
+        self.truncated_streams = probability;
+        self
+    }
+
+    /// Probability of resetting the connection of a request.
+    pub fn connection_resets(mut self, probability: f64) -> Self {
+        self.connection_resets = probability;
+        self
+    }
+}
+
+/// A local HTTP proxy forwarding the requests to `upstream` and injecting faults, either drawn
+/// according to its [`FaultConfig`] or queued with [`FaultProxy::inject`]. Each request gets at
+/// most one fault besides the latency.
+///
+/// The proxy stops when dropped.
+#[derive(Debug)]
+pub struct FaultProxy {
+    uri: String,
+    state: Arc<Mutex<State>>,
+    task: JoinHandle<()>,
+}
+
+#[derive(Debug)]
+struct State {
+    config: FaultConfig,
+    rng: u64,
+    queued: VecDeque<Fault>,
+    injected: Vec<Fault>,
+}
+
+impl FaultProxy {
+    /// Starts the proxy on a free local port.
+    pub async fn start(upstream: impl AsRef<str>, config: FaultConfig) -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let uri = format!("http://{}", listener.local_addr()?);
+        let upstream = upstream.as_ref().trim_end_matches('/').to_string();
+        let state = Arc::new(Mutex::new(State {
+            rng: config.seed.max(1),
+            config,
+            queued: VecDeque::new(),
+            injected: Vec::new(),
+        }));
+
+        let task = tokio::spawn({
+            let state = state.clone();
+            let http = reqwest::Client::new();
+
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(handle(
+                        stream,
+                        upstream.clone(),
+                        http.clone(),
+                        state.clone(),
+                    ));
+                }
+            }
+        });
+
+        Ok(Self { uri, state, task })
+    }
+
+    /// Base URL of the proxy.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// A client sending its requests through the proxy, with the API key of
+    /// [`crate::test_util::client`]. Build one with [`Client::base_url`] and
+    /// [`Client::allow_http`] to use another key.
+    pub fn client(&self) -> Client {
+        Client::new(TEST_API_KEY).base_url(&self.uri).allow_http()
+    }
+
+    /// Injects `fault` on the next request without one already queued, whatever the
+    /// configuration. [`Fault::Truncated`] applies to any response, streamed or not.
+    pub fn inject(&self, fault: Fault) {
+        self.state().queued.push_back(fault);
+    }
+
+    /// The faults injected so far, in order.
+    pub fn injected(&self) -> Vec<Fault> {
+        self.state().injected.clone()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for FaultProxy {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl State {
+    /// xorshift64*, good enough to spread the faults.
+    fn next_f64(&mut self) -> f64 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+
+        let n = self.rng.wrapping_mul(0x2545_f491_4f6c_dd1d);
+
+        (n >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn latency(&mut self) -> Option<Duration> {
+        let (min, max) = self.config.latency?;
+
+        Some(min + (max - min).mul_f64(self.next_f64()))
+    }
+
+    /// The fault of a request.
+    fn fault(&mut self) -> Option<Planned> {
+        if let Some(fault) = self.queued.pop_front() {
+            return Some(Planned::Fault(fault));
+        }
+
+        let roll = self.next_f64();
+        let config = &self.config;
+        let faults = [
+            (
+                config.rate_limited,
+                Planned::Fault(Fault::RateLimited {
+                    retry_after: Duration::from_secs(1),
+                }),
+            ),
+            (config.server_error, Planned::Fault(Fault::ServerError)),
+            (
+                config.connection_resets,
+                Planned::Fault(Fault::ConnectionReset),
+            ),
+            (config.truncated_streams, Planned::TruncateStream),
+        ];
+
+        let mut threshold = 0.0;
+
+        faults.into_iter().find_map(|(probability, fault)| {
+            threshold += probability;
+            (roll < threshold).then_some(fault)
+        })
+    }
+}
+
+/// A fault decided before forwarding a request.
+enum Planned {
+    Fault(Fault),
+    /// A drawn truncation, only applied if the response turns out to be a stream.
+    TruncateStream,
+}
+
+enum Truncate {
+    At(usize),
+    /// At a random point, if the response is a stream.
+    Stream,
+}
+
+struct ProxiedRequest {
+    method: String,
+    target: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+async fn handle(
+    mut stream: TcpStream,
+    upstream: String,
+    http: reqwest::Client,
+    state: Arc<Mutex<State>>,
+) {
+    let Some(request) = read_request(&mut stream).await else {
+        return;
+    };
+
+    let (latency, fault) = {
+        let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+        let latency = state.latency();
+
+        (latency, state.fault())
+    };
+
+    let record = |fault: Fault| {
+        state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .injected
+            .push(fault)
+    };
+
+    if let Some(latency) = latency {
+        record(Fault::Latency(latency));
+        tokio::time::sleep(latency).await;
+    }
+
+    let truncate = match fault {
+        Some(Planned::Fault(Fault::Latency(latency))) => {
+            record(Fault::Latency(latency));
+            tokio::time::sleep(latency).await;
+            None
+        }
+        Some(Planned::Fault(Fault::RateLimited { retry_after })) => {
+            record(Fault::RateLimited { retry_after });
+
+            let headers = [("retry-after".to_string(), retry_after.as_secs().to_string())];
+            let body = error_body("rate_limit_exceeded", "Rate limit reached (injected)");
+
+            let _ = write_response(&mut stream, 429, &headers, &body, None).await;
+            return;
+        }
+        Some(Planned::Fault(Fault::ServerError)) => {
+            record(Fault::ServerError);
+
+            let body = error_body("server_error", "The server had an error (injected)");
+
+            let _ = write_response(&mut stream, 500, &[], &body, None).await;
+            return;
+        }
+        Some(Planned::Fault(Fault::ConnectionReset)) => {
+            record(Fault::ConnectionReset);
+            reset(stream);
+            return;
+        }
+        Some(Planned::Fault(Fault::Truncated { bytes })) => Some(Truncate::At(bytes)),
+        Some(Planned::TruncateStream) => Some(Truncate::Stream),
+        None => None,
+    };
+
+    let Ok((status, headers, body)) = forward(&http, &upstream, request).await else {
+        let body = error_body("bad_gateway", "The upstream server could not be reached");
+
+        let _ = write_response(&mut stream, 502, &[], &body, None).await;
+        return;
+    };
+
+    let is_stream = headers.iter().any(|(name, value)| {
+        name.eq_ignore_ascii_case("content-type") && value.starts_with("text/event-stream")
+    });
+
+    let truncate = match truncate {
+        Some(Truncate::At(bytes)) => Some(bytes),
+        Some(Truncate::Stream) if is_stream => {
+            let fraction = state.lock().unwrap_or_else(|e| e.into_inner()).next_f64();
+
+            Some((body.len() as f64 * fraction) as usize)
+        }
+        Some(Truncate::Stream) | None => None,
+    };
+
+    if let Some(bytes) = truncate {
+        record(Fault::Truncated { bytes });
+    }
+
+    if write_response(&mut stream, status, &headers, &body, truncate)
+        .await
+        .is_ok()
+        && truncate.is_some()
+    {
+        reset(stream);
+    }
+}
+
+/// Reads a request, with its body when it has a `content-length`.
+async fn read_request(stream: &mut TcpStream) -> Option<ProxiedRequest> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 8192];
+
+    let head_end = loop {
+        if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end;
+        }
+
+        let read = stream.read(&mut chunk).await.ok()?;
+
+        if read == 0 {
+            return None;
+        }
+
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = std::str::from_utf8(&buffer[..head_end]).ok()?;
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split(' ');
+    let method = request_line.next()?.to_string();
+    let target = request_line.next()?.to_string();
+
+    let headers = lines
+        .filter_map(|line| {
+            let (name, value) = line.split_once(':')?;
+
+            Some((name.trim().to_string(), value.trim().to_string()))
+        })
+        .collect::<Vec<_>>();
+
+    let content_length = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.parse::<usize>().ok())
+        .unwrap_or(0);
+
+    let mut body = buffer.split_off(head_end + 4);
+
+    while body.len() < content_length {
+        let read = stream.read(&mut chunk).await.ok()?;
+
+        if read == 0 {
+            return None;
+        }
+
+        body.extend_from_slice(&chunk[..read]);
+    }
+
+    Some(ProxiedRequest {
+        method,
+        target,
+        headers,
+        body,
+    })
+}
+
+async fn forward(
+    http: &reqwest::Client,
+    upstream: &str,
+    request: ProxiedRequest,
+) -> reqwest::Result<(u16, Vec<(String, String)>, Vec<u8>)> {
+    let method =
+        reqwest::Method::from_bytes(request.method.as_bytes()).unwrap_or(reqwest::Method::GET);
+
+    let builder = request
+        .headers
+        .into_iter()
+        .filter(|(name, _)| !is_hop_header(name) && !name.eq_ignore_ascii_case("host"))
+        .fold(
+            http.request(method, format!("{upstream}{}", request.target)),
+            |builder, (name, value)| builder.header(name, value),
+        );
+
+    let response = builder.body(request.body).send().await?;
+    let status = response.status().as_u16();
+
+    let headers = response
+        .headers()
+        .iter()
+        .filter(|(name, _)| !is_hop_header(name.as_str()))
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+
+    let body = response.bytes().await?.to_vec();
+
+    Ok((status, headers, body))
+}
+
+/// Headers describing the connection rather than the message, set again by the proxy.
+fn is_hop_header(name: &str) -> bool {
+    [
+        "connection",
+        "content-length",
+        "keep-alive",
+        "transfer-encoding",
+    ]
+    .iter()
+    .any(|hop| name.eq_ignore_ascii_case(hop))
+}
+
+/// Writes a response announcing the whole body, but only writing `truncate` bytes of it if set.
+async fn write_response(
+    stream: &mut TcpStream,
+    status: u16,
+    headers: &[(String, String)],
+    body: &[u8],
+    truncate: Option<usize>,
+) -> std::io::Result<()> {
+    let reason = reqwest::StatusCode::from_u16(status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or("Unknown");
+
+    let mut head = format!("HTTP/1.1 {status} {reason}\r\n");
+
+    headers
+        .iter()
+        .for_each(|(name, value)| head.push_str(&format!("{name}: {value}\r\n")));
+
+    head.push_str(&format!(
+        "content-length: {}\r\nconnection: close\r\n\r\n",
+        body.len()
+    ));
+
+    stream.write_all(head.as_bytes()).await?;
+    stream
+        .write_all(&body[..truncate.unwrap_or(body.len()).min(body.len())])
+        .await?;
+    stream.flush().await
+}
+
+fn error_body(kind: &str, message: &str) -> Vec<u8> {
+    super::fixtures::error(kind, message)
+        .to_string()
+        .into_bytes()
+}
+
+/// Closes the connection with a reset instead of a graceful shutdown.
+fn reset(stream: TcpStream) {
+    #[allow(deprecated)]
+    let _ = stream.set_linger(Some(Duration::ZERO));
+
+    drop(stream);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use wiremock::MockServer;
+
+    use super::*;
+    use crate::test_util::{chat_completions, fixtures, json_response, FakeChatStream};
+
+    async fn upstream() -> MockServer {
+        let server = MockServer::start().await;
+
+        chat_completions()
+            .respond_with(json_response(fixtures::chat_completion("Hello")))
+            .mount(&server)
+            .await;
+
+        server
+    }
+
+    async fn post(proxy: &FaultProxy) -> reqwest::Result<reqwest::Response> {
+        reqwest::Client::new()
+            .post(format!("{}/chat/completions", proxy.uri()))
+            .body("{}")
+            .send()
+            .await
+    }
+
+    #[tokio::test]
+    async fn forwards_without_faults() {
+        let server = upstream().await;
+        let proxy = FaultProxy::start(server.uri(), FaultConfig::new())
+            .await
+            .unwrap();
+
+        let response = post(&proxy).await.unwrap();
+
+        assert_eq!(response.status(), 200);
+        assert_eq!(
+            response.json::<serde_json::Value>().await.unwrap(),
+            fixtures::chat_completion("Hello")
+        );
+        assert!(proxy.injected().is_empty());
+    }
+
+    #[tokio::test]
+    async fn injects_each_fault_once() {
+        let server = upstream().await;
+        let proxy = FaultProxy::start(server.uri(), FaultConfig::new())
+            .await
+            .unwrap();
+        let faults = [
+            Fault::Latency(Duration::from_millis(50)),
+            Fault::RateLimited {
+                retry_after: Duration::from_secs(2),
+            },
+            Fault::ServerError,
+            Fault::Truncated { bytes: 10 },
+            Fault::ConnectionReset,
+        ];
+
+        faults.iter().cloned().for_each(|fault| proxy.inject(fault));
+
+        let start = Instant::now();
+        let response = post(&proxy).await.unwrap();
+
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert_eq!(response.status(), 200);
+
+        let response = post(&proxy).await.unwrap();
+
+        assert_eq!(response.status(), 429);
+        assert_eq!(response.headers()["retry-after"], "2");
+
+        assert_eq!(post(&proxy).await.unwrap().status(), 500);
+        assert!(post(&proxy).await.unwrap().bytes().await.is_err());
+        assert!(post(&proxy).await.is_err());
+        assert_eq!(post(&proxy).await.unwrap().status(), 200);
+
+        assert_eq!(proxy.injected(), faults);
+    }
+
+    #[tokio::test]
+    async fn truncates_only_streams() {
+        let server = MockServer::start().await;
+
+        chat_completions()
+            .respond_with(FakeChatStream::new("Once upon a time").response())
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        chat_completions()
+            .respond_with(json_response(fixtures::chat_completion("Hello")))
+            .mount(&server)
+            .await;
+
+        let config = FaultConfig::new().truncated_streams(1.0);
+        let proxy = FaultProxy::start(server.uri(), config).await.unwrap();
+
+        assert!(post(&proxy).await.unwrap().bytes().await.is_err());
+        assert!(post(&proxy).await.unwrap().bytes().await.is_ok());
+        assert!(matches!(proxy.injected()[..], [Fault::Truncated { .. }]));
+    }
+
+    #[tokio::test]
+    async fn drawn_faults_follow_the_seed() {
+        let server = upstream().await;
+        let config = FaultConfig::new()
+            .seed(42)
+            .rate_limited(0.3)
+            .server_errors(0.3)
+            .connection_resets(0.3);
+
+        let mut runs = Vec::new();
+
+        for _ in 0..2 {
+            let proxy = FaultProxy::start(server.uri(), config.clone())
+                .await
+                .unwrap();
+
+            for _ in 0..10 {
+                let _ = post(&proxy).await;
+            }
+
+            runs.push(proxy.injected());
+        }
+
+        assert!(!runs[0].is_empty());
+        assert_eq!(runs[0], runs[1]);
+    }
+}
@@ -0,0 +1,336 @@
+//! [`proptest`] strategies generating messages, parameters and whole requests, to check
+//! invariants of code serializing or transforming them.
+//!
+//! The optional collections are generated empty as well, which the API treats differently from
+//! absent ones.
+
+use std::{collections::BTreeMap, num::NonZeroU64};
+
+use proptest::{collection, option, prelude::*};
+
+use crate::{
+    model::Model,
+    request::{
+        chat_completion::{ChatMessage, ChatRole, ContentPart, MessageContent},
+        parameters::{FrequencyPenalty, PresencePenalty, Temperature, TopP},
+        text_completion::Prompt,
+        ChatCompletionRequest, Stop, TextCompletionRequest,
+    },
+};
+
+pub fn chat_role() -> impl Strategy<Value = ChatRole> {
+    prop_oneof![
+        Just(ChatRole::System),
+        Just(ChatRole::Developer),
+        Just(ChatRole::User),
+        Just(ChatRole::Assistant),
+    ]
+}
+
+pub fn message_content() -> impl Strategy<Value = MessageContent> {
+    prop_oneof![
+        any::<String>().prop_map(MessageContent::Text),
+        collection::vec(any::<String>().prop_map(ContentPart::text), 0..4)
+            .prop_map(MessageContent::Parts),
+    ]
+}
+
+pub fn chat_message() -> impl Strategy<Value = ChatMessage> {
+    (chat_role(), message_content()).prop_map(|(role, content)| ChatMessage::new(role, content))
+}
+
+pub fn messages() -> impl Strategy<Value = Vec<ChatMessage>> {
+    collection::vec(chat_message(), 1..8)
+}
+
+pub fn prompt() -> impl Strategy<Value = Prompt> {
+    prop_oneof![
+        any::<String>().prop_map(Prompt::String),
+        collection::vec(any::<String>(), 0..4).prop_map(Prompt::Strings),
+        collection::vec(any::<u32>(), 0..16).prop_map(Prompt::Tokens),
+        collection::vec(collection::vec(any::<u32>(), 0..8), 0..4).prop_map(Prompt::TokenArrays),
+    ]
+}
+
+pub fn stop() -> impl Strategy<Value = Stop> {
+    prop_oneof![
+        any::<String>().prop_map(Stop::Single),
+        collection::vec(any::<String>(), 1..=Stop::MAX_SEQUENCES).prop_map(Stop::Multiple),
+    ]
+}
+
+pub fn temperature() -> impl Strategy<Value = Temperature> {
+    (Temperature::MIN..=Temperature::MAX)
+        .prop_map(|value| Temperature::try_from(value).expect("Generated in range"))
+}
+
+pub fn top_p() -> impl Strategy<Value = TopP> {
+    (TopP::MIN..=TopP::MAX).prop_map(|value| TopP::try_from(value).expect("Generated in range"))
+}
+
+pub fn presence_penalty() -> impl Strategy<Value = PresencePenalty> {
+    (PresencePenalty::MIN..=PresencePenalty::MAX)
+        .prop_map(|value| PresencePenalty::try_from(value).expect("Generated in range"))
+}
+
+pub fn frequency_penalty() -> impl Strategy<Value = FrequencyPenalty> {
+    (FrequencyPenalty::MIN..=FrequencyPenalty::MAX)
+        .prop_map(|value| FrequencyPenalty::try_from(value).expect("Generated in range"))
+}
+
+pub fn logit_bias() -> impl Strategy<Value = BTreeMap<u32, i8>> {
+    collection::btree_map(any::<u32>(), -100i8..=100, 0..8)
+}
+
+pub fn metadata() -> impl Strategy<Value = BTreeMap<String, String>> {
+    collection::btree_map("[a-z_]{1,64}", ".{0,32}", 0..16)
+}
+
+pub fn n() -> impl Strategy<Value = NonZeroU64> {
+    (1u64..=8).prop_map(|n| NonZeroU64::new(n).expect("Generated non-zero"))
+}
+
+/// Chat requests to `model`, with a random subset of the optional parameters set.
+pub fn chat_completion_request<'model, 'client>(
+    model: &'model Model<'client>,
+) -> impl Strategy<Value = ChatCompletionRequest<'model, 'client>> {
+    (
+        messages(),
+        (
+            option::of(temperature()),
+            option::of(top_p()),
+            option::of(n()),
+            option::of(stop()),
+            option::of(any::<u64>()),
+        ),
+        (
+            option::of(presence_penalty()),
+            option::of(frequency_penalty()),
+            option::of(logit_bias()),
+            option::of(any::<String>()),
+            option::of(any::<i64>()),
+            option::of(metadata()),
+        ),
+    )
+        .prop_map(
+            move |(
+                messages,
+                (temperature, top_p, n, stop, max_tokens),
+                (presence_penalty, frequency_penalty, logit_bias, user, seed, metadata),
+            )| {
+                let request = ChatCompletionRequest::init(model, messages);
+                let request = apply(
+                    request,
+                    temperature,
+                    ChatCompletionRequest::with_temperature,
+                );
+                let request = apply(request, top_p, ChatCompletionRequest::with_top_p);
+                let request = apply(request, n, ChatCompletionRequest::with_n);
+                let request = apply(request, stop, ChatCompletionRequest::with_stop);
+                let request = apply(request, max_tokens, ChatCompletionRequest::with_max_tokens);
+                let request = apply(
+                    request,
+                    presence_penalty,
+                    ChatCompletionRequest::with_presence_penalty,
+                );
+                let request = apply(
+                    request,
+                    frequency_penalty,
+                    ChatCompletionRequest::with_frequency_penalty,
+                );
+                let request = apply(request, logit_bias, ChatCompletionRequest::with_logit_bias);
+                let request = apply(request, user, ChatCompletionRequest::with_user);
+                let request = apply(request, seed, ChatCompletionRequest::with_seed);
+
+                apply(request, metadata, ChatCompletionRequest::with_metadata)
+            },
+        )
+}
+
+/// Text completion requests to `model`, with a random subset of the optional parameters set.
+pub fn text_completion_request<'model, 'client>(
+    model: &'model Model<'client>,
+) -> impl Strategy<Value = TextCompletionRequest<'model, 'client>> {
+    (
+        (
+            option::of(prompt()),
+            option::of(any::<String>()),
+            option::of(any::<u64>()),
+            option::of(temperature()),
+            option::of(top_p()),
+        ),
+        (
+            option::of(stop()),
+            option::of(0u8..=5),
+            option::of(any::<bool>()),
+            option::of(logit_bias()),
+            option::of(any::<String>()),
+        ),
+    )
+        .prop_map(
+            move |(
+                (prompt, suffix, max_tokens, temperature, top_p),
+                (stop, logprobs, echo, logit_bias, user),
+            )| {
+                let request = TextCompletionRequest::init(model);
+                let request = apply(request, prompt, TextCompletionRequest::with_prompt);
+                let request = apply(request, suffix, TextCompletionRequest::with_suffix);
+                let request = apply(request, max_tokens, TextCompletionRequest::with_max_tokens);
+                let request = apply(
+                    request,
+                    temperature,
+                    TextCompletionRequest::with_temperature,
+                );
+                let request = apply(request, top_p, TextCompletionRequest::with_top_p);
+                let request = apply(request, stop, TextCompletionRequest::with_stop);
+                let request = apply(request, logprobs, TextCompletionRequest::with_logprobs);
+                let request = apply(request, echo, TextCompletionRequest::with_echo);
+                let request = apply(request, logit_bias, TextCompletionRequest::with_logit_bias);
+
+                apply(request, user, TextCompletionRequest::with_user)
+            },
+        )
+}
+
+fn apply<R, T>(request: R, value: Option<T>, with: impl FnOnce(R, T) -> R) -> R {
+    match value {
+        Some(value) => with(request, value),
+        None => request,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+
+    use proptest::test_runner::TestRunner;
+
+    use super::*;
+    use crate::{client::Client, model::ModelId, request::Request};
+
+    fn hash(value: &impl Hash) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// The body is the same whether it goes through [`Request::to_json`], [`Serialize`] or
+    /// [`Request::to_body`].
+    fn check_serialization<'model, 'client, R, Response>(request: &R) -> Result<(), TestCaseError>
+    where
+        R: Request<'model, 'client, Response> + serde::Serialize,
+        Response: serde::de::DeserializeOwned,
+        'client: 'model,
+    {
+        let json = request.to_json().unwrap();
+
+        prop_assert_eq!(&serde_json::to_value(request).unwrap(), &json);
+        prop_assert_eq!(
+            &serde_json::from_slice::<serde_json::Value>(&request.to_body().unwrap()).unwrap(),
+            &json
+        );
+
+        Ok(())
+    }
+
+    /// Equality and hashing follow the canonical JSON.
+    fn check_canonical_eq<'model, 'client, R, Response>(a: &R, b: &R) -> Result<(), TestCaseError>
+    where
+        R: Request<'model, 'client, Response> + Clone + Eq + Hash,
+        Response: serde::de::DeserializeOwned,
+        'client: 'model,
+    {
+        prop_assert!(a == &a.clone());
+        prop_assert_eq!(hash(a), hash(&a.clone()));
+        prop_assert_eq!(
+            a == b,
+            a.to_canonical_json().unwrap() == b.to_canonical_json().unwrap()
+        );
+
+        if a == b {
+            prop_assert_eq!(hash(a), hash(b));
+        }
+
+        Ok(())
+    }
+
+    /// Empty collections are sent as empty, not dropped.
+    fn check_collection(
+        json: &serde_json::Value,
+        key: &str,
+        len: Option<usize>,
+    ) -> Result<(), TestCaseError> {
+        match len {
+            Some(len) => {
+                prop_assert_eq!(json[key].as_object().map(|object| object.len()), Some(len))
+            }
+            None => prop_assert!(json.get(key).is_none()),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn chat_completion_requests() {
+        let client = Client::new("");
+        let model = client.model_from_id(ModelId::Gpt4o);
+        let requests = (
+            chat_completion_request(&model),
+            chat_completion_request(&model),
+        );
+
+        TestRunner::default()
+            .run(&requests, |(a, b)| {
+                check_serialization(&a)?;
+                check_canonical_eq(&a, &b)?;
+
+                let json = a.to_json().unwrap();
+
+                check_collection(
+                    &json,
+                    "logit_bias",
+                    a.logit_bias().as_ref().map(BTreeMap::len),
+                )?;
+                check_collection(&json, "metadata", a.metadata().as_ref().map(BTreeMap::len))
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn text_completion_requests() {
+        let client = Client::new("");
+        let model = client.model_from_id(ModelId::TextDavinci003);
+        let requests = (
+            text_completion_request(&model),
+            text_completion_request(&model),
+        );
+
+        TestRunner::default()
+            .run(&requests, |(a, b)| {
+                check_serialization(&a)?;
+                check_canonical_eq(&a, &b)?;
+
+                let json = a.to_json().unwrap();
+
+                check_collection(
+                    &json,
+                    "logit_bias",
+                    a.logit_bias().as_ref().map(BTreeMap::len),
+                )
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn stop_sequences_are_never_empty_nor_too_many() {
+        TestRunner::default()
+            .run(&stop(), |stop| {
+                if let Stop::Multiple(sequences) = stop {
+                    prop_assert!((1..=Stop::MAX_SEQUENCES).contains(&sequences.len()));
+                }
+
+                Ok(())
+            })
+            .unwrap();
+    }
+}
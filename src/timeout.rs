@@ -0,0 +1,171 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use crate::model::longest_family;
+
+/// Derives the timeout of each request from the number of tokens it may generate and the speed
+/// of its model, since a single timeout for the whole client either kills long generations or
+/// hangs on short ones.
+///
+/// The timeout is `base + output tokens / tokens per second`, capped at `max`. The speeds are
+/// conservative estimates, well below the usual ones, so slow periods of the API don't turn
+/// into timeouts. Requests without a cap on their output are given
+/// [`Self::default_output_tokens`].
+///
+/// Opt-in, see [`crate::client::Client::timeout_policy`]. A request can also set its own
+/// timeout with `with_timeout`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeoutPolicy {
+    base: Duration,
+    max: Duration,
+    default_output_tokens: u64,
+    tokens_per_second: BTreeMap<String, f64>,
+}
+
+impl Default for TimeoutPolicy {
+    fn default() -> Self {
+        Self {
+            base: Duration::from_secs(30),
+            max: Duration::from_secs(10 * 60),
+            default_output_tokens: 4096,
+            tokens_per_second: BTreeMap::new(),
+        }
+    }
+}
+
+impl TimeoutPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Time allowed on top of the generation: connecting, queueing, reading the prompt, ...
+    pub fn base(mut self, base: Duration) -> Self {
+        self.base = base;
+        self
+    }
+
+    /// Longest timeout, whatever the number of tokens.
+    pub fn max(mut self, max: Duration) -> Self {
+        self.max = max;
+        self
+    }
+
+    /// Output tokens assumed for the requests that don't cap them.
+    pub fn default_output_tokens(mut self, tokens: u64) -> Self {
+        self.default_output_tokens = tokens;
+        self
+    }
+
+    /// Overrides the speed of `model`, and of its snapshots (`gpt-4o-2024-08-06`, ...) without a
+    /// speed of their own.
+    pub fn tokens_per_second(mut self, model: impl AsRef<str>, tokens_per_second: f64) -> Self {
+        self.tokens_per_second
+            .insert(model.as_ref().to_string(), tokens_per_second);
+        self
+    }
+
+    /// Timeout of a request to `model` generating at most `output_tokens`, [None] meaning
+    /// uncapped.
+    pub fn timeout(&self, model: &str, output_tokens: Option<u64>) -> Duration {
+        let output_tokens = output_tokens.unwrap_or(self.default_output_tokens);
+        let tokens_per_second = self.speed(model).max(f64::MIN_POSITIVE);
+        let generation = Duration::try_from_secs_f64(output_tokens as f64 / tokens_per_second)
+            .unwrap_or(self.max);
+
+        (self.base + generation).min(self.max)
+    }
+
+    /// Tokens per second of `model`, the overridden speed of its longest family first.
+    fn speed(&self, model: &str) -> f64 {
+        let overridden = longest_family(&self.tokens_per_second, model, |(family, _)| {
+            family.as_str()
+        });
+
+        match overridden {
+            Some((_, speed)) => *speed,
+            None => longest_family(DEFAULT_SPEEDS, model, |(family, _)| family)
+                .map_or(DEFAULT_SPEED, |(_, speed)| *speed),
+        }
+    }
+}
+
+/// Conservative tokens per second of the model families.
+const DEFAULT_SPEEDS: &[(&str, f64)] = &[
+    ("gpt-4o-mini", 50.0),
+    ("gpt-3.5-turbo", 50.0),
+    ("o1", 25.0),
+    ("o3", 25.0),
+    ("o4-mini", 25.0),
+    ("gpt-4o", 30.0),
+    ("gpt-4-turbo", 15.0),
+    ("gpt-4", 10.0),
+];
+
+/// Tokens per second of the models of unknown speed.
+const DEFAULT_SPEED: f64 = 15.0;
+
+/// For the endpoints that don't generate anything, like embeddings.
+pub(crate) fn no_output_tokens<T>(_request: &T) -> Option<u64> {
+    Some(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        client::Client,
+        model::ModelId,
+        request::{ChatCompletionRequest, Request},
+    };
+
+    #[test]
+    fn snapshots_get_the_speed_of_their_longest_family() {
+        let policy = TimeoutPolicy::new().tokens_per_second("gpt-4o-2024-08-06", 100.0);
+
+        assert_eq!(policy.speed("gpt-4o-mini-2024-07-18"), 50.0);
+        assert_eq!(policy.speed("gpt-4o-2024-05-13"), 30.0);
+        assert_eq!(policy.speed("gpt-4o-2024-08-06"), 100.0);
+        assert_eq!(policy.speed("gpt-4-0613"), 10.0);
+        assert_eq!(policy.speed("o3-mini"), 25.0);
+        assert_eq!(policy.speed("davinci-002"), DEFAULT_SPEED);
+    }
+
+    #[test]
+    fn timeouts_grow_with_the_output_tokens() {
+        let policy = TimeoutPolicy::new()
+            .base(Duration::from_secs(10))
+            .max(Duration::from_secs(60))
+            .default_output_tokens(300)
+            .tokens_per_second("gpt-4", 10.0);
+
+        assert_eq!(policy.timeout("gpt-4", Some(0)), Duration::from_secs(10));
+        assert_eq!(policy.timeout("gpt-4", Some(200)), Duration::from_secs(30));
+        assert_eq!(policy.timeout("gpt-4", None), Duration::from_secs(40));
+        assert_eq!(
+            policy.timeout("gpt-4", Some(100_000)),
+            Duration::from_secs(60)
+        );
+    }
+
+    #[test]
+    fn clients_have_no_policy_by_default() {
+        let client = Client::new("");
+
+        assert_eq!(client.timeout(&ModelId::Gpt4o, Some(100)), None);
+        assert_eq!(
+            client
+                .timeout_policy(TimeoutPolicy::new())
+                .timeout(&ModelId::Gpt4o, Some(300)),
+            Some(Duration::from_secs(40))
+        );
+    }
+
+    #[test]
+    fn requests_override_the_policy() {
+        let client = Client::new("").timeout_policy(TimeoutPolicy::new());
+        let model = client.model_from_id(ModelId::Gpt4o);
+        let request =
+            ChatCompletionRequest::init(&model, vec![]).with_timeout(Duration::from_secs(5));
+
+        assert_eq!(request.timeout(), Some(Duration::from_secs(5)));
+    }
+}
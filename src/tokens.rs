@@ -0,0 +1,192 @@
+use std::collections::BTreeSet;
+
+use tiktoken_rs::{
+    tokenizer::{get_tokenizer, Tokenizer},
+    CoreBPE,
+};
+
+use crate::{model::ModelId, request::chat_completion::ChatMessage};
+
+/// Tokens added around each message of a chat by its format.
+const TOKENS_PER_MESSAGE: usize = 3;
+/// Tokens priming the reply of the assistant.
+const TOKENS_PER_REPLY: usize = 3;
+
+/// Text and messages whose tokens can be counted, see [`count_tokens`].
+pub trait CountTokens {
+    fn count_tokens_with(&self, encoding: &CoreBPE) -> usize;
+}
+
+impl CountTokens for str {
+    fn count_tokens_with(&self, encoding: &CoreBPE) -> usize {
+        encoding.encode_with_special_tokens(self).len()
+    }
+}
+
+impl CountTokens for String {
+    fn count_tokens_with(&self, encoding: &CoreBPE) -> usize {
+        self.as_str().count_tokens_with(encoding)
+    }
+}
+
+/// The message without the tokens of the chat format, see [`CountTokens`] for `[ChatMessage]`.
+impl CountTokens for ChatMessage {
+    fn count_tokens_with(&self, encoding: &CoreBPE) -> usize {
+        let name = self
+            .name
+            .as_ref()
+            .map_or(0, |name| name.count_tokens_with(encoding) + 1);
+        let tool_calls = self.tool_calls.iter().flatten().map(|tool_call| {
+            tool_call.function.name.count_tokens_with(encoding)
+                + tool_call.function.arguments.count_tokens_with(encoding)
+        });
+
+        self.role.as_str().count_tokens_with(encoding)
+            + self.content.text().count_tokens_with(encoding)
+            + name
+            + tool_calls.sum::<usize>()
+    }
+}
+
+/// The prompt of a chat completion request with these messages.
+impl CountTokens for [ChatMessage] {
+    fn count_tokens_with(&self, encoding: &CoreBPE) -> usize {
+        self.iter()
+            .map(|message| TOKENS_PER_MESSAGE + message.count_tokens_with(encoding))
+            .sum::<usize>()
+            + TOKENS_PER_REPLY
+    }
+}
+
+impl CountTokens for Vec<ChatMessage> {
+    fn count_tokens_with(&self, encoding: &CoreBPE) -> usize {
+        self.as_slice().count_tokens_with(encoding)
+    }
+}
+
+/// Number of tokens of `input` (a text, chat messages, ...) for `model`. Images and audio in the
+/// messages are not counted.
+pub fn count_tokens(input: &(impl CountTokens + ?Sized), model: &ModelId) -> usize {
+    input.count_tokens_with(encoding(model))
+}
+
+/// The start of `text` fitting in `max_tokens` tokens of `model`.
+pub fn truncate<'text>(text: &'text str, max_tokens: usize, model: &ModelId) -> &'text str {
+    let offsets = token_offsets(text, model);
+
+    match offsets.get(max_tokens) {
+        Some(&end) => &text[..end],
+        None => text,
+    }
+}
+
+/// The first token of `word` for `model`, alone and after a space as in the middle of a
+/// sentence, to bias it with `logit_bias`. The model can't start a word whose first token is
+/// banned, while the following tokens of a word split in several ones are left alone, being shared
+/// with many other words.
+pub fn word_tokens(word: &str, model: &ModelId) -> BTreeSet<u32> {
+    let encoding = encoding(model);
+
+    [word.to_string(), format!(" {word}")]
+        .iter()
+        .filter_map(|variant| encoding.encode_ordinary(variant).first().copied())
+        .collect()
+}
+
+/// Byte offset in `text` where each of its tokens for `model` starts, followed by the length of
+/// `text`. The offsets of the tokens splitting a character are moved back to its start.
+pub(crate) fn token_offsets(text: &str, model: &ModelId) -> Vec<usize> {
+    let encoding = encoding(model);
+    let mut offsets = vec![0];
+    let mut end = 0;
+    let mut pending = Vec::new();
+
+    for token in encoding.encode_ordinary(text) {
+        pending.push(token);
+
+        // The tokens splitting a character only decode together
+        if let Ok(decoded) = encoding.decode(pending.clone()) {
+            end += decoded.len();
+            pending.clear();
+        }
+
+        offsets.push(end);
+    }
+
+    offsets
+}
+
+/// The tokenizer of `model`, `o200k_base` (the one of the latest models) if it is unknown.
+pub(crate) fn encoding(model: &ModelId) -> &'static CoreBPE {
+    match get_tokenizer(model.as_str()) {
+        Some(Tokenizer::Cl100kBase) => tiktoken_rs::cl100k_base_singleton(),
+        Some(Tokenizer::P50kBase) => tiktoken_rs::p50k_base_singleton(),
+        Some(Tokenizer::P50kEdit) => tiktoken_rs::p50k_edit_singleton(),
+        Some(Tokenizer::R50kBase | Tokenizer::Gpt2) => tiktoken_rs::r50k_base_singleton(),
+        Some(Tokenizer::O200kBase) | None => tiktoken_rs::o200k_base_singleton(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_the_chat_format() {
+        let text = "hello world";
+        let messages = vec![ChatMessage::user(text)];
+
+        assert_eq!(count_tokens(text, &ModelId::Gpt4), 2);
+        assert_eq!(
+            count_tokens(&messages, &ModelId::Gpt4),
+            TOKENS_PER_MESSAGE
+                + "user".count_tokens_with(encoding(&ModelId::Gpt4))
+                + 2
+                + TOKENS_PER_REPLY
+        );
+    }
+
+    #[test]
+    fn offsets_fall_on_character_boundaries() {
+        let text = "Crabs 🦀 say 你好 in façades";
+
+        for model in [ModelId::Gpt4, ModelId::Gpt4o, ModelId::TextDavinci003] {
+            let offsets = token_offsets(text, &model);
+
+            assert_eq!(offsets.first(), Some(&0));
+            assert_eq!(offsets.last(), Some(&text.len()));
+            assert!(offsets.windows(2).all(|pair| pair[0] <= pair[1]));
+            assert!(offsets.iter().all(|&offset| text.is_char_boundary(offset)));
+        }
+    }
+
+    #[test]
+    fn truncates_to_the_tokens() {
+        let text = "The quick brown fox jumps over the lazy dog 🦀🦀🦀";
+        let total = count_tokens(text, &ModelId::Gpt4o);
+
+        for max_tokens in 0..=total + 1 {
+            let truncated = truncate(text, max_tokens, &ModelId::Gpt4o);
+
+            assert!(text.starts_with(truncated));
+            assert!(count_tokens(truncated, &ModelId::Gpt4o) <= max_tokens);
+        }
+
+        assert_eq!(truncate(text, total, &ModelId::Gpt4o), text);
+        assert_eq!(truncate(text, 0, &ModelId::Gpt4o), "");
+    }
+
+    #[test]
+    fn word_tokens_are_the_first_of_each_variant() {
+        let model = ModelId::Gpt4o;
+        let word = "antidisestablishmentarianism";
+        let encoding = encoding(&model);
+        let first = |text: &str| encoding.encode_ordinary(text)[0];
+
+        assert!(encoding.encode_ordinary(word).len() > 1);
+        assert_eq!(
+            word_tokens(word, &model),
+            BTreeSet::from([first(word), first(&format!(" {word}"))])
+        );
+    }
+}
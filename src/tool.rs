@@ -0,0 +1,264 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
+
+use crate::error;
+
+pub use macros::OpenAiTool;
+
+pub mod partial_json;
+pub mod runner;
+
+/// A type that can describe itself with a JSON Schema, used for the `parameters` of a tool.
+///
+/// Implemented for the primitive types, collections of them and anything that derives
+/// [`OpenAiTool`](macro@OpenAiTool).
+pub trait ToolParameter {
+    fn schema() -> serde_json::Value;
+}
+
+/// A function the model can call, derived with `#[derive(OpenAiTool)]`.
+///
+/// The struct fields become the function parameters, its doc comment the description and its
+/// name (converted to snake_case, or overridden with `#[tool(name("..."))]`) the function name.
+/// The parameters are named as serde deserializes them, following its `rename` and `rename_all`
+/// attributes.
+///
+/// With `#[tool(strict)]` the model follows the schema exactly. Strict mode needs every parameter
+/// to be required, so the `Option` ones are required but accept `null`.
+pub trait OpenAiTool: ToolParameter + DeserializeOwned {
+    const NAME: &'static str;
+    const DESCRIPTION: Option<&'static str>;
+    const STRICT: bool = false;
+
+    /// Entry for the `tools` list of a [`crate::request::ChatCompletionRequest`].
+    fn tool() -> Tool {
+        Tool::function(FunctionDefinition {
+            name: Self::NAME.to_string(),
+            description: Self::DESCRIPTION.map(ToString::to_string),
+            parameters: Self::schema(),
+            strict: Self::STRICT.then_some(true),
+        })
+    }
+
+    /// Parses the `arguments` string the model returned for this function.
+    fn from_arguments(arguments: impl AsRef<str>) -> error::Result<Self> {
+        Ok(serde_json::from_str(arguments.as_ref())?)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tool {
+    #[serde(rename = "type")]
+    pub kind: ToolKind,
+    pub function: FunctionDefinition,
+}
+
+impl Tool {
+    pub fn function(function: FunctionDefinition) -> Self {
+        Self {
+            kind: ToolKind::Function,
+            function,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ToolKind {
+    #[serde(rename = "function")]
+    Function,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionDefinition {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub parameters: serde_json::Value,
+    /// Whether the arguments must follow `parameters` exactly. Only a subset of JSON Schema is
+    /// supported in strict mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+/// `schema` also accepting `null`, for the optional parameters of strict tools.
+pub fn nullable(mut schema: serde_json::Value) -> serde_json::Value {
+    match schema.get_mut("type") {
+        Some(serde_json::Value::String(ty)) => {
+            let ty = std::mem::take(ty);
+
+            schema["type"] = json!([ty, "null"]);
+
+            if let Some(serde_json::Value::Array(values)) = schema.get_mut("enum") {
+                values.push(serde_json::Value::Null);
+            }
+
+            schema
+        }
+        _ => json!({ "anyOf": [schema, { "type": "null" }] }),
+    }
+}
+
+macro_rules! tool_parameter {
+    ($json_type:literal [$($ty:ty),* $(,)*]) => {
+        $(
+            impl ToolParameter for $ty {
+                fn schema() -> serde_json::Value {
+                    json!({ "type": $json_type })
+                }
+            }
+        )*
+    };
+}
+
+tool_parameter!("string"[String, &str, char]);
+tool_parameter!("integer"[i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize]);
+tool_parameter!("number"[f32, f64]);
+tool_parameter!("boolean"[bool]);
+
+impl<T: ToolParameter> ToolParameter for Option<T> {
+    fn schema() -> serde_json::Value {
+        T::schema()
+    }
+}
+
+impl<T: ToolParameter> ToolParameter for Vec<T> {
+    fn schema() -> serde_json::Value {
+        json!({ "type": "array", "items": T::schema() })
+    }
+}
+
+impl<T: ToolParameter> ToolParameter for BTreeMap<String, T> {
+    fn schema() -> serde_json::Value {
+        json!({ "type": "object", "additionalProperties": T::schema() })
+    }
+}
+
+impl<T: ToolParameter> ToolParameter for HashMap<String, T> {
+    fn schema() -> serde_json::Value {
+        json!({ "type": "object", "additionalProperties": T::schema() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// Looks up the weather.
+    #[derive(Deserialize, OpenAiTool)]
+    #[serde(rename_all = "camelCase")]
+    #[allow(dead_code)]
+    struct GetWeather {
+        /// Name of the city.
+        city_name: String,
+        #[serde(rename = "units")]
+        unit: Option<Unit>,
+        #[serde(skip)]
+        cache_hit: bool,
+    }
+
+    #[derive(Deserialize, OpenAiTool)]
+    #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+    enum Unit {
+        DegreesCelsius,
+        #[serde(rename = "f")]
+        Fahrenheit,
+    }
+
+    #[derive(Deserialize, OpenAiTool)]
+    #[tool(strict, name("search"))]
+    #[allow(dead_code)]
+    struct Search {
+        query: String,
+        limit: Option<u32>,
+        unit: Option<Unit>,
+    }
+
+    #[derive(Deserialize, OpenAiTool)]
+    struct Filter {
+        r#type: String,
+        r#in: Option<Vec<String>>,
+    }
+
+    #[test]
+    fn properties_are_named_like_serde() {
+        assert_eq!(
+            GetWeather::schema(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "cityName": { "type": "string", "description": "Name of the city." },
+                    "units": { "type": "string", "enum": ["DEGREES_CELSIUS", "f"] },
+                },
+                "required": ["cityName"],
+                "additionalProperties": false,
+            })
+        );
+
+        let weather = GetWeather::from_arguments(r#"{"cityName": "Paris", "units": "f"}"#).unwrap();
+
+        assert_eq!(weather.city_name, "Paris");
+        assert!(matches!(weather.unit, Some(Unit::Fahrenheit)));
+    }
+
+    #[test]
+    fn raw_identifiers_are_named_without_their_prefix() {
+        let schema = Filter::schema();
+
+        assert_eq!(
+            schema["properties"]
+                .as_object()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>(),
+            ["in", "type"]
+        );
+        assert_eq!(schema["required"], json!(["type"]));
+
+        let filter = Filter::from_arguments(r#"{"type": "file", "in": ["src"]}"#).unwrap();
+
+        assert_eq!(filter.r#type, "file");
+        assert_eq!(filter.r#in, Some(vec!["src".to_string()]));
+    }
+
+    #[test]
+    fn strict_tools_require_every_field() {
+        let tool = Search::tool();
+
+        assert_eq!(tool.function.name, "search");
+        assert_eq!(tool.function.strict, Some(true));
+        assert_eq!(
+            tool.function.parameters,
+            json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string" },
+                    "limit": { "type": ["integer", "null"] },
+                    "unit": {
+                        "type": ["string", "null"],
+                        "enum": ["DEGREES_CELSIUS", "f", null],
+                    },
+                },
+                "required": ["query", "limit", "unit"],
+                "additionalProperties": false,
+            })
+        );
+        assert_eq!(GetWeather::tool().function.strict, None);
+    }
+
+    #[test]
+    fn nullable_wraps_schemas_without_a_single_type() {
+        assert_eq!(
+            nullable(json!({ "anyOf": [{ "type": "string" }, { "type": "integer" }] })),
+            json!({
+                "anyOf": [
+                    { "anyOf": [{ "type": "string" }, { "type": "integer" }] },
+                    { "type": "null" },
+                ],
+            })
+        );
+    }
+}
@@ -0,0 +1,299 @@
+use serde::de::DeserializeOwned;
+
+/// Incremental parser for JSON arriving in fragments, like the arguments of a streamed tool call.
+///
+/// Only the values that are fully received are kept: the open objects and arrays are closed
+/// right after their last complete value, while a string or number still being received is left
+/// out until it ends. A struct can then be parsed as soon as all its required fields arrived,
+/// which allows to start executing a tool before its whole call is received.
+#[derive(Debug, Clone, Default)]
+pub struct PartialJson {
+    buffer: String,
+    stack: Vec<Container>,
+    in_string: bool,
+    escaped: bool,
+    in_literal: bool,
+    /// End of the longest prefix that is valid once closed, and the closers it needs.
+    safe_end: usize,
+    safe_closers: String,
+    complete: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Container {
+    Object(ObjectState),
+    Array,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ObjectState {
+    Key,
+    Colon,
+    Value,
+    CommaOrEnd,
+}
+
+impl PartialJson {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a fragment and advances the parser over it.
+    pub fn push(&mut self, fragment: impl AsRef<str>) {
+        let start = self.buffer.len();
+
+        self.buffer.push_str(fragment.as_ref());
+
+        let chars = self.buffer[start..]
+            .char_indices()
+            .map(|(index, c)| (start + index, c))
+            .collect::<Vec<_>>();
+
+        chars
+            .into_iter()
+            .for_each(|(index, c)| self.advance(index, c));
+    }
+
+    /// Everything received so far.
+    pub fn buffer(&self) -> &str {
+        &self.buffer
+    }
+
+    /// Whether the top-level value was fully received.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// The received JSON, cut after its last complete value and closed. [None] until the
+    /// top-level object or array is opened.
+    pub fn completed(&self) -> Option<String> {
+        (self.safe_end > 0)
+            .then(|| format!("{}{}", &self.buffer[..self.safe_end], self.safe_closers))
+    }
+
+    /// The complete values received so far.
+    pub fn value(&self) -> Option<serde_json::Value> {
+        self.parse()
+    }
+
+    /// Parses the complete values received so far into `T`, [None] while they aren't enough.
+    pub fn parse<T: DeserializeOwned>(&self) -> Option<T> {
+        serde_json::from_str(&self.completed()?).ok()
+    }
+
+    fn advance(&mut self, index: usize, c: char) {
+        if self.in_string {
+            if self.escaped {
+                self.escaped = false;
+            } else if c == '\\' {
+                self.escaped = true;
+            } else if c == '"' {
+                self.in_string = false;
+
+                match self.stack.last_mut() {
+                    Some(Container::Object(state @ ObjectState::Key)) => {
+                        *state = ObjectState::Colon
+                    }
+                    _ => self.value_done(index + c.len_utf8()),
+                }
+            }
+
+            return;
+        }
+
+        if self.in_literal {
+            if c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.') {
+                return;
+            }
+
+            self.in_literal = false;
+            self.value_done(index);
+        }
+
+        match c {
+            '"' => self.in_string = true,
+            '{' => {
+                self.stack.push(Container::Object(ObjectState::Key));
+                self.mark_safe(index + 1);
+            }
+            '[' => {
+                self.stack.push(Container::Array);
+                self.mark_safe(index + 1);
+            }
+            '}' | ']' => {
+                self.stack.pop();
+                self.value_done(index + 1);
+            }
+            ':' => {
+                if let Some(Container::Object(state)) = self.stack.last_mut() {
+                    *state = ObjectState::Value;
+                }
+            }
+            ',' => {
+                if let Some(Container::Object(state)) = self.stack.last_mut() {
+                    *state = ObjectState::Key;
+                }
+            }
+            c if c.is_whitespace() => {}
+            _ => self.in_literal = true,
+        }
+    }
+
+    fn value_done(&mut self, end: usize) {
+        match self.stack.last_mut() {
+            Some(Container::Object(state)) => *state = ObjectState::CommaOrEnd,
+            Some(Container::Array) => {}
+            None => self.complete = true,
+        }
+
+        self.mark_safe(end);
+    }
+
+    fn mark_safe(&mut self, end: usize) {
+        self.safe_end = end;
+        self.safe_closers = self
+            .stack
+            .iter()
+            .rev()
+            .map(|container| match container {
+                Container::Object(_) => '}',
+                Container::Array => ']',
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+
+    fn pushed(fragments: &[&str]) -> PartialJson {
+        let mut json = PartialJson::new();
+
+        fragments.iter().for_each(|fragment| json.push(fragment));
+
+        json
+    }
+
+    #[test]
+    fn nothing_is_completed_before_the_top_level_value_opens() {
+        assert_eq!(PartialJson::new().completed(), None);
+        assert_eq!(pushed(&["  "]).completed(), None);
+        assert_eq!(pushed(&[" {"]).completed().as_deref(), Some(" {}"));
+        assert_eq!(pushed(&["["]).value(), Some(json!([])));
+    }
+
+    #[test]
+    fn strings_are_kept_once_closed() {
+        let mut json = pushed(&[r#"{"path": "src/ma"#]);
+
+        assert_eq!(json.value(), Some(json!({})));
+
+        json.push(r#"in.rs", "#);
+
+        assert_eq!(json.value(), Some(json!({ "path": "src/main.rs" })));
+    }
+
+    #[test]
+    fn escapes_split_across_fragments() {
+        let json = pushed(&[r#"{"quote": "say \"#, r#""hi\"#, r#"""#]);
+
+        assert_eq!(json.value(), Some(json!({})));
+
+        let json = pushed(&[r#"{"quote": "say \"#, r#""hi\"#, r#"""#, r#"", "#]);
+
+        assert_eq!(json.value(), Some(json!({ "quote": "say \"hi\"" })));
+
+        let json = pushed(&[r#"{"path": "C:\\"#, r#"", "done": true}"#]);
+
+        assert_eq!(json.value(), Some(json!({ "path": "C:\\", "done": true })));
+        assert!(json.is_complete());
+    }
+
+    #[test]
+    fn multibyte_characters_pushed_one_by_one() {
+        let text = r#"{"crab": "🦀 größer", "n": 1}"#;
+        let mut json = PartialJson::new();
+
+        for c in text.chars() {
+            json.push(c.to_string());
+
+            if let Some(completed) = json.completed() {
+                assert!(serde_json::from_str::<serde_json::Value>(&completed).is_ok());
+            }
+        }
+
+        assert_eq!(json.buffer(), text);
+        assert_eq!(json.value(), Some(json!({ "crab": "🦀 größer", "n": 1 })));
+    }
+
+    #[test]
+    fn literals_are_left_out_until_they_end() {
+        let mut json = pushed(&[r#"{"a": 12"#]);
+
+        assert_eq!(json.value(), Some(json!({})));
+
+        json.push("3, \"b\": tr");
+
+        assert_eq!(json.value(), Some(json!({ "a": 123 })));
+
+        json.push("ue}");
+
+        assert_eq!(json.value(), Some(json!({ "a": 123, "b": true })));
+        assert!(json.is_complete());
+    }
+
+    #[test]
+    fn nested_containers_are_closed() {
+        let json = pushed(&[r#"{"rows": [[1, 2], [3, "#]);
+
+        assert_eq!(
+            json.completed().as_deref(),
+            Some(r#"{"rows": [[1, 2], [3]]}"#)
+        );
+        assert!(!json.is_complete());
+
+        let json = pushed(&[r#"[{"a": {"b": [null, {"c": false}"#]);
+
+        assert_eq!(
+            json.value(),
+            Some(json!([{ "a": { "b": [null, { "c": false }] } }]))
+        );
+    }
+
+    #[test]
+    fn parses_once_the_required_fields_arrived() {
+        #[derive(Debug, Clone, Deserialize, PartialEq)]
+        struct Move {
+            from: String,
+            to: String,
+            #[serde(default)]
+            force: bool,
+        }
+
+        let mut json = PartialJson::new();
+        let mut parsed = Vec::new();
+
+        for fragment in [
+            r#"{"fr"#,
+            r#"om": "a.txt", "#,
+            r#""to": "b"#,
+            r#".txt""#,
+            "}",
+        ] {
+            json.push(fragment);
+            parsed.push(json.parse::<Move>());
+        }
+
+        let moved = Move {
+            from: "a.txt".to_string(),
+            to: "b.txt".to_string(),
+            force: false,
+        };
+
+        assert_eq!(parsed, [None, None, None, Some(moved.clone()), Some(moved)]);
+    }
+}
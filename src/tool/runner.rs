@@ -0,0 +1,434 @@
+use std::{collections::BTreeMap, future::Future, pin::Pin, sync::Arc, time::Duration};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    error,
+    model::Model,
+    request::{
+        chat_completion::{ChatCompletionResponse, ChatMessage},
+        ChatCompletionRequest, Request,
+    },
+    tool::{FunctionDefinition, OpenAiTool, Tool},
+};
+
+/// Future returned by a tool handler, resolving to the content of the `tool` message.
+pub type HandlerFuture = Pin<Box<dyn Future<Output = error::Result<String>> + Send>>;
+
+/// Handles a call to a tool, given the raw JSON `arguments` generated by the model.
+pub type Handler = Arc<dyn Fn(String) -> HandlerFuture + Send + Sync>;
+
+/// Sends chat requests with the registered tools, dispatches the tool calls the model makes to
+/// their handlers and sends the results back, until the model gives a final answer.
+#[derive(Clone)]
+pub struct ToolRunner {
+    tools: BTreeMap<String, (Tool, Handler)>,
+    max_iterations: usize,
+    timeout: Option<Duration>,
+}
+
+/// Final answer of a [`ToolRunner::run`], with the whole conversation that led to it.
+#[derive(Debug)]
+pub struct ToolRunOutput {
+    /// The initial messages, followed by the assistant tool calls, the tool results and the final
+    /// assistant message.
+    pub messages: Vec<ChatMessage>,
+    /// The response containing the final answer.
+    pub response: ChatCompletionResponse,
+}
+
+impl Default for ToolRunner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ToolRunner {
+    pub const DEFAULT_MAX_ITERATIONS: usize = 10;
+
+    pub fn new() -> Self {
+        Self {
+            tools: BTreeMap::new(),
+            max_iterations: Self::DEFAULT_MAX_ITERATIONS,
+            timeout: None,
+        }
+    }
+
+    /// Maximum number of chat requests sent by a single run.
+    pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Maximum duration of a single run, requests and handlers included.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Registers a tool with a handler receiving the raw JSON arguments.
+    pub fn register_raw<F, Fut>(mut self, tool: Tool, handler: F) -> Self
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = error::Result<String>> + Send + 'static,
+    {
+        let handler: Handler = Arc::new(move |arguments| Box::pin(handler(arguments)));
+
+        self.tools
+            .insert(tool.function.name.clone(), (tool, handler));
+        self
+    }
+
+    /// Registers a function tool with a typed handler. The arguments are deserialized into `A`
+    /// and the result serialized back into the `tool` message (strings are sent as they are).
+    /// Arguments that don't deserialize are answered with the error, for the model to retry.
+    pub fn register<A, R, F, Fut>(
+        self,
+        name: impl AsRef<str>,
+        parameters: serde_json::Value,
+        handler: F,
+    ) -> Self
+    where
+        A: DeserializeOwned,
+        R: Serialize,
+        F: Fn(A) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = error::Result<R>> + Send + 'static,
+    {
+        let tool = Tool::function(FunctionDefinition {
+            name: name.as_ref().to_string(),
+            description: None,
+            parameters,
+            strict: None,
+        });
+
+        self.register_typed(tool, handler)
+    }
+
+    /// Registers an [`OpenAiTool`], its handler receiving the parsed arguments. Like for
+    /// [`ToolRunner::register`], arguments that don't parse are answered with the error.
+    pub fn register_tool<T, R, F, Fut>(self, handler: F) -> Self
+    where
+        T: OpenAiTool,
+        R: Serialize,
+        F: Fn(T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = error::Result<R>> + Send + 'static,
+    {
+        self.register_typed(T::tool(), handler)
+    }
+
+    fn register_typed<A, R, F, Fut>(self, tool: Tool, handler: F) -> Self
+    where
+        A: DeserializeOwned,
+        R: Serialize,
+        F: Fn(A) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = error::Result<R>> + Send + 'static,
+    {
+        self.register_raw(tool, move |arguments| {
+            // Sent back instead of ending the run, so the model can fix its call
+            let result = serde_json::from_str::<A>(&arguments)
+                .map(&handler)
+                .map_err(|e| format!("Invalid arguments: {e}"));
+
+            async move {
+                let value = match result {
+                    Ok(result) => serde_json::to_value(result.await?)?,
+                    Err(error) => return Ok(error),
+                };
+
+                Ok(match value {
+                    serde_json::Value::String(content) => content,
+                    value => value.to_string(),
+                })
+            }
+        })
+    }
+
+    /// The registered tools, as sent in the `tools` of the requests.
+    pub fn tools(&self) -> Vec<Tool> {
+        self.tools.values().map(|(tool, _)| tool.clone()).collect()
+    }
+
+    /// Runs the conversation until the model answers without calling a tool.
+    ///
+    /// `configure` is applied to every request before the tools are set, to set the other
+    /// parameters (temperature, max_tokens, ...).
+    pub async fn run<'model, 'client, C>(
+        &self,
+        model: &'model Model<'client>,
+        messages: Vec<ChatMessage>,
+        configure: C,
+    ) -> error::Result<ToolRunOutput>
+    where
+        C: Fn(ChatCompletionRequest<'model, 'client>) -> ChatCompletionRequest<'model, 'client>,
+    {
+        match self.timeout {
+            Some(timeout) => {
+                tokio::time::timeout(timeout, self.run_loop(model, messages, configure))
+                    .await
+                    .map_err(|_| error::ToolError::Timeout(timeout))?
+            }
+            None => self.run_loop(model, messages, configure).await,
+        }
+    }
+
+    async fn run_loop<'model, 'client, C>(
+        &self,
+        model: &'model Model<'client>,
+        mut messages: Vec<ChatMessage>,
+        configure: C,
+    ) -> error::Result<ToolRunOutput>
+    where
+        C: Fn(ChatCompletionRequest<'model, 'client>) -> ChatCompletionRequest<'model, 'client>,
+    {
+        let tools = self.tools();
+
+        for _ in 0..self.max_iterations {
+            let request = configure(ChatCompletionRequest::init(model, messages.clone()))
+                .with_tools(tools.clone());
+            let response = request.execute().await?;
+
+            let message = response
+                .first_message()
+                .ok_or(error::ToolError::NoChoices)?
+                .clone();
+            let tool_calls = message.tool_calls.clone().unwrap_or_default();
+
+            messages.push(message);
+
+            if tool_calls.is_empty() {
+                return Ok(ToolRunOutput { messages, response });
+            }
+
+            for tool_call in tool_calls {
+                let (_, handler) = self.tools.get(&tool_call.function.name).ok_or_else(|| {
+                    error::ToolError::UnknownTool(tool_call.function.name.clone())
+                })?;
+                let result = handler(tool_call.function.arguments).await?;
+
+                messages.push(ChatMessage::tool(tool_call.id, result));
+            }
+        }
+
+        Err(error::ToolError::MaxIterationsReached(self.max_iterations).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        client::Client,
+        model::ModelId,
+        test_util::{
+            self, chat_completions, fixtures, json_response,
+            wiremock::{matchers::body_string_contains, MockServer},
+        },
+        tool::OpenAiTool,
+    };
+
+    async fn mock_tool_call(server: &MockServer, name: &str, arguments: serde_json::Value) {
+        chat_completions()
+            .respond_with(json_response(fixtures::chat_completion_tool_call(
+                name, arguments,
+            )))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    async fn run(runner: &ToolRunner, client: &Client) -> error::Result<ToolRunOutput> {
+        let model = client.model_from_id(ModelId::Gpt4oMini);
+
+        runner
+            .run(&model, vec![ChatMessage::user("Weather?")], |request| {
+                request
+            })
+            .await
+    }
+
+    fn tool_error(result: error::Result<ToolRunOutput>) -> error::ToolError {
+        match result {
+            Err(error::Error::ToolError(e)) => match e.downcast::<error::ToolError>() {
+                Ok(e) => *e,
+                Err(e) => panic!("expected a tool error, got {e:?}"),
+            },
+            result => panic!("expected a tool error, got {result:?}"),
+        }
+    }
+
+    fn weather() -> ToolRunner {
+        ToolRunner::new().register_raw(
+            Tool::function(FunctionDefinition {
+                name: "weather".to_string(),
+                description: None,
+                parameters: json!({ "type": "object" }),
+                strict: None,
+            }),
+            |_| async { Ok("Sunny".to_string()) },
+        )
+    }
+
+    #[tokio::test]
+    async fn sends_the_tool_results_back_until_the_final_answer() {
+        let server = MockServer::start().await;
+
+        mock_tool_call(&server, "weather", json!({})).await;
+        chat_completions()
+            .and(body_string_contains("Sunny"))
+            .respond_with(json_response(fixtures::chat_completion("It's sunny")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let output = run(&weather(), &test_util::client(&server)).await.unwrap();
+
+        assert_eq!(output.messages.len(), 4);
+        assert_eq!(
+            output.messages[2].tool_call_id.as_deref(),
+            Some("call_test")
+        );
+        assert_eq!(output.messages[2].content.text(), "Sunny");
+        assert_eq!(output.response.first_text().as_deref(), Some("It's sunny"));
+    }
+
+    #[tokio::test]
+    async fn stops_after_max_iterations() {
+        let server = MockServer::start().await;
+
+        chat_completions()
+            .respond_with(json_response(fixtures::chat_completion_tool_call(
+                "weather",
+                json!({}),
+            )))
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let result = run(&weather().max_iterations(2), &test_util::client(&server)).await;
+
+        assert!(matches!(
+            tool_error(result),
+            error::ToolError::MaxIterationsReached(2)
+        ));
+    }
+
+    #[tokio::test]
+    async fn stops_after_the_timeout() {
+        let server = MockServer::start().await;
+
+        chat_completions()
+            .respond_with(
+                json_response(fixtures::chat_completion("It's sunny"))
+                    .set_delay(Duration::from_millis(500)),
+            )
+            .mount(&server)
+            .await;
+
+        let timeout = Duration::from_millis(50);
+        let result = run(&weather().timeout(timeout), &test_util::client(&server)).await;
+
+        assert!(matches!(tool_error(result), error::ToolError::Timeout(t) if t == timeout));
+    }
+
+    #[tokio::test]
+    async fn calls_to_unknown_tools_are_errors() {
+        let server = MockServer::start().await;
+
+        mock_tool_call(&server, "forecast", json!({})).await;
+
+        let result = run(&weather(), &test_util::client(&server)).await;
+
+        assert!(matches!(
+            tool_error(result),
+            error::ToolError::UnknownTool(name) if name == "forecast"
+        ));
+    }
+
+    #[tokio::test]
+    async fn malformed_arguments_are_sent_back_to_the_model() {
+        #[derive(Deserialize)]
+        struct Weather {
+            city: String,
+        }
+
+        let server = MockServer::start().await;
+
+        mock_tool_call(&server, "weather", json!({ "city": 75 })).await;
+        chat_completions()
+            .and(body_string_contains("Invalid arguments"))
+            .respond_with(json_response(fixtures::chat_completion("Which city?")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let runner = ToolRunner::new().register(
+            "weather",
+            json!({ "type": "object" }),
+            |weather: Weather| async move { Ok(weather.city) },
+        );
+        let output = run(&runner, &test_util::client(&server)).await.unwrap();
+
+        assert_eq!(output.response.first_text().as_deref(), Some("Which city?"));
+    }
+
+    #[tokio::test]
+    async fn typed_handlers_get_the_arguments_and_serialize_their_results() {
+        #[derive(Deserialize)]
+        struct Weather {
+            city: String,
+        }
+
+        #[derive(Serialize)]
+        struct Forecast {
+            city: String,
+            temperature: i32,
+        }
+
+        let server = MockServer::start().await;
+
+        mock_tool_call(&server, "weather", json!({ "city": "Paris" })).await;
+        test_util::mock_chat_completion(&server, "It's warm").await;
+
+        let runner = ToolRunner::new().register(
+            "weather",
+            json!({ "type": "object" }),
+            |weather: Weather| async move {
+                Ok(Forecast {
+                    city: weather.city,
+                    temperature: 21,
+                })
+            },
+        );
+        let output = run(&runner, &test_util::client(&server)).await.unwrap();
+
+        assert_eq!(
+            output.messages[2].content.text(),
+            r#"{"city":"Paris","temperature":21}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn string_results_are_sent_as_they_are() {
+        /// Current weather of a city.
+        #[derive(Deserialize, OpenAiTool)]
+        struct GetWeather {
+            city: String,
+        }
+
+        let server = MockServer::start().await;
+
+        mock_tool_call(&server, "get_weather", json!({ "city": "Paris" })).await;
+        test_util::mock_chat_completion(&server, "It's sunny").await;
+
+        let runner = ToolRunner::new().register_tool(|weather: GetWeather| async move {
+            Ok(format!("Sunny in {}", weather.city))
+        });
+        let output = run(&runner, &test_util::client(&server)).await.unwrap();
+
+        assert_eq!(output.messages[2].content.text(), "Sunny in Paris");
+    }
+}
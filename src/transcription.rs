@@ -0,0 +1,182 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::Client,
+    error,
+    model::{Model, ModelId},
+    APIKeysAccess,
+};
+
+pub const TRANSCRIPTIONS_URL: &str = "https://api.openai.com/v1/audio/transcriptions";
+
+/// Transcribes an audio file (`mp3`, `mp4`, `mpeg`, `mpga`, `m4a`, `wav` or `webm`) into the
+/// text of its language.
+#[derive(Debug, Clone)]
+pub struct TranscriptionRequest {
+    model: ModelId,
+    file_name: String,
+    file: Vec<u8>,
+    language: Option<String>,
+    prompt: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transcription {
+    pub text: String,
+}
+
+impl TranscriptionRequest {
+    /// The audio `file`, whose format the API reads from the extension of `file_name`.
+    pub fn new(model: impl Into<ModelId>, file_name: impl AsRef<str>, file: Vec<u8>) -> Self {
+        Self {
+            model: model.into(),
+            file_name: file_name.as_ref().to_string(),
+            file,
+            language: None,
+            prompt: None,
+        }
+    }
+
+    pub fn from_path(model: impl Into<ModelId>, path: impl AsRef<Path>) -> error::Result<Self> {
+        let path = path.as_ref();
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        Ok(Self::new(model, file_name, std::fs::read(path)?))
+    }
+
+    /// Language of the audio as an ISO-639-1 code (`en`, `fr`, ...), which improves accuracy and
+    /// latency.
+    pub fn with_language(mut self, language: impl AsRef<str>) -> Self {
+        self.language = Some(language.as_ref().to_string());
+        self
+    }
+
+    /// Text guiding the style of the transcription or continuing a previous audio segment, in
+    /// the language of the audio.
+    pub fn with_prompt(mut self, prompt: impl AsRef<str>) -> Self {
+        self.prompt = Some(prompt.as_ref().to_string());
+        self
+    }
+
+    pub async fn execute(&self, client: &Client) -> error::Result<Transcription> {
+        self.check_model(client)?;
+
+        let file =
+            reqwest::multipart::Part::bytes(self.file.clone()).file_name(self.file_name.clone());
+        let form = self
+            .fields()
+            .fold(reqwest::multipart::Form::new(), |form, (name, value)| {
+                form.text(name, value)
+            })
+            .part("file", file);
+        let request = client
+            .async_client()
+            .post(client.resolve_url(TRANSCRIPTIONS_URL)?)
+            .headers(client.common_headers())
+            .multipart(form);
+        let body = client.send(request).await?.bytes().await?;
+
+        Ok(client.parse(&body)?)
+    }
+
+    /// (Blocking) Same as [`Self::execute`].
+    #[cfg(feature = "blocking")]
+    pub fn execute_blocking(&self, client: &Client) -> error::Result<Transcription> {
+        self.check_model(client)?;
+
+        let file = reqwest::blocking::multipart::Part::bytes(self.file.clone())
+            .file_name(self.file_name.clone());
+        let form = self
+            .fields()
+            .fold(
+                reqwest::blocking::multipart::Form::new(),
+                |form, (name, value)| form.text(name, value),
+            )
+            .part("file", file);
+        let request = client
+            .blocking_client()?
+            .post(client.resolve_url(TRANSCRIPTIONS_URL)?)
+            .headers(client.common_headers())
+            .multipart(form);
+        let body = client.send_blocking(request)?.bytes()?;
+
+        Ok(client.parse(&body)?)
+    }
+
+    fn check_model(&self, client: &Client) -> Result<(), error::ModelError> {
+        if client.compatibility().is_compatible(
+            TRANSCRIPTIONS_URL,
+            Model::AUDIO_TRANSCRIPTIONS,
+            &self.model,
+        ) {
+            return Ok(());
+        }
+
+        Err(error::ModelError::NotCompatible {
+            url: TRANSCRIPTIONS_URL,
+            compatible_models: Model::AUDIO_TRANSCRIPTIONS,
+        })
+    }
+
+    /// The text fields of the form, the file being sent after them.
+    fn fields(&self) -> impl Iterator<Item = (&'static str, String)> + '_ {
+        [
+            ("model", Some(self.model.as_str())),
+            ("language", self.language.as_deref()),
+            ("prompt", self.prompt.as_deref()),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value?.to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::test_util::{
+        self, json_response,
+        wiremock::{
+            matchers::{header_regex, method, path},
+            Mock, MockServer,
+        },
+    };
+
+    #[tokio::test]
+    async fn uploads_the_audio_as_a_form() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/audio/transcriptions"))
+            .and(header_regex(
+                "content-type",
+                "^multipart/form-data; boundary=",
+            ))
+            .respond_with(json_response(json!({ "text": "Hello, crab" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let transcription =
+            TranscriptionRequest::new(ModelId::Whisper1, "hello.wav", b"RIFF".to_vec())
+                .with_language("en")
+                .execute(&test_util::client(&server))
+                .await
+                .unwrap();
+        let requests = server.received_requests().await.unwrap();
+        let body = String::from_utf8_lossy(&requests[0].body);
+
+        assert_eq!(transcription.text, "Hello, crab");
+        assert!(body.contains("name=\"model\"\r\n\r\nwhisper-1\r\n"));
+        assert!(body.contains("name=\"language\"\r\n\r\nen\r\n"));
+        assert!(!body.contains("name=\"prompt\""));
+        assert!(body.contains("name=\"file\"; filename=\"hello.wav\""));
+        assert!(body.contains("RIFF"));
+    }
+}
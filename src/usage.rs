@@ -0,0 +1,123 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use crate::request::Usage;
+
+/// Sums the [`Usage`] of every response of a client, see [`crate::client::Client::usage`].
+#[derive(Debug, Default)]
+pub struct UsageTracker {
+    totals: Mutex<UsageTotals>,
+}
+
+/// The tokens used so far.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UsageTotals {
+    pub total: Usage,
+    /// Number of responses with a usage.
+    pub responses: u64,
+    /// By model, as named in the responses (`gpt-4o-2024-08-06`, ...).
+    pub by_model: BTreeMap<String, Usage>,
+    /// By the tag of the requests, see `with_usage_tag`.
+    pub by_tag: BTreeMap<String, Usage>,
+}
+
+impl UsageTracker {
+    pub(crate) fn record(&self, usage: Usage, model: Option<&str>, tag: Option<&str>) {
+        let mut totals = self.totals();
+
+        totals.total += usage;
+        totals.responses += 1;
+
+        if let Some(model) = model {
+            *totals.by_model.entry(model.to_string()).or_default() += usage;
+        }
+
+        if let Some(tag) = tag {
+            *totals.by_tag.entry(tag.to_string()).or_default() += usage;
+        }
+    }
+
+    pub fn snapshot(&self) -> UsageTotals {
+        self.totals().clone()
+    }
+
+    pub fn reset(&self) {
+        *self.totals() = UsageTotals::default();
+    }
+
+    fn totals(&self) -> std::sync::MutexGuard<'_, UsageTotals> {
+        self.totals.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        model::ModelId,
+        request::{
+            chat_completion::ChatMessage, ChatCompletionRequest, Request, TextCompletionRequest,
+        },
+        test_util::{self, fixtures, json_response, wiremock::MockServer},
+    };
+
+    fn usage(prompt_tokens: u64, completion_tokens: u64) -> Usage {
+        Usage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        }
+    }
+
+    #[tokio::test]
+    async fn sums_the_usage_in_total_by_model_and_by_tag() {
+        let server = MockServer::start().await;
+
+        test_util::mock_chat_completion(&server, "Hello").await;
+        test_util::completions()
+            .respond_with(json_response(fixtures::text_completion("Hi")))
+            .mount(&server)
+            .await;
+
+        let client = test_util::client(&server);
+
+        for tag in ["alice", "alice", "bob"] {
+            ChatCompletionRequest::for_model(
+                &client,
+                ModelId::Gpt4oMini,
+                vec![ChatMessage::user("Hi")],
+            )
+            .with_usage_tag(tag)
+            .execute()
+            .await
+            .unwrap();
+        }
+
+        TextCompletionRequest::for_model(&client, ModelId::TextDavinci003)
+            .execute()
+            .await
+            .unwrap();
+
+        let totals = client.usage();
+
+        assert_eq!(totals.total, usage(41, 34));
+        assert_eq!(totals.responses, 4);
+        assert_eq!(
+            totals.by_model,
+            BTreeMap::from([
+                ("gpt-4o-mini".to_string(), usage(36, 27)),
+                ("text-davinci-003".to_string(), usage(5, 7)),
+            ])
+        );
+        assert_eq!(
+            totals.by_tag,
+            BTreeMap::from([
+                ("alice".to_string(), usage(24, 18)),
+                ("bob".to_string(), usage(12, 9)),
+            ])
+        );
+
+        client.reset_usage();
+
+        assert_eq!(client.usage(), UsageTotals::default());
+    }
+}
@@ -0,0 +1,239 @@
+use std::path::Path;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    error,
+    request::embedding::{EmbeddingResponse, EmbeddingVector},
+};
+
+/// Embeddings and what they were computed from (`P`, the payload), searched by cosine
+/// similarity. Searching goes through every entry, which is fast enough up to some tens of
+/// thousands of them.
+///
+/// The embeddings are normalized when they are added, so that their similarity is their dot
+/// product. Saved to and loaded from JSON files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VectorIndex<P> {
+    entries: Vec<IndexEntry<P>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexEntry<P> {
+    pub embedding: EmbeddingVector,
+    pub payload: P,
+}
+
+/// An entry found by [`VectorIndex::search`].
+#[derive(Debug, Clone, Copy)]
+pub struct SearchResult<'index, P> {
+    /// Cosine similarity to the query, from -1 to 1.
+    pub score: f32,
+    /// Position of the entry in the index.
+    pub position: usize,
+    pub payload: &'index P,
+}
+
+impl<P> Default for VectorIndex<P> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<P> VectorIndex<P> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, embedding: impl Into<EmbeddingVector>, payload: P) {
+        self.entries.push(IndexEntry {
+            embedding: embedding.into().normalized(),
+            payload,
+        });
+    }
+
+    /// Adds the embeddings of `response` with the payloads of their inputs, in the order of the
+    /// inputs. Embeddings without a payload are left out.
+    pub fn insert_response(
+        &mut self,
+        response: EmbeddingResponse,
+        payloads: impl IntoIterator<Item = P>,
+    ) {
+        let mut embeddings = response.data;
+
+        embeddings.sort_by_key(|embedding| embedding.index);
+
+        for (embedding, payload) in embeddings.into_iter().zip(payloads) {
+            self.insert(embedding.embedding, payload);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &[IndexEntry<P>] {
+        &self.entries
+    }
+
+    pub fn remove(&mut self, position: usize) -> Option<IndexEntry<P>> {
+        (position < self.entries.len()).then(|| self.entries.remove(position))
+    }
+
+    /// Keeps the entries whose payload matches `keep`.
+    pub fn retain(&mut self, mut keep: impl FnMut(&P) -> bool) {
+        self.entries.retain(|entry| keep(&entry.payload));
+    }
+
+    /// The `k` entries most similar to `query`, the most similar first. Entries of another
+    /// length than the query (from another model) are skipped.
+    pub fn search(&self, query: &EmbeddingVector, k: usize) -> Vec<SearchResult<'_, P>> {
+        let query = query.clone().normalized();
+        let mut results = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.embedding.len() == query.len())
+            .map(|(position, entry)| SearchResult {
+                score: entry.embedding.dot(&query),
+                position,
+                payload: &entry.payload,
+            })
+            .collect::<Vec<_>>();
+
+        results.sort_by(|a, b| b.score.total_cmp(&a.score));
+        results.truncate(k);
+
+        results
+    }
+}
+
+impl<P: Serialize> VectorIndex<P> {
+    pub fn save(&self, path: impl AsRef<Path>) -> error::Result<()> {
+        Ok(std::fs::write(path, serde_json::to_vec(self)?)?)
+    }
+}
+
+impl<P: DeserializeOwned> VectorIndex<P> {
+    pub fn load(path: impl AsRef<Path>) -> error::Result<Self> {
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+}
+
+impl<P> FromIterator<(EmbeddingVector, P)> for VectorIndex<P> {
+    fn from_iter<T: IntoIterator<Item = (EmbeddingVector, P)>>(iter: T) -> Self {
+        let mut index = Self::new();
+
+        index.extend(iter);
+
+        index
+    }
+}
+
+impl<P> Extend<(EmbeddingVector, P)> for VectorIndex<P> {
+    fn extend<T: IntoIterator<Item = (EmbeddingVector, P)>>(&mut self, iter: T) {
+        for (embedding, payload) in iter {
+            self.insert(embedding, payload);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::fixtures;
+
+    fn index() -> VectorIndex<&'static str> {
+        [
+            (vec![1.0, 0.0], "east"),
+            (vec![0.0, 1.0], "north"),
+            (vec![1.0, 1.0], "north-east"),
+            (vec![-1.0, 0.0], "west"),
+        ]
+        .into_iter()
+        .map(|(embedding, payload)| (EmbeddingVector::from(embedding), payload))
+        .collect()
+    }
+
+    fn payloads<P: Copy>(results: &[SearchResult<'_, P>]) -> Vec<P> {
+        results.iter().map(|result| *result.payload).collect()
+    }
+
+    #[test]
+    fn finds_the_k_most_similar_entries_first() {
+        let index = index();
+        let results = index.search(&vec![3.0, 1.0].into(), 3);
+
+        assert_eq!(payloads(&results), ["east", "north-east", "north"]);
+        assert_eq!(results[0].position, 0);
+        assert!(results
+            .windows(2)
+            .all(|pair| pair[0].score >= pair[1].score));
+        assert!((results[0].score - 3.0 / 10f32.sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn skips_the_entries_of_another_length() {
+        let mut index = index();
+
+        index.insert(vec![1.0, 0.0, 0.0], "up");
+
+        assert_eq!(index.len(), 5);
+        assert_eq!(
+            payloads(&index.search(&vec![1.0, 0.0, 0.0].into(), 5)),
+            ["up"]
+        );
+        assert_eq!(index.search(&vec![1.0, 0.0].into(), 5).len(), 4);
+    }
+
+    #[test]
+    fn inserts_responses_in_the_order_of_the_inputs() {
+        let mut response: EmbeddingResponse = serde_json::from_value(fixtures::embedding([
+            vec![1.0, 0.0],
+            vec![0.0, 1.0],
+            vec![-1.0, 0.0],
+        ]))
+        .unwrap();
+
+        response.data.reverse();
+
+        let mut index = VectorIndex::new();
+
+        index.insert_response(response, ["east", "north"]);
+
+        assert_eq!(
+            index
+                .entries()
+                .iter()
+                .map(|entry| (entry.payload, entry.embedding.to_vec()))
+                .collect::<Vec<_>>(),
+            [("east", vec![1.0, 0.0]), ("north", vec![0.0, 1.0])]
+        );
+    }
+
+    #[test]
+    fn saves_and_loads_the_indexes() {
+        let path = std::env::temp_dir().join(format!("vector-index-{}.json", std::process::id()));
+        let index = index();
+
+        index.save(&path).unwrap();
+
+        let loaded = VectorIndex::<String>::load(&path).unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.len(), index.len());
+        assert!(loaded
+            .entries()
+            .iter()
+            .zip(index.entries())
+            .all(|(loaded, entry)| loaded.payload == entry.payload
+                && loaded.embedding == entry.embedding));
+    }
+}